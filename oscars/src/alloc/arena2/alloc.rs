@@ -164,10 +164,19 @@ impl<'arena> ErasedArenaPointer<'arena> {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug)]
 #[repr(transparent)]
 pub struct ArenaPointer<'arena, T>(ErasedArenaPointer<'arena>, PhantomData<&'arena T>);
 
+// manual impls, a derive would require `T: Copy`
+impl<'arena, T> Clone for ArenaPointer<'arena, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'arena, T> Copy for ArenaPointer<'arena, T> {}
+
 impl<'arena, T> ArenaPointer<'arena, T> {
     unsafe fn from_raw(raw: NonNull<ArenaHeapItem<T>>) -> Self {
         Self(
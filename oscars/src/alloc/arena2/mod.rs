@@ -105,6 +105,14 @@ impl<'alloc> ArenaAllocator<'alloc> {
         self.arenas_len() * self.arena_size
     }
 
+    pub fn heap_threshold(&self) -> usize {
+        self.heap_threshold
+    }
+
+    pub fn set_heap_threshold(&mut self, heap_threshold: usize) {
+        self.heap_threshold = heap_threshold;
+    }
+
     pub fn is_below_threshold(&self) -> bool {
         // saturating_sub avoids underflow when heap_threshold < arena_size
         self.heap_size() <= self.heap_threshold.saturating_sub(self.arena_size)
@@ -49,13 +49,6 @@ impl<'pool> ErasedPoolPointer<'pool> {
     pub fn as_non_null(&self) -> NonNull<u8> {
         self.0
     }
-
-    /// extend the lifetime of this erased pool pointer to 'static
-    //
-    // SAFETY: same as PoolPointer::extend_lifetime
-    pub(crate) unsafe fn extend_lifetime(self) -> ErasedPoolPointer<'static> {
-        ErasedPoolPointer(self.0, PhantomData)
-    }
 }
 
 /// typed pointer into a pool slot
@@ -328,6 +321,17 @@ impl SlotPool {
         self.live.set(self.live.get().saturating_sub(1));
     }
 
+    /// returns true if `ptr` is the start of an allocated slot of this pool
+    pub(crate) fn is_live(&self, ptr: NonNull<u8>) -> bool {
+        if !self.owns(ptr) {
+            return false;
+        }
+        let offset = ptr.as_ptr() as usize - self.slot_base() as usize;
+        let idx = offset / self.slot_size;
+        offset.is_multiple_of(self.slot_size)
+            && self.bitmap_chunk(idx).get() & (1u64 << (idx % 64)) != 0
+    }
+
     /// Iterates over all live (allocated) slot pointers in this pool.
     pub(crate) fn iter_live(&self) -> impl Iterator<Item = NonNull<u8>> + '_ {
         (0..self.slot_count).filter_map(move |i| {
@@ -377,6 +381,44 @@ impl Drop for SlotPool {
     }
 }
 
+// ==== LargeObject ==== //
+
+/// dedicated allocation for an object too big for the largest size class
+#[derive(Debug)]
+pub(crate) struct LargeObject {
+    pub(crate) layout: Layout,
+    pub(crate) buffer: NonNull<u8>,
+}
+
+impl LargeObject {
+    pub fn try_init(layout: Layout) -> Result<Self, PoolAllocError> {
+        // keep the pool alignment guarantee for values placed in this object
+        let layout = layout
+            .align_to(16)
+            .map_err(PoolAllocError::LayoutError)?
+            .pad_to_align();
+
+        // SAFETY: allocating with a valid Layout, size is non-zero since every
+        // large object is bigger than the largest size class
+        let buffer = unsafe {
+            let ptr = alloc(layout);
+            let Some(nn) = NonNull::new(ptr) else {
+                handle_alloc_error(layout)
+            };
+            nn
+        };
+
+        Ok(Self { layout, buffer })
+    }
+}
+
+impl Drop for LargeObject {
+    fn drop(&mut self) {
+        // SAFETY: buffer was allocated with the same layout by the global allocator
+        unsafe { dealloc(self.buffer.as_ptr(), self.layout) };
+    }
+}
+
 // ==== BumpPage ==== //
 
 /// pure bump allocator for raw bytes with a linear pointer over a buffer
@@ -1,14 +1,16 @@
 //! size-class memory pool. typed GC objects go into per size class slot pools
-//! where freed slots are recycled via a free list, raw byte allocations use
-//! separate bump pages
+//! where freed slots are recycled via a free list, objects bigger than the
+//! largest size class get a dedicated large object allocation, raw byte
+//! allocations use separate bump pages
 
 use core::{cell::Cell, ptr::NonNull};
 use rust_alloc::alloc::{Layout, LayoutError};
+use rust_alloc::collections::BTreeMap;
 use rust_alloc::vec::Vec;
 
 mod alloc;
 
-use alloc::{BumpPage, SlotPool};
+use alloc::{BumpPage, LargeObject, SlotPool};
 pub use alloc::{ErasedPoolPointer, PoolItem, PoolPointer};
 
 #[cfg(test)]
@@ -29,6 +31,7 @@ impl From<LayoutError> for PoolAllocError {
 }
 
 const SIZE_CLASSES: &[usize] = &[16, 24, 32, 48, 64, 96, 128, 192, 256, 512, 1024, 2048];
+const LARGEST_SIZE_CLASS: usize = SIZE_CLASSES[SIZE_CLASSES.len() - 1];
 
 #[inline(always)]
 fn size_class_index_for(size: usize) -> usize {
@@ -55,6 +58,9 @@ pub struct PoolAllocator<'alloc> {
     pub(crate) slot_pools: Vec<SlotPool>,
     // bump pages for raw byte allocs
     pub(crate) bump_pages: Vec<BumpPage>,
+    // objects bigger than the largest size class, one allocation each, keyed
+    // by the address of their buffer
+    pub(crate) large_objects: BTreeMap<usize, LargeObject>,
     // cached index of the last pool used by free_slot
     pub(crate) free_cache: Cell<usize>,
     // per size class cached index of the last pool used by alloc_slot
@@ -77,6 +83,7 @@ impl<'alloc> Default for PoolAllocator<'alloc> {
             current_heap_size: 0,
             slot_pools: Vec::new(),
             bump_pages: Vec::new(),
+            large_objects: BTreeMap::new(),
             free_cache: Cell::new(usize::MAX),
             alloc_cache: [
                 Cell::new(usize::MAX),
@@ -112,21 +119,66 @@ impl<'alloc> PoolAllocator<'alloc> {
         self
     }
 
-    /// total live slot pool + bump page count
+    /// total live slot pool + bump page + large object count
     pub fn pools_len(&self) -> usize {
-        self.slot_pools.len() + self.bump_pages.len()
+        self.slot_pools.len() + self.bump_pages.len() + self.large_objects.len()
     }
 
     /// exact heap size in bytes
-    fn heap_size(&self) -> usize {
+    pub fn heap_size(&self) -> usize {
         self.current_heap_size
     }
 
+    /// the heap size in bytes at which collection is requested
+    pub fn heap_threshold(&self) -> usize {
+        self.heap_threshold
+    }
+
+    pub fn set_heap_threshold(&mut self, heap_threshold: usize) {
+        self.heap_threshold = heap_threshold;
+    }
+
     /// Iterates over every live slot pointer across all slot pools.
     ///
-    /// Yields one `NonNull<u8>` per allocated (not yet freed) slot.
+    /// Yields one `NonNull<u8>` per allocated (not yet freed) slot, large
+    /// objects included.
     pub fn iter_live_slots(&self) -> impl Iterator<Item = core::ptr::NonNull<u8>> + '_ {
-        self.slot_pools.iter().flat_map(|pool| pool.iter_live())
+        self.slot_pools
+            .iter()
+            .flat_map(|pool| pool.iter_live())
+            .chain(self.large_objects.values().map(|object| object.buffer))
+    }
+
+    /// Returns the slot size of the slot pool or large object owning `ptr`,
+    /// if any.
+    pub fn slot_size_of(&self, ptr: NonNull<u8>) -> Option<usize> {
+        self.find_pool_idx(ptr)
+            .map(|pool_idx| self.slot_pools[pool_idx].slot_size)
+            .or_else(|| self.find_large(ptr).map(|object| object.layout.size()))
+    }
+
+    /// Returns true if `ptr` is the start of an allocated slot.
+    pub fn is_live_slot(&self, ptr: NonNull<u8>) -> bool {
+        match self.find_pool_idx(ptr) {
+            Some(pool_idx) => self.slot_pools[pool_idx].is_live(ptr),
+            None => self.find_large(ptr).is_some(),
+        }
+    }
+
+    /// Sums the layouts of every slot pool, recycled pool and bump page held,
+    /// the value `heap_size` is expected to track.
+    pub fn pooled_size(&self) -> usize {
+        self.slot_pools
+            .iter()
+            .chain(self.recycled_pools.iter())
+            .map(|pool| pool.layout.size())
+            .chain(self.bump_pages.iter().map(|page| page.layout.size()))
+            .chain(
+                self.large_objects
+                    .values()
+                    .map(|object| object.layout.size()),
+            )
+            .sum()
     }
 
     pub fn is_below_threshold(&self) -> bool {
@@ -172,11 +224,35 @@ impl<'alloc> PoolAllocator<'alloc> {
         if addr < end { Some(pool_idx) } else { None }
     }
 
+    /// look up the large object starting at `ptr`
+    #[inline]
+    fn find_large(&self, ptr: NonNull<u8>) -> Option<&LargeObject> {
+        self.large_objects.get(&(ptr.as_ptr() as usize))
+    }
+
     #[inline]
     pub fn try_alloc<T>(&mut self, value: T) -> Result<PoolPointer<'alloc, T>, PoolAllocError> {
-        let needed = core::mem::size_of::<PoolItem<T>>().max(8);
+        let slot_ptr = self.try_alloc_layout(Layout::new::<PoolItem<T>>())?;
+        // SAFETY: slot_ptr was successfully allocated for the layout of `PoolItem<T>`
+        unsafe {
+            let dst = slot_ptr.as_ptr() as *mut PoolItem<T>;
+            dst.write(PoolItem(value));
+            Ok(PoolPointer::from_raw(NonNull::new_unchecked(dst)))
+        }
+    }
+
+    /// allocate uninitialized memory for `layout` from the matching size class,
+    /// or as a large object if it is bigger than the largest size class
+    ///
+    /// the memory is released with `free_slot` like any typed allocation
+    #[inline]
+    pub fn try_alloc_layout(&mut self, layout: Layout) -> Result<NonNull<u8>, PoolAllocError> {
+        let needed = layout.size().max(8);
+        if needed > LARGEST_SIZE_CLASS {
+            return self.try_alloc_large(layout);
+        }
         let sc_idx = size_class_index_for(needed);
-        let slot_size = SIZE_CLASSES.get(sc_idx).copied().unwrap_or(needed);
+        let slot_size = SIZE_CLASSES[sc_idx];
 
         let cached_idx = self.alloc_cache[sc_idx].get();
         if cached_idx < self.slot_pools.len() {
@@ -184,12 +260,7 @@ impl<'alloc> PoolAllocator<'alloc> {
             if pool.slot_size == slot_size
                 && let Some(slot_ptr) = pool.alloc_slot()
             {
-                // SAFETY: slot_ptr was successfully allocated for this size class
-                return unsafe {
-                    let dst = slot_ptr.as_ptr() as *mut PoolItem<T>;
-                    dst.write(PoolItem(value));
-                    Ok(PoolPointer::from_raw(NonNull::new_unchecked(dst)))
-                };
+                return Ok(slot_ptr);
             }
         }
 
@@ -199,12 +270,7 @@ impl<'alloc> PoolAllocator<'alloc> {
                 && let Some(slot_ptr) = pool.alloc_slot()
             {
                 self.alloc_cache[sc_idx].set(i);
-                // SAFETY: slot_ptr was successfully allocated for this size class
-                return unsafe {
-                    let dst = slot_ptr.as_ptr() as *mut PoolItem<T>;
-                    dst.write(PoolItem(value));
-                    Ok(PoolPointer::from_raw(NonNull::new_unchecked(dst)))
-                };
+                return Ok(slot_ptr);
             }
         }
 
@@ -226,13 +292,7 @@ impl<'alloc> PoolAllocator<'alloc> {
             self.sorted_ranges.insert(spos, (base, end, insert_idx));
             self.slot_pools.push(pool);
             self.alloc_cache[sc_idx].set(insert_idx);
-
-            // SAFETY: slot_ptr was successfully allocated for this size class
-            return unsafe {
-                let dst = slot_ptr.as_ptr() as *mut PoolItem<T>;
-                dst.write(PoolItem(value));
-                Ok(PoolPointer::from_raw(NonNull::new_unchecked(dst)))
-            };
+            return Ok(slot_ptr);
         }
 
         // Recycle list had no match, allocate a fresh page from the OS.
@@ -247,13 +307,16 @@ impl<'alloc> PoolAllocator<'alloc> {
         self.sorted_ranges.insert(spos, (base, end, insert_idx));
         self.slot_pools.push(new_pool);
         self.alloc_cache[sc_idx].set(insert_idx);
+        Ok(slot_ptr)
+    }
 
-        // SAFETY: slot_ptr was successfully allocated for this size class
-        unsafe {
-            let dst = slot_ptr.as_ptr() as *mut PoolItem<T>;
-            dst.write(PoolItem(value));
-            Ok(PoolPointer::from_raw(NonNull::new_unchecked(dst)))
-        }
+    /// give an object bigger than the largest size class its own allocation
+    fn try_alloc_large(&mut self, layout: Layout) -> Result<NonNull<u8>, PoolAllocError> {
+        let object = LargeObject::try_init(layout)?;
+        self.current_heap_size += object.layout.size();
+        let ptr = object.buffer;
+        self.large_objects.insert(ptr.as_ptr() as usize, object);
+        Ok(ptr)
     }
 
     /// drops the value at `ptr` and returns the slot to the allocator
@@ -283,9 +346,15 @@ impl<'alloc> PoolAllocator<'alloc> {
             self.free_cache.set(pool_idx);
             return;
         }
+
+        // large objects go straight back to the OS
+        if let Some(object) = self.large_objects.remove(&(ptr.as_ptr() as usize)) {
+            self.current_heap_size = self.current_heap_size.saturating_sub(object.layout.size());
+            return;
+        }
         debug_assert!(
             false,
-            "free_slot called with pointer {ptr:p} not owned by any slot pool; \
+            "free_slot called with pointer {ptr:p} not owned by any slot pool or large object; \
              possible double-free or pointer from a raw page"
         );
     }
@@ -234,3 +234,31 @@ fn max_recycled_cap_respected() {
     assert_eq!(allocator.recycled_pools.len(), 1);
     assert!(allocator.current_heap_size < heap_before);
 }
+
+/// Objects bigger than the largest size class get their own allocation,
+/// which is returned to the OS as soon as it is freed.
+#[test]
+fn large_object_bypasses_size_classes() {
+    let mut allocator = PoolAllocator::default().with_page_size(4096);
+
+    let small = allocator.try_alloc(1u64).unwrap().as_ptr();
+    let heap_before = allocator.heap_size();
+
+    let large = allocator.try_alloc([7u8; 4096]).unwrap();
+    assert_eq!(large.as_inner_ref()[4095], 7);
+    let large = large.as_ptr().cast::<u8>();
+    assert_eq!(allocator.slot_pools.len(), 1);
+    assert_eq!(allocator.large_objects.len(), 1);
+    assert!(allocator.is_live_slot(large));
+    assert_eq!(allocator.slot_size_of(large), Some(4096));
+    assert_eq!(allocator.iter_live_slots().count(), 2);
+    assert_eq!(allocator.heap_size(), heap_before + 4096);
+    assert_eq!(allocator.heap_size(), allocator.pooled_size());
+
+    allocator.free_slot(large);
+    assert!(allocator.large_objects.is_empty());
+    assert!(!allocator.is_live_slot(large));
+    assert_eq!(allocator.heap_size(), heap_before);
+
+    allocator.free_slot(small.cast::<u8>());
+}
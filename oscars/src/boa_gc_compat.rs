@@ -0,0 +1,128 @@
+//! A facade exposing the mark sweep collector under the names `boa_gc` uses.
+//!
+//! The engine-facing contract is spelled out in
+//! `docs/boa_gc_api_surface.md`. Swapping the dependency is meant to be a
+//! rename:
+//!
+//! ```ignore
+//! use oscars::boa_gc_compat as boa_gc;
+//! ```
+//!
+//! Everything allocates in the thread-local collector of
+//! [`default_collector`](crate::collectors::mark_sweep::default_collector).
+//! Where the mark sweep API already has the `boa_gc` shape it is re-exported
+//! as is. [`WeakMap`] is a thin adapter, since the collector version takes
+//! an explicit collector.
+//!
+//! Known differences from `boa_gc`:
+//!
+//! - `Gc::into_raw` and `Gc::from_raw` deal in `PoolPointer<GcBox<T>>`
+//!   rather than `NonNull<GcBox<T>>`.
+//! - `Trace::trace` takes a `TraceColor` instead of a `Tracer`. Code written
+//!   against `custom_trace!` and `empty_trace!` is unaffected.
+//! - `GcRef::try_map` and `GcRefMut::try_map` return an `Option`.
+
+use core::cell::RefCell;
+
+use crate::collectors::mark_sweep::TraceColor;
+
+pub use crate::collectors::mark_sweep::cell::{
+    BorrowError, BorrowMutError, GcRef, GcRefCell, GcRefMut,
+};
+pub use crate::collectors::mark_sweep::{
+    Ephemeron, Finalize, Gc, Trace, WeakGc, finalizer_safe, force_collect,
+};
+pub use crate::{custom_trace, empty_trace, unsafe_empty_trace};
+pub use oscars_derive::{Finalize, Trace};
+
+/// Same as [`empty_trace!`], under the name older `boa_gc` code uses for
+/// foreign types.
+#[macro_export]
+macro_rules! unsafe_empty_trace {
+    () => {
+        $crate::empty_trace!();
+    };
+}
+
+/// A map whose entries are dropped once their key is collected.
+///
+/// Unlike the mark sweep [`WeakMap`](crate::collectors::mark_sweep::WeakMap)
+/// every operation takes `&self`, and values are returned by copy.
+pub struct WeakMap<K: Trace + 'static, V: Trace + 'static> {
+    inner: RefCell<crate::collectors::mark_sweep::WeakMap<K, V>>,
+}
+
+impl<K: Trace, V: Trace> WeakMap<K, V> {
+    /// Creates a map tracked by the collector of the current thread.
+    pub fn new() -> Self {
+        Self {
+            inner: RefCell::new(crate::collectors::mark_sweep::WeakMap::default()),
+        }
+    }
+
+    /// Returns a copy of the value stored for `key`.
+    pub fn get(&self, key: &Gc<K>) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.inner.borrow().get(key).cloned()
+    }
+
+    /// Stores `value` for `key`, replacing any previous value.
+    pub fn set(&self, key: &Gc<K>, value: V) {
+        crate::collectors::mark_sweep::with_collector(|collector| {
+            self.inner.borrow_mut().insert(key, value, collector);
+        });
+    }
+
+    /// Returns true if the map has an entry for `key`.
+    pub fn has(&self, key: &Gc<K>) -> bool {
+        self.inner.borrow().is_key_alive(key)
+    }
+
+    /// Removes the entry for `key`, returning true if there was one.
+    pub fn delete(&self, key: &Gc<K>) -> bool {
+        self.inner.borrow_mut().remove(key)
+    }
+
+    /// Returns the value stored for `key`, storing `value` first if there is
+    /// none.
+    pub fn get_or_insert(&self, key: &Gc<K>, value: V) -> V
+    where
+        V: Clone,
+    {
+        self.get_or_insert_computed(key, || value)
+    }
+
+    /// Returns the value stored for `key`, storing the result of `f` first
+    /// if there is none.
+    pub fn get_or_insert_computed<F>(&self, key: &Gc<K>, f: F) -> V
+    where
+        V: Clone,
+        F: FnOnce() -> V,
+    {
+        if let Some(value) = self.get(key) {
+            return value;
+        }
+        // `f` may use the map, so it runs before the map is borrowed
+        let value = f();
+        self.set(key, value.clone());
+        value
+    }
+}
+
+impl<K: Trace, V: Trace> Default for WeakMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Trace, V: Trace> Finalize for WeakMap<K, V> {}
+
+// SAFETY: entries are ephemerons tracked by the collector
+unsafe impl<K: Trace, V: Trace> Trace for WeakMap<K, V> {
+    unsafe fn trace(&self, _color: TraceColor) {}
+    fn run_finalizer(&self) {
+        Finalize::finalize(self);
+    }
+}
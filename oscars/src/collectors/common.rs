@@ -26,13 +26,63 @@ use std::collections::{HashMap, HashSet};
 pub trait Finalize {
     /// Cleanup logic for a type
     fn finalize(&self) {}
+
+    /// Returns `false` if finalizing this value, including the values it
+    /// holds, does nothing.
+    ///
+    /// Collectors that defer finalization use this to free such values
+    /// right away. `#[derive(Finalize)]` and the container impls ask their
+    /// fields and elements, a type with a custom `finalize` keeps the default.
+    #[inline]
+    fn needs_finalize(&self) -> bool {
+        true
+    }
+}
+
+/// Lets `#[derive(Finalize)]` ask a field whether it needs finalization,
+/// a field whose type is not known to implement [`Finalize`], e.g. one of a
+/// generic type, answers `true`.
+#[doc(hidden)]
+pub mod needs_finalize {
+    use super::Finalize;
+
+    pub struct Field<'a, T: ?Sized>(pub &'a T);
+
+    pub trait Known {
+        fn needs_finalize(&self) -> bool;
+    }
+
+    impl<T: Finalize + ?Sized> Known for Field<'_, T> {
+        #[inline]
+        fn needs_finalize(&self) -> bool {
+            self.0.needs_finalize()
+        }
+    }
+
+    // picked by method resolution only when `Known` does not apply, since it
+    // takes one more reference
+    pub trait Unknown {
+        #[inline]
+        fn needs_finalize(&self) -> bool {
+            true
+        }
+    }
+
+    impl<T: ?Sized> Unknown for &Field<'_, T> {}
 }
 
 // primitive and standard library blanket impls
 
 macro_rules! simple_finalize {
     ($($T:ty),* $(,)?) => {
-        $( impl Finalize for $T {} )*
+        $(
+            impl Finalize for $T {
+                #[inline]
+                fn needs_finalize(&self) -> bool {
+                    false
+                }
+            }
+        )*
     }
 }
 
@@ -56,7 +106,6 @@ simple_finalize![
     char,
     TypeId,
     String,
-    str,
     Rc<str>,
     NonZeroIsize,
     NonZeroUsize,
@@ -72,6 +121,13 @@ simple_finalize![
     NonZeroU128,
 ];
 
+impl Finalize for str {
+    #[inline]
+    fn needs_finalize(&self) -> bool {
+        false
+    }
+}
+
 #[cfg(target_has_atomic = "8")]
 simple_finalize![atomic::AtomicBool, atomic::AtomicI8, atomic::AtomicU8];
 
@@ -87,14 +143,36 @@ simple_finalize![atomic::AtomicI64, atomic::AtomicU64];
 #[cfg(target_has_atomic = "ptr")]
 simple_finalize![atomic::AtomicIsize, atomic::AtomicUsize];
 
-impl<T: ?Sized> Finalize for &'static T {}
+impl<T: ?Sized> Finalize for &'static T {
+    #[inline]
+    fn needs_finalize(&self) -> bool {
+        false
+    }
+}
+
+// containers need finalization if one of their elements does
+macro_rules! elements_finalize {
+    ($self:ident => $elements:expr) => {
+        #[inline]
+        fn needs_finalize(&$self) -> bool {
+            $elements.any(|e| Finalize::needs_finalize(e))
+        }
+    };
+}
 
-impl<T: Finalize, const N: usize> Finalize for [T; N] {}
+impl<T: Finalize, const N: usize> Finalize for [T; N] {
+    elements_finalize!(self => self.iter());
+}
 
 // Function pointer tuples, provide `Finalize` for function types.
 macro_rules! fn_finalize_one {
     ($ty:ty $(,$args:ident)*) => {
-        impl<Ret $(,$args)*> Finalize for $ty {}
+        impl<Ret $(,$args)*> Finalize for $ty {
+            #[inline]
+            fn needs_finalize(&self) -> bool {
+                false
+            }
+        }
     }
 }
 macro_rules! fn_finalize_group {
@@ -117,7 +195,14 @@ macro_rules! fn_finalize_group {
 macro_rules! tuple_finalize {
     () => {};
     ($($args:ident),*) => {
-        impl<$($args),*> Finalize for ($($args,)*) {}
+        impl<$($args: Finalize),*> Finalize for ($($args,)*) {
+            #[allow(non_snake_case)]
+            #[inline]
+            fn needs_finalize(&self) -> bool {
+                let ($($args,)*) = self;
+                false $(|| $args.needs_finalize())*
+            }
+        }
     }
 }
 
@@ -146,38 +231,81 @@ type_arg_impls![
     (A, B, C, D, E, F, G, H, I, J, K, L);
 ];
 
-impl<T: Finalize + ?Sized> Finalize for Box<T> {}
-impl<T: Finalize> Finalize for Box<[T]> {}
-impl<T: Finalize> Finalize for Vec<T> {}
+impl<T: Finalize> Finalize for [T] {
+    elements_finalize!(self => self.iter());
+}
+impl<T: Finalize + ?Sized> Finalize for Box<T> {
+    #[inline]
+    fn needs_finalize(&self) -> bool {
+        (**self).needs_finalize()
+    }
+}
+impl<T: Finalize> Finalize for Vec<T> {
+    elements_finalize!(self => self.iter());
+}
 
 #[cfg(feature = "thin-vec")]
-impl<T: Finalize> Finalize for thin_vec::ThinVec<T> {}
+impl<T: Finalize> Finalize for thin_vec::ThinVec<T> {
+    elements_finalize!(self => self.iter());
+}
 
-impl<T: Finalize> Finalize for Option<T> {}
-impl<T: Finalize, E: Finalize> Finalize for Result<T, E> {}
-impl<T: Ord + Finalize> Finalize for BinaryHeap<T> {}
-impl<K: Finalize, V: Finalize> Finalize for BTreeMap<K, V> {}
-impl<T: Finalize> Finalize for BTreeSet<T> {}
-impl<T: Finalize> Finalize for LinkedList<T> {}
-impl<T: Finalize> Finalize for VecDeque<T> {}
+impl<T: Finalize> Finalize for Option<T> {
+    elements_finalize!(self => self.iter());
+}
+impl<T: Finalize, E: Finalize> Finalize for Result<T, E> {
+    #[inline]
+    fn needs_finalize(&self) -> bool {
+        match self {
+            Ok(value) => value.needs_finalize(),
+            Err(error) => error.needs_finalize(),
+        }
+    }
+}
+impl<T: Ord + Finalize> Finalize for BinaryHeap<T> {
+    elements_finalize!(self => self.iter());
+}
+impl<K: Finalize, V: Finalize> Finalize for BTreeMap<K, V> {
+    elements_finalize!(self => self.iter().flat_map(|(k, v)| [k as &dyn Finalize, v]));
+}
+impl<T: Finalize> Finalize for BTreeSet<T> {
+    elements_finalize!(self => self.iter());
+}
+impl<T: Finalize> Finalize for LinkedList<T> {
+    elements_finalize!(self => self.iter());
+}
+impl<T: Finalize> Finalize for VecDeque<T> {
+    elements_finalize!(self => self.iter());
+}
 
 use core::hash::{BuildHasher, Hash};
 impl<K: Eq + Hash + Finalize, V: Finalize, S: BuildHasher> Finalize
     for hashbrown::hash_map::HashMap<K, V, S>
 {
+    elements_finalize!(self => self.iter().flat_map(|(k, v)| [k as &dyn Finalize, v]));
 }
 
 #[cfg(feature = "std")]
-impl<K: Eq + Hash + Finalize, V: Finalize, S: BuildHasher> Finalize for HashMap<K, V, S> {}
+impl<K: Eq + Hash + Finalize, V: Finalize, S: BuildHasher> Finalize for HashMap<K, V, S> {
+    elements_finalize!(self => self.iter().flat_map(|(k, v)| [k as &dyn Finalize, v]));
+}
 
 #[cfg(feature = "std")]
-impl<T: Eq + Hash + Finalize, S: BuildHasher> Finalize for HashSet<T, S> {}
+impl<T: Eq + Hash + Finalize, S: BuildHasher> Finalize for HashSet<T, S> {
+    elements_finalize!(self => self.iter());
+}
 
 impl<T: Finalize> Finalize for Cell<Option<T>> {}
-impl<T: Finalize> Finalize for OnceCell<T> {}
+impl<T: Finalize> Finalize for OnceCell<T> {
+    elements_finalize!(self => self.get().into_iter());
+}
 impl<T: ToOwned + Finalize + ?Sized> Finalize for Cow<'static, T> where T::Owned: Finalize {}
 
-impl<T> Finalize for PhantomData<T> {}
+impl<T> Finalize for PhantomData<T> {
+    #[inline]
+    fn needs_finalize(&self) -> bool {
+        false
+    }
+}
 
 #[cfg(feature = "icu")]
 impl Finalize for icu_locale_core::LanguageIdentifier {}
@@ -186,16 +314,22 @@ impl Finalize for icu_locale_core::LanguageIdentifier {}
 impl Finalize for icu_locale_core::Locale {}
 
 #[cfg(feature = "either")]
-impl<L: Finalize, R: Finalize> Finalize for either::Either<L, R> {}
+impl<L: Finalize, R: Finalize> Finalize for either::Either<L, R> {
+    #[inline]
+    fn needs_finalize(&self) -> bool {
+        either::for_both!(self, value => value.needs_finalize())
+    }
+}
 
 #[cfg(feature = "arrayvec")]
-impl<T: Finalize, const N: usize> Finalize for arrayvec::ArrayVec<T, N> {}
+impl<T: Finalize, const N: usize> Finalize for arrayvec::ArrayVec<T, N> {
+    elements_finalize!(self => self.iter());
+}
 
 #[cfg(feature = "std")]
-impl Finalize for std::path::Path {}
-#[cfg(feature = "std")]
-impl Finalize for std::path::PathBuf {}
-#[cfg(feature = "std")]
-impl Finalize for std::time::Instant {}
-#[cfg(feature = "std")]
-impl Finalize for std::time::SystemTime {}
+simple_finalize![
+    std::path::Path,
+    std::path::PathBuf,
+    std::time::Instant,
+    std::time::SystemTime,
+];
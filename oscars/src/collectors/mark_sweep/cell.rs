@@ -230,7 +230,16 @@ impl Display for BorrowMutError {
     }
 }
 
-impl<T: Trace + ?Sized> Finalize for GcRefCell<T> {}
+impl<T: Trace + ?Sized> Finalize for GcRefCell<T> {
+    fn needs_finalize(&self) -> bool {
+        match self.borrow.get().borrowed() {
+            // `run_finalizer` skips the value while it is being written
+            BorrowState::Writing => false,
+            // SAFETY: Please see GcCell's Trace impl Safety note.
+            _ => unsafe { (*self.cell.get()).needs_finalize() },
+        }
+    }
+}
 
 // SAFETY: GcCell maintains its own BorrowState and rootedness. GcCell's implementation
 // focuses on only continuing Trace-based methods while the cell state is not written.
@@ -0,0 +1,154 @@
+//! Debug checks of `Gc` handles, enabled by the `checked_handles` feature.
+//!
+//! A `Gc` points into the pool of its collector with a `'static` lifetime, so
+//! nothing stops it from being used after the collector is dropped, or after
+//! a sweep freed its value because of a rooting bug. With this feature every
+//! box is stamped with the id of its collector and a generation, and every
+//! handle keeps a copy of the stamp of its box. `Deref`, `Clone` and `Drop`
+//! of a handle then check that its collector is alive, that its slot is still
+//! allocated in the pool bitmap and that the slot was not reused since, and
+//! panic with a message naming the failed check instead of reading freed
+//! memory.
+//!
+//! Collectors are registered per thread on their first allocation and
+//! unregistered at the end of their `Drop`. Boxes of other collectors, e.g.
+//! the `NullCollector`, carry no stamp and are not checked.
+
+use core::cell::{Cell, RefCell};
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use hashbrown::HashMap;
+use rustc_hash::FxBuildHasher;
+
+use crate::alloc::mempool3::PoolAllocator;
+
+// 0 is the id of boxes made by collectors that are not checked
+static NEXT_COLLECTOR_ID: AtomicU32 = AtomicU32::new(1);
+
+std::thread_local! {
+    static LIVE: RefCell<HashMap<u32, LiveCollector, FxBuildHasher>> =
+        RefCell::new(HashMap::default());
+}
+
+struct LiveCollector {
+    // boxed by the collector, so the address survives moves of the collector
+    allocator: *const RefCell<PoolAllocator<'static>>,
+    // values dropped by a sweep may drop handles to values freed before them
+    sweeping: bool,
+}
+
+/// The collector and generation a box was allocated with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Stamp {
+    collector: u32,
+    generation: u32,
+}
+
+/// What is done with a handle when it is checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Access {
+    Deref,
+    Clone,
+    Drop,
+}
+
+impl Access {
+    fn verb(self) -> &'static str {
+        match self {
+            Self::Deref => "dereferenced",
+            Self::Clone => "cloned",
+            Self::Drop => "dropped",
+        }
+    }
+}
+
+// The id and allocation count of a collector.
+#[derive(Debug, Default)]
+pub(crate) struct CollectorChecks {
+    id: Cell<u32>,
+    generation: Cell<u32>,
+}
+
+impl CollectorChecks {
+    // returns the stamp of a new box, the collector is registered on its
+    // first allocation
+    pub(crate) fn next_stamp(&self, allocator: &RefCell<PoolAllocator<'static>>) -> Stamp {
+        if self.id.get() == 0 {
+            let id = NEXT_COLLECTOR_ID.fetch_add(1, Ordering::Relaxed);
+            self.id.set(id);
+            LIVE.with(|live| {
+                live.borrow_mut().insert(
+                    id,
+                    LiveCollector {
+                        allocator,
+                        sweeping: false,
+                    },
+                )
+            });
+        }
+        let generation = self.generation.get().wrapping_add(1);
+        self.generation.set(generation);
+        Stamp {
+            collector: self.id.get(),
+            generation,
+        }
+    }
+
+    // turns the checks of dropped handles off while the collector sweeps
+    pub(crate) fn set_sweeping(&self, sweeping: bool) {
+        let id = self.id.get();
+        if id != 0 {
+            let _ = LIVE.try_with(|live| {
+                if let Some(collector) = live.borrow_mut().get_mut(&id) {
+                    collector.sweeping = sweeping;
+                }
+            });
+        }
+    }
+
+    // called at the end of the collector's `Drop`
+    pub(crate) fn unregister(&self) {
+        let id = self.id.replace(0);
+        if id != 0 {
+            let _ = LIVE.try_with(|live| live.borrow_mut().remove(&id));
+        }
+    }
+}
+
+/// Checks that the box at `ptr` stamped with `stamp` may be accessed.
+///
+/// `current` reads the stamp the box holds now, it is only called once the
+/// slot is known to be allocated.
+pub(crate) fn check(
+    stamp: Stamp,
+    ptr: NonNull<u8>,
+    access: Access,
+    type_name: &str,
+    current: impl FnOnce() -> Stamp,
+) {
+    // a panic while unwinding would abort
+    if stamp.collector == 0 || std::thread::panicking() {
+        return;
+    }
+    let verb = access.verb();
+    let _ = LIVE.try_with(|live| {
+        let live = live.borrow();
+        let Some(collector) = live.get(&stamp.collector) else {
+            panic!("Gc<{type_name}> {verb} after its collector was dropped");
+        };
+        if access == Access::Drop && collector.sweeping {
+            return;
+        }
+        // SAFETY: the allocator is unregistered before the collector frees it
+        let Ok(allocator) = (unsafe { &*collector.allocator }).try_borrow() else {
+            return;
+        };
+        if !allocator.is_live_slot(ptr) {
+            panic!("Gc<{type_name}> {verb} after its value was freed by a collection");
+        }
+        if current() != stamp {
+            panic!("Gc<{type_name}> {verb} after its slot was reused by another value");
+        }
+    });
+}
@@ -0,0 +1,149 @@
+//! A thread-local default collector for code that allocates without passing
+//! a collector around, the way `boa_gc` is used.
+//!
+//! [`Gc::new`], [`WeakGc::new`], [`SoftGc::new`], [`Ephemeron::new`] and
+//! [`FinalizationRegistry::new`], as well as `WeakMap::default` and
+//! `WeakSet::default`, allocate in the collector of the current thread,
+//! which is created on first use and dropped when the thread exits. Handles must not outlive it,
+//! the same rule that applies to a collector passed to `new_in`. Operations
+//! that still take a collector, like `WeakMap::insert`, get it from
+//! [`with_collector`].
+//!
+//! Tests that want a fresh heap, or a collector configured with a policy or
+//! zeal mode, can install their own for a closure with
+//! [`with_scoped_collector`].
+
+use core::cell::Cell;
+use core::ptr::NonNull;
+
+use crate::collectors::mark_sweep::{
+    Ephemeron, FinalizationRegistry, Gc, MarkSweepGarbageCollector, SoftGc, Trace, WeakGc, WeakMap,
+    WeakSet,
+};
+
+std::thread_local! {
+    static COLLECTOR: MarkSweepGarbageCollector = MarkSweepGarbageCollector::default();
+    // collector installed by `with_scoped_collector`, shadows `COLLECTOR`
+    static SCOPED: Cell<Option<NonNull<MarkSweepGarbageCollector>>> = const { Cell::new(None) };
+}
+
+/// Runs `f` with the collector of the current thread, the innermost
+/// [`with_scoped_collector`] override if there is one.
+pub fn with_collector<R>(f: impl FnOnce(&MarkSweepGarbageCollector) -> R) -> R {
+    match SCOPED.get() {
+        // SAFETY: `with_scoped_collector` clears the override before the
+        // borrow it was made from ends
+        Some(collector) => f(unsafe { collector.as_ref() }),
+        None => COLLECTOR.with(f),
+    }
+}
+
+/// Makes `collector` the collector of the current thread while `f` runs.
+///
+/// Overrides nest, the previous collector is restored when `f` returns or
+/// unwinds.
+pub fn with_scoped_collector<R>(collector: &MarkSweepGarbageCollector, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<NonNull<MarkSweepGarbageCollector>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED.set(self.0);
+        }
+    }
+
+    let _restore = Restore(SCOPED.replace(Some(NonNull::from(collector))));
+    f()
+}
+
+/// Runs a collection on the collector of the current thread.
+pub fn force_collect() {
+    with_collector(MarkSweepGarbageCollector::collect);
+}
+
+/// Releases the objects kept alive by [`WeakGc::deref_keep`] in the collector
+/// of the current thread, see
+/// [`MarkSweepGarbageCollector::clear_kept_objects`].
+pub fn clear_kept_objects() {
+    with_collector(MarkSweepGarbageCollector::clear_kept_objects);
+}
+
+/// Returns true when the collector of the current thread is not collecting,
+/// see [`MarkSweepGarbageCollector::finalizer_safe`].
+pub fn finalizer_safe() -> bool {
+    with_collector(MarkSweepGarbageCollector::finalizer_safe)
+}
+
+impl<T: Trace> Gc<T> {
+    /// Allocates `value` in the collector of the current thread.
+    #[must_use]
+    pub fn new(value: T) -> Self {
+        with_collector(|collector| Self::new_in(value, collector))
+    }
+
+    /// Allocates a self-referential value in the collector of the current
+    /// thread, see [`Gc::new_cyclic_in`].
+    pub fn new_cyclic<F>(data_fn: F) -> Self
+    where
+        F: FnOnce(&WeakGc<T>) -> T,
+    {
+        with_collector(|collector| Self::new_cyclic_in(data_fn, collector))
+    }
+}
+
+impl<T: Trace> WeakGc<T> {
+    /// Creates a weak handle to `value` in the collector of the current thread.
+    pub fn new(value: &Gc<T>) -> Self {
+        with_collector(|collector| Self::new_in(value, collector))
+    }
+
+    /// Creates a weak handle to `value` in the collector of the current
+    /// thread, running `callback` after the collection that frees `value`.
+    pub fn new_with_callback(value: &Gc<T>, callback: impl FnOnce() + 'static) -> Self {
+        with_collector(|collector| Self::new_with_callback_in(value, callback, collector))
+    }
+}
+
+impl<T: Trace> SoftGc<T> {
+    /// Creates a soft reference to `value` in the collector of the current
+    /// thread.
+    pub fn new(value: &Gc<T>) -> Self {
+        with_collector(|collector| Self::new_in(value, collector))
+    }
+}
+
+impl<K: Trace, V: Trace> Ephemeron<K, V> {
+    /// Creates an ephemeron from `key` to `value` in the collector of the
+    /// current thread.
+    pub fn new(key: &Gc<K>, value: V) -> Self {
+        with_collector(|collector| Self::new_in(key, value, collector))
+    }
+}
+
+// `WeakMap::new` and `WeakSet::new` take the collector in every build, the
+// way they do in `mark_sweep_arena2`, so the thread collector is only reached
+// through `Default`
+impl<K: Trace, V: Trace> Default for WeakMap<K, V> {
+    /// Creates a map tracked by the collector of the current thread.
+    fn default() -> Self {
+        with_collector(|collector| Self::new(collector))
+    }
+}
+
+impl<K: Trace> Default for WeakSet<K> {
+    /// Creates a set tracked by the collector of the current thread.
+    fn default() -> Self {
+        with_collector(|collector| Self::new(collector))
+    }
+}
+
+impl<H: 'static> FinalizationRegistry<H> {
+    /// Creates a registry tracked by the collector of the current thread.
+    pub fn new() -> Self {
+        with_collector(|collector| Self::new_in(collector))
+    }
+}
+
+impl<H: 'static> Default for FinalizationRegistry<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
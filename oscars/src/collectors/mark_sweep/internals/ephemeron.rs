@@ -6,7 +6,7 @@ use core::marker::PhantomData;
 use crate::{
     alloc::mempool3::PoolItem,
     collectors::mark_sweep::{
-        ErasedEphemeron, TraceColor,
+        ErasedEphemeron, GcErasedPointer, TraceColor,
         internals::{GcBox, WeakGcBox},
         pointers::Gc,
         trace::Trace,
@@ -25,7 +25,7 @@ pub struct Ephemeron<K: Trace + ?Sized + 'static, V: Trace + 'static> {
 impl<K: Trace, V: Trace> Ephemeron<K, V> {
     // create an Ephemeron with the given GC trace color
     pub(crate) fn new(key: &Gc<K>, value: V, color: TraceColor) -> Self {
-        let weak_key = WeakGcBox::new(key.inner_ptr);
+        let weak_key = WeakGcBox::new(key.inner_ptr.to_erased());
         let value = GcBox::new_in(value, color);
         let vtable = vtable_of::<K, V>();
         Self {
@@ -47,6 +47,13 @@ impl<K: Trace, V: Trace> Ephemeron<K, V> {
         None
     }
 
+    pub(crate) fn value_mut(&mut self) -> Option<&mut V> {
+        if self.key.value().is_some() {
+            return Some(self.value.value_mut());
+        }
+        None
+    }
+
     pub fn upgrade(&self) -> Option<Gc<K>> {
         self.key.inner_ptr().map(|ptr| {
             // Increment the roots, since we are creating a new root.
@@ -84,6 +91,24 @@ impl<K: Trace, V: Trace> Ephemeron<K, V> {
     pub(crate) fn drop_fn(&self) -> EphemeronDropFn {
         self.vtable.drop_fn
     }
+
+    pub(crate) fn key_ptr_fn(&self) -> EphemeronKeyPtrFn {
+        self.vtable.key_ptr_fn
+    }
+
+    pub(crate) fn invalidate_fn(&self) -> EphemeronInvalidateFn {
+        self.vtable.invalidate_fn
+    }
+
+    #[cfg(any(test, feature = "heap_snapshot"))]
+    pub(crate) fn type_name(&self) -> &'static str {
+        (self.vtable.type_name)()
+    }
+
+    #[cfg(any(test, feature = "heap_snapshot"))]
+    pub(crate) fn size(&self) -> usize {
+        self.vtable.size
+    }
 }
 
 impl<K: Trace, V: Trace> Finalize for Ephemeron<K, V> {
@@ -153,12 +178,24 @@ pub(crate) const fn vtable_of<K: Trace + 'static, V: Trace + 'static>() -> &'sta
             drop_fn: EphemeronMarker::<K, V>::drop_fn::<K, V>,
             is_reachable_fn: |this, color| unsafe {
                 let ephemeron = this.cast::<PoolItem<Ephemeron<K, V>>>().as_ref().value();
-                ephemeron.key.is_reachable(color)
+                ephemeron.active.get() && ephemeron.key.is_reachable(color)
             },
             finalize_fn: |this| unsafe {
                 let ephemeron = this.cast::<PoolItem<Ephemeron<K, V>>>().as_ref().value();
                 Trace::run_finalizer(ephemeron);
             },
+            key_ptr_fn: |this| unsafe {
+                let ephemeron = this.cast::<PoolItem<Ephemeron<K, V>>>().as_ref().value();
+                ephemeron.key.as_heap_ptr()
+            },
+            invalidate_fn: |this| unsafe {
+                let ephemeron = this.cast::<PoolItem<Ephemeron<K, V>>>().as_ref().value();
+                ephemeron.invalidate();
+            },
+            #[cfg(any(test, feature = "heap_snapshot"))]
+            type_name: core::any::type_name::<Ephemeron<K, V>>,
+            #[cfg(any(test, feature = "heap_snapshot"))]
+            size: size_of::<Ephemeron<K, V>>(),
             _key_type_id: TypeId::of::<K>(),
             _key_size: size_of::<WeakGcBox<K>>(),
             _value_type_id: TypeId::of::<V>(),
@@ -173,12 +210,20 @@ type EphemeronTraceFn = unsafe fn(this: ErasedEphemeron, color: TraceColor);
 type EphemeronDropFn = unsafe fn(this: ErasedEphemeron);
 type EphemeronIsReachableFn = unsafe fn(this: ErasedEphemeron, color: TraceColor) -> bool;
 type EphemeronFinalizeFn = unsafe fn(this: ErasedEphemeron);
+type EphemeronKeyPtrFn = unsafe fn(this: ErasedEphemeron) -> Option<GcErasedPointer>;
+type EphemeronInvalidateFn = unsafe fn(this: ErasedEphemeron);
 
 pub struct EphemeronVTable {
     trace_fn: EphemeronTraceFn,
     drop_fn: EphemeronDropFn,
     is_reachable_fn: EphemeronIsReachableFn,
     finalize_fn: EphemeronFinalizeFn,
+    key_ptr_fn: EphemeronKeyPtrFn,
+    invalidate_fn: EphemeronInvalidateFn,
+    #[cfg(any(test, feature = "heap_snapshot"))]
+    type_name: fn() -> &'static str,
+    #[cfg(any(test, feature = "heap_snapshot"))]
+    size: usize,
     _key_type_id: TypeId,
     _key_size: usize,
     _value_type_id: TypeId,
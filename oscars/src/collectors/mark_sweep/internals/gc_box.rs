@@ -3,10 +3,12 @@
 use core::any::TypeId;
 
 use crate::collectors::mark_sweep::Finalize;
+#[cfg(feature = "checked_handles")]
+use crate::collectors::mark_sweep::checked::Stamp;
 use crate::collectors::mark_sweep::internals::gc_header::{GcHeader, HeaderColor};
 use crate::collectors::mark_sweep::{Trace, TraceColor};
 
-use super::{DropFn, FinalizeFn, TraceFn, VTable, vtable_of};
+use super::{DropFn, FinalizeFn, NeedsFinalizeFn, TraceFn, VTable, vtable_of, vtable_of_unsized};
 
 pub struct NonTraceable(());
 
@@ -22,14 +24,41 @@ unsafe impl Trace for NonTraceable {
     }
 }
 
+// Placeholder value of a box reserved by `Gc::new_cyclic_in`, the real value
+// is written with `GcBox::init` once the closure returns. Tracing and dropping
+// it are no-ops, so a collection can run while the value is being built.
+#[repr(transparent)]
+pub(crate) struct Uninit<T>(core::mem::MaybeUninit<T>);
+
+impl<T> Uninit<T> {
+    pub(crate) const fn new() -> Self {
+        Self(core::mem::MaybeUninit::uninit())
+    }
+}
+
+impl<T> Finalize for Uninit<T> {}
+
+unsafe impl<T> Trace for Uninit<T> {
+    crate::empty_trace!();
+}
+
 // TODO: Do we need the vtable on the neo box
 
 // NOTE: This may not be the best idea, but let's find out.
 //
 use crate::alloc::mempool3::{ErasedPoolPointer, PoolItem};
+use core::alloc::Layout;
 use core::cell::Cell;
 use core::marker::PhantomData;
 use core::ptr::NonNull;
+use rust_alloc::boxed::Box;
+
+// Moves a possibly fat pointer to the address of `data` and keeps its
+// metadata. The result is derived from `ptr`, so it carries the provenance of
+// `ptr` rather than the one of `data`.
+pub(crate) fn with_data_ptr<T: ?Sized>(ptr: *mut T, data: *mut u8) -> *mut T {
+    ptr.with_addr(data.addr())
+}
 
 pub struct WeakGcBox<T: Trace + ?Sized + 'static> {
     pub(crate) inner_ptr: Cell<Option<ErasedPoolPointer<'static>>>,
@@ -81,6 +110,13 @@ impl<T: Trace> WeakGcBox<T> {
     pub(crate) fn inner_ptr(
         &self,
     ) -> Option<crate::alloc::mempool3::PoolPointer<'static, GcBox<T>>> {
+        // a box reserved by `Gc::new_cyclic_in` has no value yet
+        if self
+            .inner_ref()
+            .is_some_and(|inner| inner.header.is_constructing())
+        {
+            return None;
+        }
         // SAFETY: This pointer started out as a `GcBox<T>`, so it's safe to cast
         // it back, the `PhantomData` guarantees that the type `T` is still correct
         unsafe {
@@ -126,31 +162,115 @@ unsafe impl<T: Trace + ?Sized> Trace for WeakGcBox<T> {
 pub struct GcBox<T: Trace + ?Sized + 'static> {
     pub(crate) header: GcHeader,
     vtable: &'static VTable,
+    #[cfg(feature = "checked_handles")]
+    stamp: Cell<Stamp>,
     value: T,
 }
 
+// new objects get the opposite of the current live epoch color so they
+// survive the current sweep cycle
+// root_count starts at 0, `Gc::new_in` increments it to 1
+fn new_header(color: TraceColor) -> GcHeader {
+    match color {
+        TraceColor::White => GcHeader::new_typed::<true>(),
+        TraceColor::Black => GcHeader::new_typed::<false>(),
+    }
+}
+
 impl<T: Trace> GcBox<T> {
-    // new objects get the opposite of the current live epoch color so they
-    // survive the current sweep cycle
-    // root_count starts at 0, `Gc::new_in` increments it to 1
     pub(crate) fn new_in(value: T, color: TraceColor) -> Self {
-        let header = match color {
-            TraceColor::White => GcHeader::new_typed::<true>(),
-            TraceColor::Black => GcHeader::new_typed::<false>(),
-        };
         Self {
-            header,
+            header: new_header(color),
             vtable: vtable_of::<T>(),
+            #[cfg(feature = "checked_handles")]
+            stamp: Cell::new(Stamp::default()),
             value,
         }
     }
 }
 
+// A box moved in from a `Box<T>` may hold a dynamically sized value. Erased
+// pointers to it are thin, so it keeps a fat pointer to itself behind the
+// value and records where in its header, see `vtable_of_unsized`.
+impl<T: Trace + ?Sized> GcBox<T> {
+    // returns the layout of a box holding `value` and the offset of its fat
+    // pointer
+    pub(crate) fn boxed_layout(value: &T) -> (Layout, usize) {
+        let (layout, _) = Layout::new::<GcBox<()>>()
+            .extend(Layout::for_value(value))
+            .expect("GcBox layout overflows isize");
+        let (layout, fat_offset) = layout
+            .pad_to_align()
+            .extend(Layout::new::<*mut GcBox<T>>())
+            .expect("GcBox layout overflows isize");
+        (layout.pad_to_align(), fat_offset)
+    }
+
+    // moves `value` into `slot` and returns the pointer to the new box
+    //
+    // SAFETY: `slot` must be valid for writes of `boxed_layout(&value)`
+    pub(crate) unsafe fn write_boxed(
+        slot: NonNull<u8>,
+        value: Box<T>,
+        color: TraceColor,
+    ) -> NonNull<PoolItem<GcBox<T>>> {
+        let (_, fat_offset) = Self::boxed_layout(&value);
+        let header = new_header(color);
+        header.set_fat_offset(
+            u32::try_from(fat_offset).expect("dynamically sized GC values are limited to 4 GiB"),
+        );
+        let value_layout = Layout::for_value::<T>(&value);
+        let src = Box::into_raw(value);
+        // only the metadata of `src` is kept
+        let this = with_data_ptr(src as *mut GcBox<T>, slot.as_ptr());
+        // SAFETY: the caller guarantees `slot` is big enough, the value is
+        // moved out of the box allocation which is then freed without dropping it
+        unsafe {
+            (&raw mut (*this).header).write(header);
+            (&raw mut (*this).vtable).write(vtable_of_unsized::<T>());
+            #[cfg(feature = "checked_handles")]
+            (&raw mut (*this).stamp).write(Cell::new(Stamp::default()));
+            core::ptr::copy_nonoverlapping(
+                src.cast::<u8>(),
+                (&raw mut (*this).value).cast::<u8>(),
+                value_layout.size(),
+            );
+            slot.add(fat_offset).cast::<*mut GcBox<T>>().write(this);
+            if value_layout.size() != 0 {
+                rust_alloc::alloc::dealloc(src.cast::<u8>(), value_layout);
+            }
+            NonNull::new_unchecked(this as *mut PoolItem<GcBox<T>>)
+        }
+    }
+
+    // reads the fat pointer a box made by `write_boxed` stores behind its value
+    //
+    // SAFETY: `this` must point at a live box made by `write_boxed`
+    pub(crate) unsafe fn fat_ptr(this: NonNull<u8>) -> NonNull<GcBox<T>> {
+        // SAFETY: the header is the first field of every box
+        let fat_offset = unsafe { this.cast::<GcHeader>().as_ref().fat_offset() };
+        // SAFETY: `write_boxed` stored the pointer at `fat_offset`
+        unsafe { NonNull::new_unchecked(this.add(fat_offset).cast::<*mut GcBox<T>>().read()) }
+    }
+}
+
 impl<T: Trace + ?Sized> GcBox<T> {
+    // returns a pointer to the value of the box `this` points at, derived
+    // from `this` without creating a reference
+    //
+    // SAFETY: `this` must point at a box
+    pub(crate) unsafe fn value_ptr(this: *mut Self) -> *mut T {
+        unsafe { &raw mut (*this).value }
+    }
+
     pub fn value(&self) -> &T {
         &self.value
     }
 
+    pub(crate) fn value_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+
     pub(crate) fn is_reachable(&self, color: TraceColor) -> bool {
         match color {
             TraceColor::Black => self.header.is_black() || self.header.is_grey(),
@@ -190,16 +310,52 @@ impl<T: Trace + ?Sized> GcBox<T> {
         self.vtable.finalize_fn()
     }
 
+    pub(crate) fn needs_finalize_fn(&self) -> NeedsFinalizeFn {
+        self.vtable.needs_finalize_fn()
+    }
+
     pub(crate) fn size(&self) -> usize {
-        self.vtable.size()
+        // sized boxes have no fat pointer and a `fat_offset` of 0
+        self.header.fat_offset() + self.vtable.size()
     }
 
     pub(crate) fn type_id(&self) -> TypeId {
         self.vtable.type_id()
     }
 
+    #[cfg(any(test, feature = "std"))]
+    pub(crate) fn type_name(&self) -> &'static str {
+        self.vtable.type_name()
+    }
+
+    #[cfg(feature = "checked_handles")]
+    pub(crate) fn stamp(&self) -> Stamp {
+        self.stamp.get()
+    }
+
+    #[cfg(feature = "checked_handles")]
+    pub(crate) fn set_stamp(&self, stamp: Stamp) {
+        self.stamp.set(stamp);
+    }
+
     #[inline]
-    fn trace_impl(&self, color: TraceColor) {
+    pub(crate) fn trace_impl(&self, color: TraceColor) {
+        // while a heap snapshot records edges, only the inspected object is
+        // traced into and every other box it reaches is recorded as an edge,
+        // the check stays out of the mark path without the `heap_snapshot` feature
+        #[cfg(any(test, feature = "heap_snapshot"))]
+        {
+            use crate::collectors::mark_sweep::snapshot::{Visit, visit};
+            match visit((self as *const Self).cast::<u8>() as usize) {
+                Visit::Trace => {}
+                Visit::Expand => {
+                    unsafe { Trace::trace(&self.value, color) };
+                    return;
+                }
+                Visit::Record => return,
+            }
+        }
+
         match color {
             TraceColor::White if self.header.is_black() => {
                 self.header.mark(HeaderColor::Grey);
@@ -224,10 +380,28 @@ impl<T: Trace + ?Sized> GcBox<T> {
     }
 }
 
+impl<T: Trace> GcBox<Uninit<T>> {
+    // writes the value of a box reserved by `Gc::new_cyclic_in` and switches
+    // it over to the vtable of `T`
+    //
+    // SAFETY: `this` must point at a live box whose value was never written
+    pub(crate) unsafe fn init(this: NonNull<Self>, value: T) {
+        let this = this.as_ptr();
+        unsafe {
+            (&raw mut (*this).value).cast::<T>().write(value);
+            (*this).vtable = vtable_of::<T>();
+        }
+    }
+}
+
 impl<T: Trace> Finalize for GcBox<T> {
     fn finalize(&self) {
         self.value.finalize();
     }
+
+    fn needs_finalize(&self) -> bool {
+        self.value.needs_finalize()
+    }
 }
 
 unsafe impl<T: Trace> Trace for GcBox<T> {
@@ -4,6 +4,13 @@ use core::{cell::Cell, fmt};
 const WHITE_MARK_BITS: u8 = 0b0000_0000;
 const BLACK_MARK_BITS: u8 = 0b0000_0011;
 const GREY_MARK_BITS: u8 = 0b0000_0001;
+// set while `Gc::new_cyclic_in` builds the value, weak handles treat the box as dead
+const CONSTRUCTING_BIT: u8 = 0b0000_0100;
+// set once the finalizer of a box resurrected by deferred finalization ran
+const FINALIZED_BIT: u8 = 0b0000_1000;
+// collections a softly referenced box went through since its last access
+const SOFT_AGE_SHIFT: u8 = 4;
+const SOFT_AGE_BITS: u8 = 0b1111_0000;
 
 #[derive(Debug, Clone, Copy)]
 pub struct HeaderFlags(pub(crate) u8);
@@ -45,6 +52,40 @@ impl HeaderFlags {
         // Clear the color bits while preserving IS_WEAK and any other flag bits
         Self(self.0 & !BLACK_MARK_BITS)
     }
+
+    pub const fn is_constructing(self) -> bool {
+        self.0 & CONSTRUCTING_BIT != 0
+    }
+
+    pub const fn with_constructing(self, constructing: bool) -> Self {
+        if constructing {
+            Self(self.0 | CONSTRUCTING_BIT)
+        } else {
+            Self(self.0 & !CONSTRUCTING_BIT)
+        }
+    }
+
+    pub const fn is_finalized(self) -> bool {
+        self.0 & FINALIZED_BIT != 0
+    }
+
+    pub const fn with_finalized(self) -> Self {
+        Self(self.0 | FINALIZED_BIT)
+    }
+
+    pub const fn soft_age(self) -> u8 {
+        (self.0 & SOFT_AGE_BITS) >> SOFT_AGE_SHIFT
+    }
+
+    pub const fn with_soft_age(self, age: u8) -> Self {
+        // saturate at the largest age the bits can hold
+        let age = if age > SOFT_AGE_BITS >> SOFT_AGE_SHIFT {
+            SOFT_AGE_BITS >> SOFT_AGE_SHIFT
+        } else {
+            age
+        };
+        Self((self.0 & !SOFT_AGE_BITS) | (age << SOFT_AGE_SHIFT))
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -58,6 +99,9 @@ pub enum HeaderColor {
 pub struct GcHeader {
     pub(crate) flags: Cell<HeaderFlags>,
     root_count: Cell<u16>,
+    // offset of the fat pointer a dynamically sized box keeps behind its
+    // value, 0 for sized boxes. Fits in the padding before the vtable.
+    fat_offset: Cell<u32>,
 }
 
 impl fmt::Debug for GcHeader {
@@ -77,6 +121,7 @@ impl GcHeader {
         Self {
             flags: Cell::new(HeaderFlags::new_white()),
             root_count: Cell::new(0),
+            fat_offset: Cell::new(0),
         }
     }
 
@@ -84,6 +129,7 @@ impl GcHeader {
         Self {
             flags: Cell::new(HeaderFlags::new_black()),
             root_count: Cell::new(0),
+            fat_offset: Cell::new(0),
         }
     }
 
@@ -143,6 +189,39 @@ impl GcHeader {
     pub const fn is_grey(&self) -> bool {
         self.flags.get().is_grey()
     }
+
+    pub fn set_constructing(&self, constructing: bool) {
+        self.flags
+            .set(self.flags.get().with_constructing(constructing));
+    }
+
+    pub const fn is_constructing(&self) -> bool {
+        self.flags.get().is_constructing()
+    }
+
+    pub fn set_finalized(&self) {
+        self.flags.set(self.flags.get().with_finalized());
+    }
+
+    pub const fn is_finalized(&self) -> bool {
+        self.flags.get().is_finalized()
+    }
+
+    pub fn set_soft_age(&self, age: u8) {
+        self.flags.set(self.flags.get().with_soft_age(age));
+    }
+
+    pub const fn soft_age(&self) -> u8 {
+        self.flags.get().soft_age()
+    }
+
+    pub(crate) fn set_fat_offset(&self, offset: u32) {
+        self.fat_offset.set(offset);
+    }
+
+    pub(crate) fn fat_offset(&self) -> usize {
+        self.fat_offset.get() as usize
+    }
 }
 
 #[cfg(test)]
@@ -177,4 +256,24 @@ mod tests {
         assert!(!header.is_white(), "failed to toggle black");
         assert!(!header.is_grey(), "failed to toggle black");
     }
+
+    #[test]
+    fn soft_age_survives_marking() {
+        let header = GcHeader::new_white();
+        header.set_soft_age(5);
+        header.mark(HeaderColor::Grey);
+        header.mark(HeaderColor::Black);
+        assert!(header.is_black());
+        assert_eq!(header.soft_age(), 5);
+        header.mark(HeaderColor::White);
+        assert_eq!(header.soft_age(), 5);
+
+        header.set_finalized();
+        assert_eq!(header.soft_age(), 5);
+
+        header.set_soft_age(u8::MAX);
+        assert_eq!(header.soft_age(), 15);
+        assert!(header.is_white());
+        assert!(header.is_finalized());
+    }
 }
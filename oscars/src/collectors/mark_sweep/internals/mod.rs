@@ -6,6 +6,9 @@ mod vtable;
 pub(crate) use ephemeron::Ephemeron;
 #[allow(unused_imports)]
 pub(crate) use gc_header::{GcHeader, HeaderColor};
-pub(crate) use vtable::{DropFn, FinalizeFn, TraceFn, VTable, vtable_of};
+pub(crate) use vtable::{
+    DropFn, FinalizeFn, NeedsFinalizeFn, TraceFn, VTable, vtable_of, vtable_of_unsized,
+};
 
 pub use self::gc_box::{GcBox, NonTraceable, WeakGcBox};
+pub(crate) use self::gc_box::{Uninit, with_data_ptr};
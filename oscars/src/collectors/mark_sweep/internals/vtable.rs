@@ -2,7 +2,7 @@ use core::any::TypeId;
 
 use crate::alloc::mempool3::PoolItem;
 
-use crate::collectors::mark_sweep::{GcBox, GcErasedPointer, Trace, TraceColor};
+use crate::collectors::mark_sweep::{Finalize, GcBox, GcErasedPointer, Trace, TraceColor};
 
 // Workaround: https://users.rust-lang.org/t/custom-vtables-with-integers/78508
 pub(crate) const fn vtable_of<T: Trace + 'static>() -> &'static VTable {
@@ -34,6 +34,13 @@ pub(crate) const fn vtable_of<T: Trace + 'static>() -> &'static VTable {
             let value = unsafe { this.cast::<PoolItem<GcBox<Self>>>().as_ref().value() };
             Trace::run_finalizer(value);
         }
+
+        // SAFETY: The caller must ensure that the passed erased pointer is `GcBox<Self>`.
+        unsafe fn needs_finalize_fn(this: GcErasedPointer) -> bool {
+            // SAFETY: The caller must ensure that the passed erased pointer is `GcBox<Self>`.
+            let value = unsafe { this.cast::<PoolItem<GcBox<Self>>>().as_ref().value() };
+            Finalize::needs_finalize(value)
+        }
     }
 
     impl<T: Trace + 'static> HasVTable for T {
@@ -41,7 +48,10 @@ pub(crate) const fn vtable_of<T: Trace + 'static>() -> &'static VTable {
             trace_fn: T::trace_fn,
             drop_fn: T::drop_fn,
             finalize_fn: T::finalize_fn,
+            needs_finalize_fn: T::needs_finalize_fn,
             type_id: TypeId::of::<T>(),
+            #[cfg(any(test, feature = "std"))]
+            type_name: core::any::type_name::<T>,
             size: size_of::<GcBox<T>>(),
         };
     }
@@ -49,16 +59,82 @@ pub(crate) const fn vtable_of<T: Trace + 'static>() -> &'static VTable {
     T::VTABLE
 }
 
+// Vtable of a dynamically sized box written by `GcBox::write_boxed`. A thin
+// erased pointer lacks the metadata of `T`, so these functions read it from
+// the fat pointer the box stores behind its value.
+pub(crate) const fn vtable_of_unsized<T: Trace + ?Sized + 'static>() -> &'static VTable {
+    trait HasUnsizedVTable: Trace + 'static {
+        const VTABLE: &'static VTable;
+
+        unsafe fn trace_fn(this: GcErasedPointer, color: TraceColor) {
+            // SAFETY: The caller must ensure that the passed erased pointer is
+            // a dynamically sized `GcBox<Self>`.
+            let gc_box = unsafe { GcBox::<Self>::fat_ptr(this.cast()).as_ref() };
+            gc_box.trace_impl(color);
+        }
+
+        // SAFETY: The caller must ensure that the passed erased pointer is a
+        // dynamically sized `GcBox<Self>`.
+        unsafe fn drop_fn(this: GcErasedPointer) {
+            // SAFETY: The caller must ensure that the passed erased pointer is
+            // a dynamically sized `GcBox<Self>`.
+            let this = unsafe { GcBox::<Self>::fat_ptr(this.cast()) };
+
+            // SAFETY: The caller must ensure the erased pointer is not dropped or deallocated.
+            unsafe { core::ptr::drop_in_place(this.as_ptr()) };
+        }
+
+        // SAFETY: The caller must ensure that the passed erased pointer is a
+        // dynamically sized `GcBox<Self>`.
+        unsafe fn finalize_fn(this: GcErasedPointer) {
+            // SAFETY: The caller must ensure that the passed erased pointer is
+            // a dynamically sized `GcBox<Self>`.
+            let value = unsafe { GcBox::<Self>::fat_ptr(this.cast()).as_ref().value() };
+            Finalize::finalize(value);
+        }
+
+        // SAFETY: The caller must ensure that the passed erased pointer is a
+        // dynamically sized `GcBox<Self>`.
+        unsafe fn needs_finalize_fn(this: GcErasedPointer) -> bool {
+            // SAFETY: The caller must ensure that the passed erased pointer is
+            // a dynamically sized `GcBox<Self>`.
+            let value = unsafe { GcBox::<Self>::fat_ptr(this.cast()).as_ref().value() };
+            Finalize::needs_finalize(value)
+        }
+    }
+
+    impl<T: Trace + ?Sized + 'static> HasUnsizedVTable for T {
+        const VTABLE: &'static VTable = &VTable {
+            trace_fn: T::trace_fn,
+            drop_fn: T::drop_fn,
+            finalize_fn: T::finalize_fn,
+            needs_finalize_fn: T::needs_finalize_fn,
+            type_id: TypeId::of::<T>(),
+            #[cfg(any(test, feature = "std"))]
+            type_name: core::any::type_name::<T>,
+            // the box size varies, `GcBox::size` adds the offset of this pointer
+            size: size_of::<*mut GcBox<T>>(),
+        };
+    }
+
+    T::VTABLE
+}
+
 pub(crate) type TraceFn = unsafe fn(this: GcErasedPointer, color: TraceColor);
 pub(crate) type DropFn = unsafe fn(this: GcErasedPointer);
 pub(crate) type FinalizeFn = unsafe fn(this: GcErasedPointer);
+pub(crate) type NeedsFinalizeFn = unsafe fn(this: GcErasedPointer) -> bool;
 
 #[derive(Debug)]
 pub(crate) struct VTable {
     trace_fn: TraceFn,
     drop_fn: DropFn,
     finalize_fn: FinalizeFn,
+    needs_finalize_fn: NeedsFinalizeFn,
     type_id: TypeId,
+    #[cfg(any(test, feature = "std"))]
+    type_name: fn() -> &'static str,
+    // size of the box, or of the trailing fat pointer for dynamically sized boxes
     size: usize,
 }
 
@@ -75,10 +151,19 @@ impl VTable {
         self.finalize_fn
     }
 
+    pub(crate) fn needs_finalize_fn(&self) -> NeedsFinalizeFn {
+        self.needs_finalize_fn
+    }
+
     pub(crate) const fn type_id(&self) -> TypeId {
         self.type_id
     }
 
+    #[cfg(any(test, feature = "std"))]
+    pub(crate) fn type_name(&self) -> &'static str {
+        (self.type_name)()
+    }
+
     pub(crate) fn size(&self) -> usize {
         self.size
     }
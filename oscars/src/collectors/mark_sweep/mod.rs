@@ -6,27 +6,56 @@
 use core::cell::{Cell, RefCell};
 use core::ptr::NonNull;
 
+use hashbrown::{HashMap, HashSet};
+use rustc_hash::FxBuildHasher;
+
 use crate::{
     alloc::mempool3::{PoolAllocError, PoolAllocator, PoolItem, PoolPointer},
-    collectors::mark_sweep::internals::{Ephemeron, GcBox, NonTraceable},
+    collectors::mark_sweep::internals::{GcBox, NonTraceable},
+    collectors::observer::{GcObserver, ObserverId, Observers},
+    collectors::policy::{GcPolicy, Scheduler},
+    collectors::shutdown::{LeakReport, LeakedObject, ShutdownPolicy},
+    collectors::stats::{GcClock, GcStats, StatsRecorder},
+    collectors::unwind::PanicSink,
+    collectors::zeal::{Zeal, ZealState},
 };
+use rust_alloc::boxed::Box;
+use rust_alloc::rc::Rc;
 use rust_alloc::vec::Vec;
 
 mod pointers;
 pub(crate) mod trace;
 
 pub mod cell;
+#[cfg(feature = "checked_handles")]
+pub(crate) mod checked;
+#[cfg(feature = "std")]
+pub mod default_collector;
+#[cfg(any(test, feature = "heap_snapshot"))]
+pub mod snapshot;
+#[cfg(any(test, feature = "heap_snapshot"))]
+pub mod verify;
 
 #[cfg(all(test, feature = "mark_sweep"))]
 mod tests;
 
 pub(crate) mod internals;
 
+#[cfg(feature = "std")]
+pub use default_collector::{
+    clear_kept_objects, finalizer_safe, force_collect, with_collector, with_scoped_collector,
+};
 #[doc(hidden)]
 pub use pointers::ErasedWeakMap;
-pub use pointers::{Gc, WeakGc, WeakMap};
+pub use pointers::{
+    Ephemeron, FinalizationRegistry, Gc, SoftGc, SoftPolicy, WeakGc, WeakMap, WeakSet,
+};
 pub use trace::{Finalize, Trace, TraceColor};
 
+// used by `#[derive(Finalize)]`
+#[doc(hidden)]
+pub use crate::collectors::common::needs_finalize as __needs_finalize;
+
 pub trait Collector {
     // trigger a full collection cycle
     fn collect(&self);
@@ -42,6 +71,15 @@ pub trait Collector {
         value: T,
     ) -> Result<PoolPointer<'gc, GcBox<T>>, PoolAllocError>;
 
+    // Allocates a GC node for a boxed, possibly dynamically sized, value by
+    // moving it out of the `Box` into a `GcBox`
+    //
+    // the returned pointer is tied to the collector's lifetime.
+    fn alloc_boxed_gc_node<'gc, T: Trace + ?Sized + 'static>(
+        &'gc self,
+        value: Box<T>,
+    ) -> Result<PoolPointer<'gc, GcBox<T>>, PoolAllocError>;
+
     // Allocates an ephemeron node pointing to an existing GC key, and a new value
     //
     // the returned pointer is tied to the collector's lifetime
@@ -49,21 +87,45 @@ pub trait Collector {
         &'gc self,
         key: &Gc<K>,
         value: V,
-    ) -> Result<PoolPointer<'gc, Ephemeron<K, V>>, PoolAllocError>;
+    ) -> Result<PoolPointer<'gc, internals::Ephemeron<K, V>>, PoolAllocError>;
 
     // Register a weak map with the GC so it can prune dead entries
     #[doc(hidden)]
     fn track_weak_map(&self, map: core::ptr::NonNull<dyn ErasedWeakMap>);
+
+    // Keeps `gc` alive until the collector's kept objects are cleared, the
+    // ECMAScript `AddToKeptObjects` operation
+    fn keep_alive<T: Trace + ?Sized>(&self, gc: &Gc<T>);
+
+    // Runs `callback` once a collection frees `ephemeron` because its key
+    // died, after the collection has finished
+    #[doc(hidden)]
+    fn on_weak_cleared(&self, ephemeron: NonNull<u8>, callback: Box<dyn FnOnce()>);
+
+    // Treats `gc` as strongly reachable while it is fresh under the
+    // collector's `SoftPolicy`
+    fn track_soft_ref<T: Trace + ?Sized>(&self, gc: &Gc<T>);
+
+    // Returns a flag shared by the public handles of `ephemeron`, cleared
+    // once the collector frees it
+    #[doc(hidden)]
+    fn track_ephemeron(&self, ephemeron: NonNull<u8>) -> Rc<Cell<bool>>;
 }
 
 type GcErasedPointer = NonNull<PoolItem<GcBox<NonTraceable>>>;
-pub(crate) type ErasedEphemeron = NonNull<PoolItem<Ephemeron<NonTraceable, NonTraceable>>>;
+type WeakCallback = Box<dyn FnOnce()>;
+pub(crate) type ErasedEphemeron =
+    NonNull<PoolItem<internals::Ephemeron<NonTraceable, NonTraceable>>>;
 
 #[derive(Default)]
 pub struct MarkSweepGarbageCollector {
     // we use RefCell so we can borrow the arena mutably via &self
     // this fits the Allocator trait and is safe for single-threaded use
+    #[cfg(not(feature = "checked_handles"))]
     pub(crate) allocator: RefCell<PoolAllocator<'static>>,
+    // boxed so checked handles still find it after the collector moved
+    #[cfg(feature = "checked_handles")]
+    pub(crate) allocator: Box<RefCell<PoolAllocator<'static>>>,
     root_queue: RefCell<Vec<GcErasedPointer>>,
     ephemeron_queue: RefCell<Vec<ErasedEphemeron>>,
     // current trace color epoch, flips each cycle
@@ -75,6 +137,31 @@ pub struct MarkSweepGarbageCollector {
     pending_root_queue: RefCell<Vec<GcErasedPointer>>,
     pending_ephemeron_queue: RefCell<Vec<ErasedEphemeron>>,
     pub(crate) weak_maps: RefCell<Vec<NonNull<dyn ErasedWeakMap>>>,
+    // targets of `WeakGc::deref_keep`, each holds a root until cleared
+    kept_objects: RefCell<Vec<GcErasedPointer>>,
+    // callbacks of `WeakGc::new_with_callback`, keyed by ephemeron
+    weak_callbacks: RefCell<HashMap<NonNull<u8>, WeakCallback, FxBuildHasher>>,
+    // callbacks of ephemerons freed by the current collection, run once it ends
+    cleared_weak_callbacks: RefCell<Vec<WeakCallback>>,
+    // liveness flags of public `Ephemeron` handles, keyed by ephemeron
+    ephemeron_handles: RefCell<HashMap<NonNull<u8>, Rc<Cell<bool>>, FxBuildHasher>>,
+    // targets of `SoftGc`, removed when they are swept
+    soft_refs: RefCell<HashSet<GcErasedPointer, FxBuildHasher>>,
+    soft_policy: Cell<SoftPolicy>,
+    // run finalizers after the sweep instead of during it
+    deferred_finalization: Cell<bool>,
+    // what `Drop` does with a heap that still has rooted values
+    shutdown_policy: Cell<ShutdownPolicy>,
+    // decides when to collect and how the heap threshold moves between cycles
+    scheduler: Scheduler,
+    stats: StatsRecorder,
+    observers: Observers,
+    zeal: ZealState,
+    // the first panic of a finalizer or destructor, raised once the
+    // collection or teardown that ran it is over
+    panics: PanicSink,
+    #[cfg(feature = "checked_handles")]
+    checks: checked::CollectorChecks,
 }
 
 impl MarkSweepGarbageCollector {
@@ -88,6 +175,204 @@ impl MarkSweepGarbageCollector {
         self
     }
 
+    /// Sets the [`GcPolicy`] deciding when this collector runs.
+    pub fn with_policy(self, policy: impl GcPolicy + 'static) -> Self {
+        self.set_policy(policy);
+        self
+    }
+
+    /// Replaces the [`GcPolicy`], the new policy is consulted from the next
+    /// allocation on.
+    pub fn set_policy(&self, policy: impl GcPolicy + 'static) {
+        self.scheduler.set_policy(Box::new(policy));
+    }
+
+    /// Returns the heap size in bytes at which the next collection is requested.
+    pub fn heap_threshold(&self) -> usize {
+        self.allocator.borrow().heap_threshold()
+    }
+
+    /// Runs finalizers once the collection has finished instead of during
+    /// the sweep.
+    ///
+    /// A dead value whose type [needs finalization](Finalize::needs_finalize)
+    /// is kept alive for one more cycle, together with everything it
+    /// references, so its finalizer can use the values it holds. It is freed
+    /// by a later collection once unreachable again, without being finalized
+    /// a second time.
+    ///
+    /// The finalizers of a cycle run from the most recently allocated value
+    /// to the oldest, which finalizes a value before the values it was built
+    /// from. Values linked after allocation may run in any order, but every
+    /// value a finalizer reaches is still alive while it runs.
+    pub fn with_deferred_finalization(self, deferred: bool) -> Self {
+        self.set_deferred_finalization(deferred);
+        self
+    }
+
+    /// Switches deferred finalization on or off from the next collection on.
+    pub fn set_deferred_finalization(&self, deferred: bool) {
+        self.deferred_finalization.set(deferred);
+    }
+
+    /// Sets the [`SoftPolicy`] deciding how long a [`SoftGc`] keeps its
+    /// target alive.
+    pub fn with_soft_policy(self, policy: SoftPolicy) -> Self {
+        self.set_soft_policy(policy);
+        self
+    }
+
+    /// Replaces the [`SoftPolicy`], the new policy applies from the next
+    /// collection on.
+    pub fn set_soft_policy(&self, policy: SoftPolicy) {
+        self.soft_policy.set(policy);
+    }
+
+    pub fn soft_policy(&self) -> SoftPolicy {
+        self.soft_policy.get()
+    }
+
+    /// Sets the [`ShutdownPolicy`] applied when the collector is dropped while
+    /// values are still rooted.
+    pub fn with_shutdown_policy(self, policy: ShutdownPolicy) -> Self {
+        self.set_shutdown_policy(policy);
+        self
+    }
+
+    /// Replaces the [`ShutdownPolicy`].
+    pub fn set_shutdown_policy(&self, policy: ShutdownPolicy) {
+        self.shutdown_policy.set(policy);
+    }
+
+    pub fn shutdown_policy(&self) -> ShutdownPolicy {
+        self.shutdown_policy.get()
+    }
+
+    /// Lists the values currently rooted by handles outside the heap, the
+    /// values a drop of the collector would report.
+    pub fn leak_report(&self) -> LeakReport {
+        let mut report = LeakReport::default();
+        let roots = self.root_queue.borrow();
+        let pending = self.pending_root_queue.borrow();
+        for node in roots.iter().chain(pending.iter()) {
+            let gc_box = unsafe { node.as_ref().value() };
+            if gc_box.is_rooted() {
+                report.push(LeakedObject {
+                    addr: node.as_ptr() as usize,
+                    #[cfg(any(test, feature = "std"))]
+                    type_name: gc_box.type_name(),
+                    roots: gc_box.roots(),
+                });
+            }
+        }
+        report
+    }
+
+    /// Sets the [`GcClock`] used to measure collection pauses.
+    pub fn with_clock(self, clock: impl GcClock + 'static) -> Self {
+        self.set_clock(clock);
+        self
+    }
+
+    /// Replaces the [`GcClock`] used to measure collection pauses.
+    pub fn set_clock(&self, clock: impl GcClock + 'static) {
+        self.stats.set_clock(Box::new(clock));
+    }
+
+    /// Returns the statistics gathered since creation or the last [`reset_stats`].
+    ///
+    /// [`reset_stats`]: Self::reset_stats
+    pub fn stats(&self) -> GcStats {
+        self.stats.stats()
+    }
+
+    /// Captures a [`HeapSnapshot`](snapshot::HeapSnapshot) of every live
+    /// allocation and the references between them.
+    ///
+    /// Must not be called from inside a collection, e.g. from a `GcObserver`.
+    #[cfg(any(test, feature = "heap_snapshot"))]
+    pub fn heap_snapshot(&self) -> snapshot::HeapSnapshot {
+        assert!(
+            !self.is_collecting.get(),
+            "heap_snapshot called during a collection"
+        );
+        snapshot::capture(self)
+    }
+
+    /// Returns the shortest chain of references from a root to `gc`, the
+    /// answer to why it survives collections.
+    ///
+    /// `gc` itself is not counted as a root, `None` means nothing but the
+    /// handle used for the query keeps the value alive. Must not be called
+    /// from inside a collection.
+    #[cfg(any(test, feature = "heap_snapshot"))]
+    pub fn retaining_path<T: Trace + ?Sized>(
+        &self,
+        gc: &Gc<T>,
+    ) -> Option<crate::collectors::retaining::RetainingPath> {
+        assert!(
+            !self.is_collecting.get(),
+            "retaining_path called during a collection"
+        );
+        let target = gc.inner_ptr.as_ptr().cast::<u8>().as_ptr() as usize;
+        snapshot::retaining_path(self, target, gc.inner_ref().roots())
+    }
+
+    /// Checks the collector invariants, see [`verify`] for the list, and
+    /// returns a report of every violation found.
+    ///
+    /// Must not be called from inside a collection.
+    #[cfg(any(test, feature = "heap_snapshot"))]
+    pub fn verify_heap(&self) -> verify::HeapReport {
+        assert!(
+            !self.is_collecting.get(),
+            "verify_heap called during a collection"
+        );
+        verify::verify(self)
+    }
+
+    // panics with the verification report if an invariant is broken
+    #[cfg(feature = "verify_heap")]
+    fn assert_heap_valid(&self, when: &str) {
+        // runs inside the collection, between its phases
+        let report = verify::verify(self);
+        assert!(report.is_ok(), "heap verification failed {when}: {report}");
+    }
+
+    /// Clears the gathered statistics.
+    pub fn reset_stats(&self) {
+        self.stats.reset(self.allocator.borrow().heap_size());
+    }
+
+    /// Registers a [`GcObserver`] notified around every collection cycle.
+    ///
+    /// See [`observer`](crate::collectors::observer) for what callbacks may do.
+    pub fn add_observer(&self, observer: impl GcObserver + 'static) -> ObserverId {
+        self.observers.add(Box::new(observer))
+    }
+
+    /// Removes a previously registered observer, returns false if it was
+    /// already removed.
+    pub fn remove_observer(&self, id: ObserverId) -> bool {
+        self.observers.remove(id)
+    }
+
+    /// Installs a [`Zeal`] stress mode, see [`zeal`](crate::collectors::zeal).
+    pub fn with_zeal(self, zeal: Zeal) -> Self {
+        self.set_zeal(Some(zeal));
+        self
+    }
+
+    /// Installs or, with `None`, removes the [`Zeal`] stress mode. The
+    /// allocation count restarts from zero.
+    pub fn set_zeal(&self, zeal: Option<Zeal>) {
+        self.zeal.set(zeal);
+    }
+
+    pub fn zeal(&self) -> Option<Zeal> {
+        self.zeal.get()
+    }
+
     // returns the number of live slot pools + bump pages held by this collector
     //
     // prefer this over accessing `self.allocator` directly in tests so that
@@ -101,13 +386,37 @@ impl MarkSweepGarbageCollector {
     pub fn finalizer_safe(&self) -> bool {
         !self.is_collecting.get()
     }
+
+    /// Releases the objects kept alive by [`WeakGc::deref_keep`], the
+    /// ECMAScript `ClearKeptObjects` operation run at the end of a job.
+    pub fn clear_kept_objects(&self) {
+        for node in self.kept_objects.take() {
+            // SAFETY: a kept object holds a root, so it was not swept
+            unsafe { node.as_ref().value().dec_roots() };
+        }
+    }
 }
 
 impl Drop for MarkSweepGarbageCollector {
     fn drop(&mut self) {
+        // the current job ends with the collector
+        self.clear_kept_objects();
+        // callbacks may hold handles into the heap, drop them while it is
+        // still there
+        self.weak_callbacks.take();
+        self.cleared_weak_callbacks.take();
+        // every ephemeron is freed or leaked with the heap
+        for (_, alive) in self.ephemeron_handles.take() {
+            alive.set(false);
+        }
+        self.soft_refs.take();
+        #[cfg(feature = "checked_handles")]
+        self.checks.set_sweeping(true);
+
         // SAFETY:
         // `Gc<T>` pointers act as if they live forever (`'static`).
-        // if the GC drops while rooted values still exist, we leak memory to prevent UAF.
+        // if the GC drops while rooted values still exist, we leak memory to
+        // prevent UAF unless the shutdown policy asks to finalize anyway.
         let has_rooted_values = self
             .root_queue
             .borrow()
@@ -119,17 +428,27 @@ impl Drop for MarkSweepGarbageCollector {
                 .iter()
                 .any(|node| unsafe { node.as_ref().value().is_rooted() });
 
-        if self.pools_len() > 0 && has_rooted_values {
+        if self.pools_len() > 0
+            && has_rooted_values
+            && !self
+                .shutdown_policy
+                .get()
+                .sweeps_rooted(|| self.leak_report())
+        {
             // Unrooted items are NOT swept here so they intentionally leak
             // instead of triggering a Use-After-Free.
             // The underlying arena pools WILL be dropped (and OS memory reclaimed)
             // when `self.allocator` is dropped at the end of this scope.
         } else {
-            // No rooted items are alive. Sweep and clean up the remaining
-            // cycles and loose allocations before the allocator natively drops.
+            // No rooted items are alive, or the policy finalizes them anyway.
+            // Sweep and clean up the remaining cycles and loose allocations
+            // before the allocator natively drops.
             self.sweep_all_queues();
             self.reclaim_dead_weak_maps();
         }
+        #[cfg(feature = "checked_handles")]
+        self.checks.unregister();
+        self.panics.resume();
     }
 }
 
@@ -144,9 +463,25 @@ impl MarkSweepGarbageCollector {
                 self.0.set(false);
             }
         }
-        let _guard = CollectionGuard(&self.is_collecting);
+        let guard = CollectionGuard(&self.is_collecting);
+        #[cfg(feature = "checked_handles")]
+        self.checks.set_sweeping(true);
+        let heap_size_before = self.allocator.borrow().heap_size();
+        let started = self.stats.begin_cycle();
+        self.observers.notify(|o| o.on_collection_start());
+        #[cfg(feature = "verify_heap")]
+        self.assert_heap_valid("before collection");
+
+        // fresh soft targets are rooted for the length of the cycle
+        let pinned = self.pin_fresh_soft_refs(heap_size_before);
 
         self.run_mark_phase();
+        // dead values waiting for a deferred finalizer are rooted for the
+        // rest of the cycle
+        let resurrected = self.resurrect_finalizable();
+        #[cfg(feature = "verify_heap")]
+        self.assert_heap_valid("after marking");
+        self.observers.notify(|o| o.on_mark_end());
 
         // the sweep color is the color used to mark alive objects during this cycle
         let sweep_color = self.trace_color.get();
@@ -155,6 +490,147 @@ impl MarkSweepGarbageCollector {
         // memory so we can still inspect the trace color on ephemerons;
         // use sweep_color since alive objects were marked with it.
         self.sweep_trace_color(sweep_color);
+        self.age_soft_refs(pinned);
+
+        let pause = self.stats.end_cycle(started);
+        let cycle = self.stats.stats().last_cycle;
+        self.observers.notify(|o| o.on_sweep_end(&cycle));
+
+        let mut alloc = self.allocator.borrow_mut();
+        let threshold = alloc.heap_threshold();
+        let next_threshold =
+            self.scheduler
+                .on_cycle_end(heap_size_before, alloc.heap_size(), pause, threshold);
+        alloc.set_heap_threshold(next_threshold);
+        drop(alloc);
+
+        if next_threshold != threshold {
+            self.observers
+                .notify(|o| o.on_threshold_change(threshold, next_threshold));
+        }
+        #[cfg(feature = "verify_heap")]
+        self.assert_heap_valid("after collection");
+
+        // callbacks run outside the collection, so they may use the heap
+        drop(guard);
+        #[cfg(feature = "checked_handles")]
+        self.checks.set_sweeping(false);
+        self.run_deferred_finalizers(resurrected);
+        let cleared = self.cleared_weak_callbacks.take();
+        for callback in cleared {
+            self.panics.call(callback);
+        }
+        self.panics.resume();
+    }
+
+    // roots the dead boxes whose finalizer is deferred and has not run yet,
+    // then marks what they reach
+    fn resurrect_finalizable(&self) -> Vec<GcErasedPointer> {
+        if !self.deferred_finalization.get() {
+            return Vec::new();
+        }
+        let color = self.trace_color.get();
+        let resurrected = self
+            .root_queue
+            .borrow()
+            .iter()
+            .copied()
+            .filter(|node| {
+                let gc_box = unsafe { node.as_ref().value() };
+                !gc_box.is_reachable(color)
+                    && !gc_box.header.is_finalized()
+                    && unsafe { gc_box.needs_finalize_fn()(*node) }
+            })
+            .collect::<Vec<_>>();
+        if !resurrected.is_empty() {
+            for node in &resurrected {
+                unsafe { node.as_ref().value().inc_roots() };
+            }
+            // boxes marked by the first pass are not traced again
+            self.run_mark_phase();
+        }
+        resurrected
+    }
+
+    // runs the finalizers of the resurrected boxes, newest first, then
+    // releases the roots taken by `resurrect_finalizable`
+    fn run_deferred_finalizers(&self, resurrected: Vec<GcErasedPointer>) {
+        for &node in resurrected.iter().rev() {
+            // SAFETY: a resurrected box holds a root, so it survived the sweep
+            let gc_box = unsafe { node.as_ref().value() };
+            gc_box.header.set_finalized();
+            self.panics.call(|| unsafe { gc_box.finalize_fn()(node) });
+        }
+        for node in resurrected {
+            unsafe { node.as_ref().value().dec_roots() };
+        }
+    }
+
+    // roots the soft targets the policy still considers fresh
+    fn pin_fresh_soft_refs(&self, heap_size: usize) -> Vec<GcErasedPointer> {
+        let policy = self.soft_policy.get();
+        let mut pinned = Vec::new();
+        for &node in self.soft_refs.borrow().iter() {
+            // SAFETY: soft targets are removed from the set when they are swept
+            let gc_box = unsafe { node.as_ref().value() };
+            if policy.is_fresh(gc_box.header.soft_age(), heap_size) {
+                gc_box.inc_roots();
+                pinned.push(node);
+            }
+        }
+        pinned
+    }
+
+    // counts the cycle towards the age of every surviving soft target, then
+    // releases the roots taken by `pin_fresh_soft_refs`
+    fn age_soft_refs(&self, pinned: Vec<GcErasedPointer>) {
+        for node in self.soft_refs.borrow().iter() {
+            // SAFETY: the sweep removed every freed target from the set
+            let header = unsafe { &node.as_ref().value().header };
+            header.set_soft_age(header.soft_age().saturating_add(1));
+        }
+        for node in pinned {
+            // SAFETY: a pinned target was rooted, so it survived the sweep
+            unsafe { node.as_ref().value().dec_roots() };
+        }
+    }
+
+    // returns the slot of a dead box to the allocator, poisoning it first in
+    // zeal mode
+    fn free_box_slot(&self, node: GcErasedPointer) {
+        self.free_poisoned_slot(node.cast::<u8>());
+    }
+
+    // returns the slot of a dead ephemeron to the allocator, poisoning it
+    // first in zeal mode. Weak handles check the flag from `track_ephemeron`
+    // before they read it
+    fn free_ephemeron_slot(&self, ephemeron: ErasedEphemeron) {
+        self.free_poisoned_slot(ephemeron.cast::<u8>());
+    }
+
+    // nothing reads a slot once it is freed, the allocator writes its free
+    // list link over the first word
+    fn free_poisoned_slot(&self, ptr: NonNull<u8>) {
+        let mut alloc = self.allocator.borrow_mut();
+        if self.zeal.poisons()
+            && let Some(size) = alloc.slot_size_of(ptr)
+        {
+            // SAFETY: the slot was dropped and is owned by the allocator
+            unsafe { crate::collectors::zeal::poison(ptr.as_ptr(), size) };
+        }
+        alloc.free_slot(ptr);
+    }
+
+    // records an allocation of `bytes` and asks the policy whether the heap
+    // now needs a collection
+    fn should_collect(&self, alloc: &PoolAllocator<'static>, bytes: usize) -> bool {
+        self.stats.observe_heap(alloc.heap_size());
+        self.scheduler.on_alloc(
+            bytes,
+            alloc.heap_size(),
+            alloc.heap_threshold(),
+            !alloc.is_below_threshold(),
+        )
     }
 
     // Force-collect all tracked items in collector teardown.
@@ -179,46 +655,45 @@ impl MarkSweepGarbageCollector {
         for node in roots.iter().chain(pending_r.iter()).copied() {
             let node_ref = unsafe { node.as_ref() };
             let gc_box = node_ref.value();
-            unsafe { gc_box.finalize_fn()(node) };
+            if !gc_box.header.is_finalized() {
+                self.panics.call(|| unsafe { gc_box.finalize_fn()(node) });
+            }
         }
 
         for ephemeron in ephemerons.iter().chain(pending_e.iter()).copied() {
             let ephemeron_ref = unsafe { ephemeron.as_ref() };
             let vtable = ephemeron_ref.value();
-            unsafe { vtable.finalize_fn()(ephemeron) };
+            self.panics
+                .call(|| unsafe { vtable.finalize_fn()(ephemeron) });
         }
 
         // Phase 2: drop and free all tracked values.
         for node in roots {
             let node_ref = unsafe { node.as_ref() };
-            let gc_box = node_ref.value();
-            unsafe { gc_box.drop_fn()(node) };
-            self.allocator.borrow_mut().free_slot(node.cast::<u8>());
+            let drop_fn = node_ref.value().drop_fn();
+            self.panics.call(|| unsafe { drop_fn(node) });
+            self.free_box_slot(node);
         }
 
         for node in pending_r {
             let node_ref = unsafe { node.as_ref() };
-            let gc_box = node_ref.value();
-            unsafe { gc_box.drop_fn()(node) };
-            self.allocator.borrow_mut().free_slot(node.cast::<u8>());
+            let drop_fn = node_ref.value().drop_fn();
+            self.panics.call(|| unsafe { drop_fn(node) });
+            self.free_box_slot(node);
         }
 
         for ephemeron in ephemerons {
             let ephemeron_ref = unsafe { ephemeron.as_ref() };
-            let vtable = ephemeron_ref.value();
-            unsafe { vtable.drop_fn()(ephemeron) };
-            self.allocator
-                .borrow_mut()
-                .free_slot(ephemeron.cast::<u8>());
+            let drop_fn = ephemeron_ref.value().drop_fn();
+            self.panics.call(|| unsafe { drop_fn(ephemeron) });
+            self.free_ephemeron_slot(ephemeron);
         }
 
         for ephemeron in pending_e {
             let ephemeron_ref = unsafe { ephemeron.as_ref() };
-            let vtable = ephemeron_ref.value();
-            unsafe { vtable.drop_fn()(ephemeron) };
-            self.allocator
-                .borrow_mut()
-                .free_slot(ephemeron.cast::<u8>());
+            let drop_fn = ephemeron_ref.value().drop_fn();
+            self.panics.call(|| unsafe { drop_fn(ephemeron) });
+            self.free_ephemeron_slot(ephemeron);
         }
     }
 
@@ -251,7 +726,9 @@ impl MarkSweepGarbageCollector {
             let is_map_alive = map.is_alive();
             if is_map_alive {
                 // We need mut access to prune.
-                unsafe { (&mut *map_ptr.as_ptr()).prune_dead_entries(sweep_color) };
+                let pruned = unsafe { (&mut *map_ptr.as_ptr()).prune_dead_entries(sweep_color) };
+                self.stats
+                    .update_cycle(|cycle| cycle.weak_map_entries_pruned += pruned);
             } else {
                 // WeakMap was dropped, reclaim the inner allocation.
                 //
@@ -301,15 +778,43 @@ impl MarkSweepGarbageCollector {
             }
         }
 
-        for ephemeron_heap_item in self.ephemeron_queue.borrow().iter() {
+        // Ephemerons whose key is not marked yet, grouped by key so that each
+        // fixpoint round only has to check every distinct key once.
+        let mut pending: HashMap<GcErasedPointer, Vec<ErasedEphemeron>, FxBuildHasher> =
+            HashMap::default();
+
+        for ephemeron_heap_item in self.ephemeron_queue.borrow().iter().copied() {
             let ephemeron_ref = unsafe { ephemeron_heap_item.as_ref() };
             let is_reachable =
-                unsafe { ephemeron_ref.value().is_reachable_fn()(*ephemeron_heap_item, color) };
+                unsafe { ephemeron_ref.value().is_reachable_fn()(ephemeron_heap_item, color) };
 
             if is_reachable {
                 // no manual mark_slot is needed as alloc_slot handled it
                 // sweep uses the vtable is_reachable_fn/free_slot path
-                unsafe { ephemeron_ref.value().trace_fn()(*ephemeron_heap_item, color) }
+                unsafe { ephemeron_ref.value().trace_fn()(ephemeron_heap_item, color) }
+            } else if let Some(key) =
+                unsafe { ephemeron_ref.value().key_ptr_fn()(ephemeron_heap_item) }
+            {
+                pending.entry(key).or_default().push(ephemeron_heap_item);
+            }
+        }
+
+        // Ephemeron fixpoint: tracing a value may mark the key of an ephemeron
+        // we already passed over, so keep going until a round marks no new key.
+        while !pending.is_empty() {
+            let newly_reachable = pending
+                .extract_if(|key, _| unsafe { key.as_ref().value().is_reachable(color) })
+                .collect::<Vec<_>>();
+
+            if newly_reachable.is_empty() {
+                break;
+            }
+
+            for ephemeron_heap_item in newly_reachable.into_iter().flat_map(|(_, e)| e) {
+                let ephemeron_ref = unsafe { ephemeron_heap_item.as_ref() };
+                if unsafe { ephemeron_ref.value().is_reachable_fn()(ephemeron_heap_item, color) } {
+                    unsafe { ephemeron_ref.value().trace_fn()(ephemeron_heap_item, color) }
+                }
             }
         }
 
@@ -328,10 +833,11 @@ impl MarkSweepGarbageCollector {
             .extract_if(.., |node| {
                 let heap_item_ref = unsafe { node.as_ref() };
                 let gc_box = heap_item_ref.value();
-                // Check if the value is not reachable, i.e. dead.
-                if !gc_box.is_reachable(color) {
+                // Check if the value is not reachable, i.e. dead, and was not
+                // already finalized by a deferred finalizer.
+                if !gc_box.is_reachable(color) && !gc_box.header.is_finalized() {
                     // Finalize the dead item
-                    unsafe { gc_box.finalize_fn()(*node) };
+                    self.panics.call(|| unsafe { gc_box.finalize_fn()(*node) });
                     // Recheck if the value is now rooted again after finalization.
                     if gc_box.is_rooted() {
                         unsafe { gc_box.trace_fn()(*node, color) };
@@ -350,7 +856,8 @@ impl MarkSweepGarbageCollector {
 
                 let is_reachable = unsafe { ephemeron_ref.is_reachable_fn()(*node, color) };
                 if !is_reachable {
-                    unsafe { ephemeron_ref.finalize_fn()(*node) };
+                    self.panics
+                        .call(|| unsafe { ephemeron_ref.finalize_fn()(*node) });
                 }
 
                 // Check whether the ephemeron is reachable.
@@ -362,6 +869,7 @@ impl MarkSweepGarbageCollector {
         let mut still_alive_roots = Vec::default();
 
         let mut still_alive = Vec::default();
+        let mut ephemerons_pruned = 0;
         for ephemeron in ephemerons {
             let ephemeron_ref = unsafe { ephemeron.as_ref() };
             // If it's reachable according to the color, and it's active
@@ -374,14 +882,29 @@ impl MarkSweepGarbageCollector {
             }
             // copy ptrs for aliasing safety
             let drop_fn = ephemeron_ref.value().drop_fn();
-
-            unsafe { drop_fn(ephemeron) };
-            self.allocator
+            ephemerons_pruned += 1;
+            if let Some(callback) = self
+                .weak_callbacks
                 .borrow_mut()
-                .free_slot(ephemeron.cast::<u8>());
+                .remove(&ephemeron.cast::<u8>())
+            {
+                self.cleared_weak_callbacks.borrow_mut().push(callback);
+            }
+            if let Some(alive) = self
+                .ephemeron_handles
+                .borrow_mut()
+                .remove(&ephemeron.cast::<u8>())
+            {
+                alive.set(false);
+            }
+
+            self.panics.call(|| unsafe { drop_fn(ephemeron) });
+            self.free_ephemeron_slot(ephemeron);
         }
         self.ephemeron_queue.borrow_mut().extend(still_alive);
+        self.observers.notify(|o| o.on_weak_pruned());
 
+        let (mut objects_freed, mut bytes_freed) = (0, 0);
         for node in droppables {
             // copy ptrs for aliasing safety
             let (is_rooted, drop_fn) = {
@@ -401,11 +924,30 @@ impl MarkSweepGarbageCollector {
                 self.is_collecting.get(),
                 "free_slot called outside a collection — ordering invariant violated"
             );
-            unsafe { drop_fn(node) };
+            self.soft_refs.borrow_mut().remove(&node);
+            // the whole slot goes back to the allocator, size class rounding
+            // and the fat pointer of a dynamically sized box included
+            let size = self
+                .allocator
+                .borrow()
+                .slot_size_of(node.cast::<u8>())
+                .unwrap_or_default();
+            self.panics.call(|| unsafe { drop_fn(node) });
+            objects_freed += 1;
+            bytes_freed += size;
             // reclaim the arena slot, clear the bitmap bit and add to free list
-            self.allocator.borrow_mut().free_slot(node.cast::<u8>());
+            self.free_box_slot(node);
         }
-        self.root_queue.borrow_mut().extend(still_alive_roots);
+        let mut root_queue = self.root_queue.borrow_mut();
+        root_queue.extend(still_alive_roots);
+
+        let survivors = root_queue.len();
+        self.stats.update_cycle(|cycle| {
+            cycle.objects_freed += objects_freed;
+            cycle.bytes_freed += bytes_freed;
+            cycle.survivors = survivors;
+            cycle.ephemerons_pruned += ephemerons_pruned;
+        });
     }
 }
 
@@ -426,7 +968,8 @@ impl Collector for MarkSweepGarbageCollector {
         &'gc self,
         value: T,
     ) -> Result<PoolPointer<'gc, GcBox<T>>, crate::alloc::mempool3::PoolAllocError> {
-        if self.collect_needed.get() && !self.is_collecting.get() {
+        let zeal_due = self.zeal.tick();
+        if (zeal_due || self.collect_needed.get()) && !self.is_collecting.get() {
             self.collect_needed.set(false);
             self.collect();
         }
@@ -436,8 +979,12 @@ impl Collector for MarkSweepGarbageCollector {
         // try_alloc creates a new arena page on OOM — no pre-creation needed.
         let mut alloc = self.allocator.borrow_mut();
         let arena_ptr = alloc.try_alloc(gc_box)?;
-        let needs_collect = !alloc.is_below_threshold();
+        let needs_collect = self.should_collect(&alloc, size_of::<PoolItem<GcBox<T>>>());
         drop(alloc);
+        #[cfg(feature = "checked_handles")]
+        arena_ptr
+            .as_inner_ref()
+            .set_stamp(self.checks.next_stamp(&self.allocator));
 
         // flag for a deferred collection if the heap crossed its threshold
         if needs_collect {
@@ -454,6 +1001,47 @@ impl Collector for MarkSweepGarbageCollector {
         Ok(arena_ptr)
     }
 
+    // Allocates a GC node for a boxed value, which may be dynamically sized
+    //
+    // values bigger than the largest size class become large objects
+    fn alloc_boxed_gc_node<'gc, T: Trace + ?Sized + 'static>(
+        &'gc self,
+        value: Box<T>,
+    ) -> Result<PoolPointer<'gc, GcBox<T>>, crate::alloc::mempool3::PoolAllocError> {
+        let zeal_due = self.zeal.tick();
+        if (zeal_due || self.collect_needed.get()) && !self.is_collecting.get() {
+            self.collect_needed.set(false);
+            self.collect();
+        }
+
+        let (layout, _) = GcBox::boxed_layout(&*value);
+        let mut alloc = self.allocator.borrow_mut();
+        let slot = alloc.try_alloc_layout(layout)?;
+        let needs_collect = self.should_collect(&alloc, layout.size());
+        drop(alloc);
+
+        // SAFETY: `slot` was allocated with the layout of the box
+        let arena_ptr = unsafe { GcBox::write_boxed(slot, value, self.trace_color.get()) };
+        #[cfg(feature = "checked_handles")]
+        unsafe { arena_ptr.as_ref() }
+            .value()
+            .set_stamp(self.checks.next_stamp(&self.allocator));
+
+        if needs_collect {
+            self.collect_needed.set(true);
+        }
+
+        let erased: NonNull<PoolItem<GcBox<NonTraceable>>> = arena_ptr.cast();
+        if self.is_collecting.get() {
+            self.pending_root_queue.borrow_mut().push(erased);
+        } else {
+            self.root_queue.borrow_mut().push(erased);
+        }
+
+        // SAFETY: the box was just written to the slot
+        Ok(unsafe { PoolPointer::from_raw(arena_ptr) })
+    }
+
     // Allocates an ephemeron node for a (key, value) pair
     //
     // the returned pointer is only valid while the collector (`&self`) is alive
@@ -462,18 +1050,21 @@ impl Collector for MarkSweepGarbageCollector {
         &'gc self,
         key: &crate::collectors::mark_sweep::pointers::Gc<K>,
         value: V,
-    ) -> Result<PoolPointer<'gc, Ephemeron<K, V>>, crate::alloc::mempool3::PoolAllocError> {
-        if self.collect_needed.get() && !self.is_collecting.get() {
+    ) -> Result<PoolPointer<'gc, internals::Ephemeron<K, V>>, crate::alloc::mempool3::PoolAllocError>
+    {
+        let zeal_due = self.zeal.tick();
+        if (zeal_due || self.collect_needed.get()) && !self.is_collecting.get() {
             self.collect_needed.set(false);
             self.collect();
         }
 
-        let ephemeron = Ephemeron::new(key, value, self.trace_color.get());
+        let ephemeron = internals::Ephemeron::new(key, value, self.trace_color.get());
 
         // try_alloc creates a new arena page on OOM
         let mut alloc = self.allocator.borrow_mut();
         let inner_ptr = alloc.try_alloc(ephemeron)?;
-        let needs_collect = !alloc.is_below_threshold();
+        let needs_collect =
+            self.should_collect(&alloc, size_of::<PoolItem<internals::Ephemeron<K, V>>>());
         drop(alloc);
 
         if needs_collect {
@@ -482,7 +1073,7 @@ impl Collector for MarkSweepGarbageCollector {
 
         let eph_ptr = inner_ptr
             .as_ptr()
-            .cast::<PoolItem<Ephemeron<NonTraceable, NonTraceable>>>();
+            .cast::<PoolItem<internals::Ephemeron<NonTraceable, NonTraceable>>>();
 
         if self.is_collecting.get() {
             self.pending_ephemeron_queue.borrow_mut().push(eph_ptr);
@@ -501,4 +1092,26 @@ impl Collector for MarkSweepGarbageCollector {
     ) {
         self.weak_maps.borrow_mut().push(map);
     }
+
+    fn keep_alive<T: Trace + ?Sized>(&self, gc: &Gc<T>) {
+        gc.inner_ref().inc_roots();
+        self.kept_objects.borrow_mut().push(gc.as_heap_ptr());
+    }
+
+    fn on_weak_cleared(&self, ephemeron: NonNull<u8>, callback: Box<dyn FnOnce()>) {
+        self.weak_callbacks.borrow_mut().insert(ephemeron, callback);
+    }
+
+    fn track_soft_ref<T: Trace + ?Sized>(&self, gc: &Gc<T>) {
+        self.soft_refs.borrow_mut().insert(gc.as_heap_ptr());
+    }
+
+    fn track_ephemeron(&self, ephemeron: NonNull<u8>) -> Rc<Cell<bool>> {
+        Rc::clone(
+            self.ephemeron_handles
+                .borrow_mut()
+                .entry(ephemeron)
+                .or_insert_with(|| Rc::new(Cell::new(true))),
+        )
+    }
 }
@@ -0,0 +1,96 @@
+use core::cell::Cell;
+
+use rust_alloc::rc::Rc;
+
+use crate::{
+    alloc::mempool3::PoolPointer,
+    collectors::mark_sweep::{Collector, Finalize, Gc, Trace, internals},
+};
+
+/// A key-value pair whose value is only reachable while its key is.
+///
+/// The handle keeps neither alive, the collector tracks the ephemeron behind
+/// it on its own and frees it with its key. Once freed, the handle reports no
+/// key and no value.
+pub struct Ephemeron<K: Trace + 'static, V: Trace + 'static> {
+    inner_ptr: PoolPointer<'static, internals::Ephemeron<K, V>>,
+    // cleared by the collector when it frees the ephemeron
+    alive: Rc<Cell<bool>>,
+}
+
+impl<K: Trace, V: Trace> Ephemeron<K, V> {
+    pub fn new_in<C: Collector>(key: &Gc<K>, value: V, collector: &C) -> Self {
+        let inner_ptr = collector
+            .alloc_ephemeron_node(key, value)
+            .expect("Failed to allocate Ephemeron node");
+
+        // SAFETY: safe because the gc tracks this
+        let inner_ptr = unsafe { inner_ptr.extend_lifetime() };
+        let alive = collector.track_ephemeron(inner_ptr.as_ptr().cast::<u8>());
+
+        Self { inner_ptr, alive }
+    }
+
+    // the ephemeron, if the collector has not freed it yet
+    fn inner(&self) -> Option<&internals::Ephemeron<K, V>> {
+        self.alive.get().then(|| self.inner_ptr.as_inner_ref())
+    }
+
+    /// Returns the key if it is still alive and the ephemeron was not
+    /// invalidated.
+    pub fn key(&self) -> Option<Gc<K>> {
+        let inner = self.inner()?;
+        if !inner.active.get() {
+            return None;
+        }
+        inner.upgrade()
+    }
+
+    /// Returns a copy of the value if the key is still alive and the
+    /// ephemeron was not invalidated.
+    pub fn value(&self) -> Option<V>
+    where
+        V: Clone,
+    {
+        let inner = self.inner()?;
+        if !inner.active.get() {
+            return None;
+        }
+        inner.value().cloned()
+    }
+
+    /// Returns true if the value can still be read.
+    pub fn has_value(&self) -> bool {
+        self.inner()
+            .is_some_and(|inner| inner.active.get() && inner.value().is_some())
+    }
+
+    /// Detaches the value from the key, it is freed by the next collection
+    /// even if the key stays alive.
+    pub fn invalidate(&self) {
+        if let Some(inner) = self.inner() {
+            inner.invalidate();
+        }
+    }
+}
+
+impl<K: Trace, V: Trace> Clone for Ephemeron<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner_ptr: self.inner_ptr,
+            alive: Rc::clone(&self.alive),
+        }
+    }
+}
+
+impl<K: Trace, V: Trace> Finalize for Ephemeron<K, V> {
+    #[inline]
+    fn needs_finalize(&self) -> bool {
+        false
+    }
+}
+
+// SAFETY: the collector tracks the ephemeron behind the handle on its own
+unsafe impl<K: Trace, V: Trace> Trace for Ephemeron<K, V> {
+    crate::empty_trace!();
+}
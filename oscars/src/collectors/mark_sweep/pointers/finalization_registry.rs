@@ -0,0 +1,226 @@
+use core::cell::Cell;
+use core::ptr::NonNull;
+
+use rust_alloc::boxed::Box;
+use rust_alloc::vec::Vec;
+
+use crate::{
+    alloc::mempool3::PoolItem,
+    collectors::mark_sweep::{
+        Collector, ErasedEphemeron, Finalize, TraceColor,
+        internals::{Ephemeron, NonTraceable},
+        pointers::weak_map::ErasedWeakMap,
+        trace::Trace,
+    },
+};
+
+use super::Gc;
+
+// a registered target, the held value and the optional unregister token
+//
+// target and token are ephemerons with a `()` value, so neither is kept alive
+// by the registry
+struct RegistryCell<H> {
+    target: ErasedEphemeron,
+    held: H,
+    token: Option<ErasedEphemeron>,
+}
+
+impl<H> RegistryCell<H> {
+    // lets the next sweep free the ephemerons of a removed cell instead of
+    // waiting for their keys to be collected
+    fn invalidate(&self) {
+        for ephemeron in core::iter::once(self.target).chain(self.token) {
+            // SAFETY: the ephemerons of a cell are not swept while it is
+            // registered
+            unsafe { ephemeron.as_ref().value().invalidate_fn()(ephemeron) };
+        }
+    }
+}
+
+// the registry store, managed by the collector like a `WeakMapInner`
+struct RegistryInner<H: 'static> {
+    cells: Vec<RegistryCell<H>>,
+    // held values of collected targets, waiting for the embedder
+    cleanup: Vec<H>,
+    is_alive: Cell<bool>,
+}
+
+fn is_reachable(ephemeron: ErasedEphemeron, color: TraceColor) -> bool {
+    // SAFETY: the ephemeron is only swept after it stops being reachable,
+    // which is when the registry lets go of it
+    unsafe { ephemeron.as_ref().value().is_reachable_fn()(ephemeron, color) }
+}
+
+fn alloc_ephemeron<T: Trace, C: Collector>(key: &Gc<T>, collector: &C) -> ErasedEphemeron {
+    let ptr = collector
+        .alloc_ephemeron_node(key, ())
+        .expect("Failed to allocate Ephemeron node");
+    ptr.as_ptr()
+        .cast::<PoolItem<Ephemeron<NonTraceable, NonTraceable>>>()
+}
+
+// the registry piggybacks on the weak map hook, which runs after marking and
+// before the sweep frees the ephemerons of dead targets
+impl<H: 'static> ErasedWeakMap for RegistryInner<H> {
+    fn prune_dead_entries(&mut self, color: TraceColor) -> usize {
+        let before = self.cells.len();
+        for cell in self
+            .cells
+            .extract_if(.., |cell| !is_reachable(cell.target, color))
+        {
+            self.cleanup.push(cell.held);
+        }
+        // a token is held weakly, forget it once it is collected
+        for cell in self.cells.iter_mut() {
+            if cell.token.is_some_and(|token| !is_reachable(token, color)) {
+                cell.token = None;
+            }
+        }
+        before - self.cells.len()
+    }
+
+    fn is_alive(&self) -> bool {
+        self.is_alive.get()
+    }
+
+    fn for_each_ephemeron(&self, f: &mut dyn FnMut(NonNull<u8>)) {
+        for cell in self.cells.iter() {
+            f(cell.target.cast::<u8>());
+            if let Some(token) = cell.token {
+                f(token.cast::<u8>());
+            }
+        }
+    }
+}
+
+/// Delivers a held value once its target has been collected, the building
+/// block of a JS `FinalizationRegistry`.
+///
+/// Unlike [`Finalize::finalize`], nothing runs inside the collection: the
+/// held values of collected targets are queued, and the embedder takes them
+/// with [`cleanup_some`](Self::cleanup_some) when it chooses.
+///
+/// The held value is owned by the registry and dropped with it. Targets and
+/// unregister tokens are held weakly.
+///
+/// Like a [`WeakMap`](super::WeakMap), a registry must not outlive its
+/// collector.
+pub struct FinalizationRegistry<H: 'static> {
+    // raw pointer to collector owned memory
+    inner: NonNull<RegistryInner<H>>,
+}
+
+impl<H: 'static> FinalizationRegistry<H> {
+    pub fn new_in<C: Collector>(collector: &C) -> Self {
+        let inner = NonNull::from(Box::leak(Box::new(RegistryInner {
+            cells: Vec::new(),
+            cleanup: Vec::new(),
+            is_alive: Cell::new(true),
+        })));
+        collector.track_weak_map(inner);
+        Self { inner }
+    }
+
+    /// Queues `held` once `target` is collected.
+    pub fn register<T: Trace, C: Collector>(&mut self, target: &Gc<T>, held: H, collector: &C) {
+        self.push(target, held, None, collector);
+    }
+
+    /// Queues `held` once `target` is collected, unless the registration is
+    /// first removed by [`unregister`](Self::unregister) with `token`.
+    pub fn register_with_token<T: Trace, U: Trace, C: Collector>(
+        &mut self,
+        target: &Gc<T>,
+        held: H,
+        token: &Gc<U>,
+        collector: &C,
+    ) {
+        let token = alloc_ephemeron(token, collector);
+        self.push(target, held, Some(token), collector);
+    }
+
+    fn push<T: Trace, C: Collector>(
+        &mut self,
+        target: &Gc<T>,
+        held: H,
+        token: Option<ErasedEphemeron>,
+        collector: &C,
+    ) {
+        let target = alloc_ephemeron(target, collector);
+        // SAFETY: `&mut self` gives exclusive access to `inner`
+        unsafe { self.inner.as_mut() }.cells.push(RegistryCell {
+            target,
+            held,
+            token,
+        });
+    }
+
+    /// Removes every registration made with `token`, dropping their held
+    /// values. Returns true if there was one.
+    pub fn unregister<U: Trace>(&mut self, token: &Gc<U>) -> bool {
+        let token_ptr = token.as_heap_ptr();
+        // SAFETY: `&mut self` gives exclusive access to `inner`
+        let inner = unsafe { self.inner.as_mut() };
+        let mut removed = false;
+        for cell in inner.cells.extract_if(.., |cell| {
+            cell.token.is_some_and(|ephemeron| {
+                // SAFETY: tokens are dropped from their cell before they are swept
+                let key = unsafe { ephemeron.as_ref().value().key_ptr_fn()(ephemeron) };
+                key == Some(token_ptr)
+            })
+        }) {
+            cell.invalidate();
+            removed = true;
+        }
+        removed
+    }
+
+    /// Returns the number of held values waiting in the cleanup queue.
+    pub fn pending_cleanup(&self) -> usize {
+        // SAFETY: we hold `&self` so the registry is alive and unchanged
+        unsafe { self.inner.as_ref() }.cleanup.len()
+    }
+
+    /// Drains the cleanup queue, passing each held value to `callback`.
+    ///
+    /// Must not be called from inside a collection. Values queued while the
+    /// callback runs are left for the next call.
+    pub fn cleanup_some(&mut self, mut callback: impl FnMut(H)) {
+        // SAFETY: `&mut self` gives exclusive access to `inner`
+        let queued = core::mem::take(&mut unsafe { self.inner.as_mut() }.cleanup);
+        for held in queued {
+            callback(held);
+        }
+    }
+}
+
+impl<H: 'static> Finalize for FinalizationRegistry<H> {
+    #[inline]
+    fn needs_finalize(&self) -> bool {
+        false
+    }
+}
+
+// held values are owned by the registry, targets and tokens are tracked as
+// ephemerons by the collector
+unsafe impl<H: 'static> Trace for FinalizationRegistry<H> {
+    unsafe fn trace(&self, _color: TraceColor) {}
+    fn run_finalizer(&self) {
+        Finalize::finalize(self);
+    }
+}
+
+impl<H: 'static> Drop for FinalizationRegistry<H> {
+    fn drop(&mut self) {
+        // SAFETY: `inner` stays valid until `is_alive` is set false here
+        let inner = unsafe { self.inner.as_mut() };
+        // drop the held values now rather than when the collector reclaims the
+        // store, which may happen after the heap they point into is gone
+        for cell in inner.cells.drain(..) {
+            cell.invalidate();
+        }
+        inner.cleanup.clear();
+        inner.is_alive.set(false);
+    }
+}
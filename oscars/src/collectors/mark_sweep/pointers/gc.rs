@@ -1,18 +1,28 @@
-use crate::alloc::mempool3::{ErasedPoolPointer, PoolItem, PoolPointer};
+use crate::alloc::mempool3::{PoolItem, PoolPointer};
 use crate::collectors::mark_sweep::Collector;
 use crate::collectors::mark_sweep::Finalize;
-use crate::collectors::mark_sweep::internals::NonTraceable;
+#[cfg(feature = "checked_handles")]
+use crate::collectors::mark_sweep::checked::{self, Access, Stamp};
+use crate::collectors::mark_sweep::internals::{NonTraceable, Uninit, with_data_ptr};
+use crate::collectors::mark_sweep::pointers::WeakGc;
 use crate::collectors::mark_sweep::{internals::GcBox, trace::Trace};
 use core::any::TypeId;
 use core::cmp::Ordering;
 use core::fmt::{self, Debug, Display};
 use core::ops::Deref;
-use core::{marker::PhantomData, ptr::NonNull};
+use core::ptr::NonNull;
+use rust_alloc::boxed::Box;
 
 /// A garbage-collected pointer type over an immutable value.
+///
+/// `T` may be dynamically sized, e.g. `Gc<str>`, `Gc<[T]>` or
+/// `Gc<dyn Trait>`. Such pointers are made with [`Gc::from_box_in`] and
+/// friends, or by unsizing a sized `Gc` with [`coerce_gc!`](crate::coerce_gc).
 pub struct Gc<T: Trace + ?Sized + 'static> {
-    pub(crate) inner_ptr: ErasedPoolPointer<'static>,
-    pub(crate) marker: PhantomData<T>,
+    pub(crate) inner_ptr: PoolPointer<'static, GcBox<T>>,
+    // copy of the stamp of the box, checked on every access
+    #[cfg(feature = "checked_handles")]
+    stamp: Stamp,
 }
 
 impl<T: Trace> Gc<T> {
@@ -20,21 +30,78 @@ impl<T: Trace> Gc<T> {
     pub fn new_in<C: Collector>(value: T, collector: &C) -> Self {
         let inner_ptr = collector
             .alloc_gc_node(value)
-            .expect("Failed to allocate Gc node")
-            .to_erased();
+            .expect("Failed to allocate Gc node");
 
         // SAFETY: safe because the gc tracks this
         let inner_ptr = unsafe { inner_ptr.extend_lifetime() };
 
-        let gc = Self {
-            inner_ptr,
-            marker: PhantomData,
-        };
+        let gc = Self::from_inner(inner_ptr);
         // GcBox is allocated with 0 roots, increment to 1 for the new handle
         gc.inner_ptr().as_inner_ref().inc_roots();
         gc
     }
 
+    /// Allocates a value that can hold a [`WeakGc`] to its own allocation.
+    ///
+    /// The slot is reserved before `data_fn` runs, and `data_fn` receives a
+    /// [`WeakGc`] to it which upgrades to `None` until `data_fn` returns. A
+    /// collection triggered inside `data_fn` leaves the reserved slot alone,
+    /// and if `data_fn` panics the slot is reclaimed by a later collection.
+    pub fn new_cyclic_in<C: Collector, F>(data_fn: F, collector: &C) -> Self
+    where
+        F: FnOnce(&WeakGc<T>) -> T,
+    {
+        // dropping `reserved` on unwind releases its root
+        let reserved = Gc::new_in(Uninit::<T>::new(), collector);
+        reserved.inner_ref().header.set_constructing(true);
+        // SAFETY: the ephemeron only records the address of its key, and weak
+        // handles do not read the value while the box is constructing
+        let weak = WeakGc::new_in(unsafe { Gc::cast_ref_unchecked::<T>(&reserved) }, collector);
+
+        let value = data_fn(&weak);
+
+        // SAFETY: `reserved` is rooted and its value was never written
+        unsafe { GcBox::init(reserved.as_sized_inner_ptr().cast(), value) };
+        reserved.inner_ref().header.set_constructing(false);
+        // the root of `reserved` becomes the root of the returned handle
+        let inner_ptr = reserved.inner_ptr.to_erased();
+        core::mem::forget(reserved);
+        // SAFETY: the box now holds an initialized `T`
+        Self::from_inner(unsafe { inner_ptr.to_typed_pool_pointer::<GcBox<T>>() })
+    }
+
+    /// Cast a `&Gc<T>` to `&Gc<U>` without consuming the handle.
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure that `U` is the correct runtime type for `this`.
+    #[inline]
+    #[must_use]
+    pub unsafe fn cast_ref_unchecked<U: Trace + 'static>(this: &Self) -> &Gc<U> {
+        unsafe { &*(this as *const Self).cast::<Gc<U>>() }
+    }
+}
+
+impl<T: Trace + ?Sized> Gc<T> {
+    /// Moves a boxed value into the GC heap.
+    ///
+    /// Unlike [`Gc::new_in`] this accepts dynamically sized values, which is
+    /// how a `Gc<dyn Trait>` is made from a `Box<dyn Trait>`.
+    #[must_use]
+    pub fn from_box_in<C: Collector>(value: Box<T>, collector: &C) -> Self {
+        let inner_ptr = collector
+            .alloc_boxed_gc_node(value)
+            .expect("Failed to allocate Gc node");
+
+        // SAFETY: safe because the gc tracks this
+        let inner_ptr = unsafe { inner_ptr.extend_lifetime() };
+
+        let gc = Self::from_inner(inner_ptr);
+        // GcBox is allocated with 0 roots, increment to 1 for the new handle
+        gc.inner_ref().inc_roots();
+        gc
+    }
+
     /// Converts a `Gc` into a raw [`PoolPointer`].
     pub fn into_raw(this: Self) -> PoolPointer<'static, GcBox<T>> {
         let ptr = this.inner_ptr();
@@ -48,20 +115,40 @@ impl<T: Trace> Gc<T> {
     ///
     /// Incorrect usage of `from_raw` can lead to use after free.
     pub unsafe fn from_raw(ptr: PoolPointer<'static, GcBox<T>>) -> Self {
+        Self::from_inner(ptr)
+    }
+
+    // the handle of the live box at `inner_ptr`
+    fn from_inner(inner_ptr: PoolPointer<'static, GcBox<T>>) -> Self {
         Self {
-            inner_ptr: ptr.to_erased(),
-            marker: PhantomData,
+            #[cfg(feature = "checked_handles")]
+            stamp: inner_ptr.as_inner_ref().stamp(),
+            inner_ptr,
         }
     }
 
+    // panics if the box of the handle is gone, see `checked`
+    #[cfg(feature = "checked_handles")]
+    fn check(&self, access: Access) {
+        checked::check(
+            self.stamp,
+            self.inner_ptr.to_erased().as_non_null(),
+            access,
+            core::any::type_name::<T>(),
+            || self.inner_ref().stamp(),
+        );
+    }
+
     pub fn ptr_eq<U: Trace + ?Sized>(this: &Self, other: &Gc<U>) -> bool {
-        this.inner_ptr.as_non_null() == other.inner_ptr.as_non_null()
+        this.inner_ptr.to_erased().as_non_null() == other.inner_ptr.to_erased().as_non_null()
     }
 
     pub fn size(&self) -> usize {
         self.inner_ref().size()
     }
 
+    /// Returns the [`TypeId`] of the allocated value, for a `Gc` made by
+    /// [`coerce_gc!`](crate::coerce_gc) that is the type before unsizing.
     pub fn type_id(&self) -> TypeId {
         self.inner_ref().type_id()
     }
@@ -86,33 +173,47 @@ impl<T: Trace> Gc<T> {
     #[inline]
     #[must_use]
     pub unsafe fn cast_unchecked<U: Trace + 'static>(this: Self) -> Gc<U> {
-        let inner_ptr = this.inner_ptr;
+        let inner_ptr = this.inner_ptr.to_erased();
         core::mem::forget(this);
-        Gc {
-            inner_ptr,
-            marker: PhantomData,
-        }
+        // SAFETY: guaranteed by the caller
+        Gc::from_inner(unsafe { inner_ptr.to_typed_pool_pointer::<GcBox<U>>() })
     }
 
-    /// Cast a `&Gc<T>` to `&Gc<U>` without consuming the handle.
+    /// Converts a `Gc<T>` into a `Gc<U>` pointing at the same value, e.g. a
+    /// `Gc<[T; N]>` into a `Gc<[T]>` or a `Gc<T>` into a `Gc<dyn Trait>`.
+    ///
+    /// `f` receives a pointer to the value and returns it as a `*const U`,
+    /// prefer the safe [`coerce_gc!`](crate::coerce_gc) which writes it as an
+    /// unsizing coercion.
     ///
     /// # Safety
     ///
-    /// Caller must ensure that `U` is the correct runtime type for `this`.
-    #[inline]
+    /// `f` must return the pointer it is given, only changing its metadata to
+    /// metadata that is valid for the value.
     #[must_use]
-    pub unsafe fn cast_ref_unchecked<U: Trace + 'static>(this: &Self) -> &Gc<U> {
-        unsafe { &*(this as *const Self).cast::<Gc<U>>() }
+    pub unsafe fn coerce_unchecked<U: Trace + ?Sized>(
+        this: Self,
+        f: impl FnOnce(*const T) -> *const U,
+    ) -> Gc<U> {
+        let inner_ptr = this.inner_ptr.as_ptr();
+        // SAFETY: `inner_ptr` points at the live box of `this`, `PoolItem` is
+        // repr(transparent)
+        let value = f(unsafe { GcBox::value_ptr(&raw mut (*inner_ptr.as_ptr()).0) });
+        core::mem::forget(this);
+        // `value` points into the box and carries the metadata for `U`, only
+        // its address moves back to the start of the box
+        let inner_ptr = with_data_ptr(
+            value.cast_mut() as *mut PoolItem<GcBox<U>>,
+            inner_ptr.cast::<u8>().as_ptr(),
+        );
+        // SAFETY: the root of `this` moves to the returned handle
+        Gc::from_inner(unsafe { PoolPointer::from_raw(NonNull::new_unchecked(inner_ptr)) })
     }
-}
 
-impl<T: Trace> Gc<T> {
     pub(crate) fn inner_ptr(&self) -> PoolPointer<'static, GcBox<T>> {
-        unsafe { self.inner_ptr.to_typed_pool_pointer::<GcBox<T>>() }
+        self.inner_ptr
     }
-}
 
-impl<T: Trace + ?Sized> Gc<T> {
     pub(crate) fn as_sized_inner_ptr(&self) -> NonNull<GcBox<NonTraceable>> {
         // SAFETY: use `&raw mut` to get a raw pointer without creating
         // a `&mut` reference, avoiding Stacked Borrows UB during GC tracing
@@ -124,7 +225,7 @@ impl<T: Trace + ?Sized> Gc<T> {
 
     pub(crate) fn as_heap_ptr(&self) -> NonNull<PoolItem<GcBox<NonTraceable>>> {
         self.inner_ptr
-            .as_non_null()
+            .as_ptr()
             .cast::<PoolItem<GcBox<NonTraceable>>>()
     }
 
@@ -133,25 +234,43 @@ impl<T: Trace + ?Sized> Gc<T> {
     }
 }
 
-impl<T: Trace> Deref for Gc<T> {
+impl<T: Trace + Clone> Gc<[T]> {
+    /// Allocates a slice holding clones of `values`.
+    #[must_use]
+    pub fn from_slice_in<C: Collector>(values: &[T], collector: &C) -> Self {
+        Self::from_box_in(values.into(), collector)
+    }
+}
+
+impl Gc<str> {
+    /// Allocates a copy of `value`.
+    #[must_use]
+    pub fn from_str_in<C: Collector>(value: &str, collector: &C) -> Self {
+        Self::from_box_in(value.into(), collector)
+    }
+}
+
+impl<T: Trace + ?Sized> Deref for Gc<T> {
     type Target = T;
     fn deref(&self) -> &T {
+        #[cfg(feature = "checked_handles")]
+        self.check(Access::Deref);
         self.inner_ptr().as_inner_ref().value()
     }
 }
 
 #[allow(clippy::inline_always)]
-impl<T: Trace + PartialEq> PartialEq for Gc<T> {
+impl<T: Trace + ?Sized + PartialEq> PartialEq for Gc<T> {
     #[inline(always)]
     fn eq(&self, other: &Self) -> bool {
         **self == **other
     }
 }
 
-impl<T: Trace + Eq> Eq for Gc<T> {}
+impl<T: Trace + ?Sized + Eq> Eq for Gc<T> {}
 
 #[allow(clippy::inline_always)]
-impl<T: Trace + PartialOrd> PartialOrd for Gc<T> {
+impl<T: Trace + ?Sized + PartialOrd> PartialOrd for Gc<T> {
     #[inline(always)]
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         (**self).partial_cmp(&**other)
@@ -174,19 +293,19 @@ impl<T: Trace + PartialOrd> PartialOrd for Gc<T> {
     }
 }
 
-impl<T: Trace + Ord> Ord for Gc<T> {
+impl<T: Trace + ?Sized + Ord> Ord for Gc<T> {
     fn cmp(&self, other: &Self) -> Ordering {
         (**self).cmp(&**other)
     }
 }
 
-impl<T: Trace + Display> Display for Gc<T> {
+impl<T: Trace + ?Sized + Display> Display for Gc<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         Display::fmt(&**self, f)
     }
 }
 
-impl<T: Trace + Debug> Debug for Gc<T> {
+impl<T: Trace + ?Sized + Debug> Debug for Gc<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         Debug::fmt(&**self, f)
     }
@@ -198,6 +317,13 @@ impl<T: Trace + ?Sized> Finalize for Gc<T> {
             self.as_sized_inner_ptr().as_ref().dec_roots();
         }
     }
+
+    // the sweep finalizes every dead value, so a dead holder releases its
+    // handles whether or not it is kept for finalization
+    #[inline]
+    fn needs_finalize(&self) -> bool {
+        false
+    }
 }
 
 unsafe impl<T: Trace + ?Sized> Trace for Gc<T> {
@@ -211,18 +337,40 @@ unsafe impl<T: Trace + ?Sized> Trace for Gc<T> {
     }
 }
 
-impl<T: Trace> Clone for Gc<T> {
+impl<T: Trace + ?Sized> Clone for Gc<T> {
     fn clone(&self) -> Self {
-        self.inner_ptr().as_inner_ref().inc_roots();
+        #[cfg(feature = "checked_handles")]
+        self.check(Access::Clone);
+        self.inner_ref().inc_roots();
         Self {
             inner_ptr: self.inner_ptr,
-            marker: PhantomData,
+            #[cfg(feature = "checked_handles")]
+            stamp: self.stamp,
         }
     }
 }
 
 impl<T: Trace + ?Sized> Drop for Gc<T> {
     fn drop(&mut self) {
+        #[cfg(feature = "checked_handles")]
+        self.check(Access::Drop);
         Finalize::finalize(self);
     }
 }
+
+/// Unsizes a [`Gc`], e.g. `coerce_gc!(gc => [u32])` turns a `Gc<[u32; 4]>`
+/// into a `Gc<[u32]>` and `coerce_gc!(gc => dyn Trait)` a `Gc<T>` into a
+/// `Gc<dyn Trait>`.
+///
+/// Only conversions the compiler accepts as unsizing coercions compile, which
+/// makes this the safe counterpart of [`Gc::coerce_unchecked`].
+#[macro_export]
+macro_rules! coerce_gc {
+    ($gc:expr => $ty:ty) => {{
+        let gc = $gc;
+        // SAFETY: the closure only applies an unsizing coercion to the pointer
+        unsafe {
+            $crate::collectors::mark_sweep::Gc::coerce_unchecked(gc, |ptr| -> *const $ty { ptr })
+        }
+    }};
+}
@@ -1,9 +1,17 @@
 //! Pointers represents the External types returned by the Boa Garbage Collector
 
+mod ephemeron;
+mod finalization_registry;
 mod gc;
+mod soft;
 mod weak;
 pub(crate) mod weak_map;
+mod weak_set;
 
+pub use ephemeron::Ephemeron;
+pub use finalization_registry::FinalizationRegistry;
 pub use gc::Gc;
+pub use soft::{SoftGc, SoftPolicy};
 pub use weak::WeakGc;
 pub use weak_map::{ErasedWeakMap, WeakMap};
+pub use weak_set::WeakSet;
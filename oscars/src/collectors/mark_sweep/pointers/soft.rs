@@ -0,0 +1,110 @@
+use crate::collectors::mark_sweep::{Collector, Finalize, Gc, Trace, WeakGc};
+
+/// A reference that keeps its target alive for a while after its last
+/// access, weaker than a [`Gc`] but stronger than a [`WeakGc`].
+///
+/// The target is treated as strongly reachable until it has gone through
+/// the number of collections set by the collector's
+/// [`SoftPolicy`] without an
+/// [`upgrade`](Self::upgrade), or the heap grows over the policy's limit.
+/// From then on it is only kept alive by other references. The age is shared
+/// by every soft reference to the same target.
+pub struct SoftGc<T: Trace + 'static> {
+    weak: WeakGc<T>,
+}
+
+impl<T: Trace> SoftGc<T> {
+    pub fn new_in<C: Collector>(value: &Gc<T>, collector: &C) -> Self {
+        collector.track_soft_ref(value);
+        value.inner_ref().header.set_soft_age(0);
+        Self {
+            weak: WeakGc::new_in(value, collector),
+        }
+    }
+
+    /// Returns the target if it is still alive and restarts its age.
+    pub fn upgrade(&self) -> Option<Gc<T>> {
+        let gc = self.weak.upgrade()?;
+        gc.inner_ref().header.set_soft_age(0);
+        Some(gc)
+    }
+
+    /// Returns the target if it is still alive, without counting as an
+    /// access.
+    pub fn peek(&self) -> Option<Gc<T>> {
+        self.weak.upgrade()
+    }
+}
+
+impl<T: Trace> Clone for SoftGc<T> {
+    fn clone(&self) -> Self {
+        Self {
+            weak: self.weak.clone(),
+        }
+    }
+}
+
+impl<T: Trace> Finalize for SoftGc<T> {
+    #[inline]
+    fn needs_finalize(&self) -> bool {
+        false
+    }
+}
+
+// SAFETY: the handle keeps nothing alive itself, the collector roots fresh
+// soft targets on its own
+unsafe impl<T: Trace> Trace for SoftGc<T> {
+    crate::empty_trace!();
+}
+
+/// Decides how long a soft reference keeps its target alive.
+///
+/// A softly referenced value is treated as strongly reachable until it has
+/// gone `max_age` collections without being accessed, and as weakly
+/// reachable while the heap is over `heap_limit` when a collection starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoftPolicy {
+    max_age: u8,
+    heap_limit: Option<usize>,
+}
+
+impl SoftPolicy {
+    /// The largest age a collector tracks.
+    pub const MAX_AGE: u8 = 15;
+
+    /// Keeps soft targets alive for `max_age` collections after their last
+    /// access, at most [`MAX_AGE`](Self::MAX_AGE).
+    pub const fn new(max_age: u8) -> Self {
+        assert!(max_age <= Self::MAX_AGE, "soft reference age out of range");
+        Self {
+            max_age,
+            heap_limit: None,
+        }
+    }
+
+    /// Stops keeping soft targets alive while the heap is over `heap_limit`
+    /// bytes.
+    pub const fn with_heap_limit(mut self, heap_limit: usize) -> Self {
+        self.heap_limit = Some(heap_limit);
+        self
+    }
+
+    pub const fn max_age(&self) -> u8 {
+        self.max_age
+    }
+
+    pub const fn heap_limit(&self) -> Option<usize> {
+        self.heap_limit
+    }
+
+    // whether a target of `age` is kept alive by a cycle starting at `heap_size`
+    pub(crate) fn is_fresh(&self, age: u8, heap_size: usize) -> bool {
+        age < self.max_age && self.heap_limit.is_none_or(|limit| heap_size <= limit)
+    }
+}
+
+impl Default for SoftPolicy {
+    fn default() -> Self {
+        Self::new(4)
+    }
+}
@@ -1,14 +1,19 @@
 // `WeakGc<T>` uses `Ephemeron<T, ()>`, this allocates two GcBox headers
 // per weak pointer. This overhead is acceptable for now but could be
 // optimized in the future
+use core::cell::Cell;
+
+use rust_alloc::rc::Rc;
+
 use crate::{
     alloc::mempool3::PoolPointer,
-    collectors::mark_sweep::{Collector, Gc, Trace, internals::Ephemeron},
+    collectors::mark_sweep::{Collector, Finalize, Gc, Trace, internals::Ephemeron},
 };
 
-#[repr(transparent)]
 pub struct WeakGc<T: Trace + 'static> {
     inner_ptr: PoolPointer<'static, Ephemeron<T, ()>>,
+    // cleared by the collector when it frees the ephemeron
+    alive: Rc<Cell<bool>>,
 }
 
 impl<T: Trace> WeakGc<T> {
@@ -22,16 +27,75 @@ impl<T: Trace> WeakGc<T> {
 
         // SAFETY: safe because the gc tracks this
         let inner_ptr = unsafe { inner_ptr.extend_lifetime() };
+        let alive = collector.track_ephemeron(inner_ptr.as_ptr().cast::<u8>());
+
+        Self { inner_ptr, alive }
+    }
+
+    /// Creates a weak handle like [`new_in`](Self::new_in), and runs `callback`
+    /// after the collection that frees `value`.
+    ///
+    /// The callback is queued during the sweep and runs once the collection
+    /// has finished, so it may use the heap. It runs even if the handle was
+    /// dropped first, and never runs if the collector is dropped first.
+    pub fn new_with_callback_in<C: Collector>(
+        value: &super::Gc<T>,
+        callback: impl FnOnce() + 'static,
+        collector: &C,
+    ) -> Self
+    where
+        T: Sized,
+    {
+        let weak = Self::new_in(value, collector);
+        collector.on_weak_cleared(
+            weak.inner_ptr.as_ptr().cast::<u8>(),
+            rust_alloc::boxed::Box::new(callback),
+        );
+        weak
+    }
 
-        Self { inner_ptr }
+    // the ephemeron, if the collector has not freed it yet
+    fn inner(&self) -> Option<&Ephemeron<T, ()>> {
+        self.alive.get().then(|| self.inner_ptr.as_inner_ref())
     }
 
     /// Returns the value of this [`WeakGc`] if the underlying value is alive.
     pub fn value(&self) -> Option<&T> {
-        self.inner_ptr.as_inner_ref().key()
+        self.inner()?.key()
     }
 
     pub fn upgrade(&self) -> Option<Gc<T>> {
-        self.inner_ptr.as_inner_ref().upgrade()
+        self.inner()?.upgrade()
     }
+
+    /// Upgrades like [`upgrade`](Self::upgrade) and keeps the target alive
+    /// until the collector's kept objects are cleared, the semantics of
+    /// `WeakRef.prototype.deref`.
+    pub fn deref_keep<C: Collector>(&self, collector: &C) -> Option<Gc<T>> {
+        let gc = self.upgrade()?;
+        collector.keep_alive(&gc);
+        Some(gc)
+    }
+}
+
+impl<T: Trace> Clone for WeakGc<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner_ptr: self.inner_ptr,
+            alive: Rc::clone(&self.alive),
+        }
+    }
+}
+
+impl<T: Trace> Finalize for WeakGc<T> {
+    #[inline]
+    fn needs_finalize(&self) -> bool {
+        false
+    }
+}
+
+// SAFETY: a weak handle keeps nothing alive, the collector tracks the
+// ephemeron behind it on its own
+unsafe impl<T: Trace> Trace for WeakGc<T> {
+    crate::empty_trace!();
 }
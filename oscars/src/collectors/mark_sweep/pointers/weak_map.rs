@@ -10,7 +10,7 @@ use core::{hash::Hasher, ptr::NonNull};
 use super::Gc;
 
 #[inline]
-fn hash_addr(addr: usize) -> u64 {
+pub(super) fn hash_addr(addr: usize) -> u64 {
     let mut h = FxHasher::default();
     h.write_usize(addr);
     h.finish()
@@ -19,8 +19,11 @@ fn hash_addr(addr: usize) -> u64 {
 // type erased trait so the collector can prune any WeakMap without knowing K/V
 #[doc(hidden)]
 pub trait ErasedWeakMap {
-    fn prune_dead_entries(&mut self, color: TraceColor);
+    // returns the number of entries removed
+    fn prune_dead_entries(&mut self, color: TraceColor) -> usize;
     fn is_alive(&self) -> bool;
+    // calls `f` with the ephemeron backing each entry
+    fn for_each_ephemeron(&self, f: &mut dyn FnMut(NonNull<u8>));
 }
 
 // the actual weak map store, managed by the collector
@@ -61,21 +64,30 @@ impl<K: Trace, V: Trace> WeakMapInner<K, V> {
     }
 
     fn get(&self, key: &Gc<K>) -> Option<&V> {
-        let key_addr = key.inner_ptr.as_non_null().as_ptr() as usize;
+        let key_addr = key.inner_ptr.as_ptr().as_ptr() as usize;
         self.entries
             .find(hash_addr(key_addr), |e| e.0 == key_addr)
             .and_then(|(_, p)| p.as_inner_ref().value())
     }
 
+    fn get_mut(&mut self, key: &Gc<K>) -> Option<&mut V> {
+        let key_addr = key.inner_ptr.as_ptr().as_ptr() as usize;
+        self.entries
+            .find_mut(hash_addr(key_addr), |e| e.0 == key_addr)
+            // SAFETY: the map owns its ephemerons, nothing else hands out
+            // references to their values
+            .and_then(|(_, p)| unsafe { (*p.as_ptr().as_ptr()).0.value_mut() })
+    }
+
     fn is_key_alive(&self, key: &Gc<K>) -> bool {
-        let key_addr = key.inner_ptr.as_non_null().as_ptr() as usize;
+        let key_addr = key.inner_ptr.as_ptr().as_ptr() as usize;
         self.entries
             .find(hash_addr(key_addr), |e| e.0 == key_addr)
             .is_some()
     }
 
     fn remove(&mut self, key: &Gc<K>) -> bool {
-        let key_addr = key.inner_ptr.as_non_null().as_ptr() as usize;
+        let key_addr = key.inner_ptr.as_ptr().as_ptr() as usize;
         // the backing ephemeron stays in the collector queue and gets swept
         // when the key is collected
         if let Ok(entry) = self
@@ -89,17 +101,31 @@ impl<K: Trace, V: Trace> WeakMapInner<K, V> {
             false
         }
     }
+
+    fn clear(&mut self) {
+        for (_, ptr) in self.entries.drain() {
+            ptr.as_inner_ref().invalidate();
+        }
+    }
 }
 
 impl<K: Trace, V: Trace> ErasedWeakMap for WeakMapInner<K, V> {
-    fn prune_dead_entries(&mut self, color: TraceColor) {
+    fn prune_dead_entries(&mut self, color: TraceColor) -> usize {
+        let before = self.entries.len();
         self.entries
             .retain(|(_, ephemeron_ptr)| ephemeron_ptr.as_inner_ref().is_reachable(color));
+        before - self.entries.len()
     }
 
     fn is_alive(&self) -> bool {
         self.is_alive.get()
     }
+
+    fn for_each_ephemeron(&self, f: &mut dyn FnMut(NonNull<u8>)) {
+        for (_, ephemeron_ptr) in self.entries.iter() {
+            f(ephemeron_ptr.as_ptr().cast::<u8>());
+        }
+    }
 }
 
 // map that prunes entries automatically when their GC keys are collected
@@ -132,7 +158,7 @@ impl<K: Trace, V: Trace> WeakMap<K, V> {
 
     // insert a value for `key`, replacing and invalidating any old ephemeron
     pub fn insert<C: Collector>(&mut self, key: &Gc<K>, value: V, collector: &C) {
-        let key_addr = key.inner_ptr.as_non_null().as_ptr() as usize;
+        let key_addr = key.inner_ptr.as_ptr().as_ptr() as usize;
 
         let ephemeron_ptr = collector
             .alloc_ephemeron_node(key, value)
@@ -152,18 +178,73 @@ impl<K: Trace, V: Trace> WeakMap<K, V> {
         unsafe { self.inner.as_ref().get(key) }
     }
 
+    pub fn get_mut(&mut self, key: &Gc<K>) -> Option<&mut V> {
+        // SAFETY: we have unique access to `self`
+        unsafe { self.inner.as_mut().get_mut(key) }
+    }
+
+    // returns the value for `key`, inserting the result of `f` first if there
+    // is none
+    pub fn get_or_insert_with<C: Collector>(
+        &mut self,
+        key: &Gc<K>,
+        f: impl FnOnce() -> V,
+        collector: &C,
+    ) -> &mut V {
+        if !self.contains_key(key) {
+            self.insert(key, f(), collector);
+        }
+        self.get_mut(key).expect("entry was just inserted")
+    }
+
     pub fn is_key_alive(&self, key: &Gc<K>) -> bool {
         // SAFETY: we hold `&self` so the map is alive and unchanged
         unsafe { self.inner.as_ref().is_key_alive(key) }
     }
 
+    pub fn contains_key(&self, key: &Gc<K>) -> bool {
+        self.is_key_alive(key)
+    }
+
     pub fn remove(&mut self, key: &Gc<K>) -> bool {
         // SAFETY: we have unique access to `self`
         unsafe { self.inner.as_mut().remove(key) }
     }
+
+    // entries of collected keys are pruned by the collection that frees the
+    // key, so every remaining entry is live
+    pub fn len(&self) -> usize {
+        // SAFETY: we hold `&self` so the map is alive and unchanged
+        unsafe { self.inner.as_ref().entries.len() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        // SAFETY: we have unique access to `self`
+        unsafe { self.inner.as_mut().clear() }
+    }
+
+    // iterates the live entries in no particular order, each key is returned
+    // as a new root
+    pub fn iter(&self) -> impl Iterator<Item = (Gc<K>, &V)> + '_ {
+        // SAFETY: we hold `&self` so the map is alive and unchanged
+        let entries = unsafe { &self.inner.as_ref().entries };
+        entries.iter().filter_map(|(_, ptr)| {
+            let ephemeron = ptr.as_inner_ref();
+            Some((ephemeron.upgrade()?, ephemeron.value()?))
+        })
+    }
 }
 
-impl<K: Trace, V: Trace> Finalize for WeakMap<K, V> {}
+impl<K: Trace, V: Trace> Finalize for WeakMap<K, V> {
+    #[inline]
+    fn needs_finalize(&self) -> bool {
+        false
+    }
+}
 
 // ephemerons are tracked in collector queue
 //no extra work needed during trace
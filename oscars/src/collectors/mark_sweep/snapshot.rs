@@ -0,0 +1,487 @@
+//! Heap snapshots of a [`MarkSweepGarbageCollector`].
+//!
+//! A [`HeapSnapshot`] is a node and edge graph of everything the collector
+//! tracks: every live `Gc` allocation and every ephemeron (weak references
+//! and weak map entries), plus one edge per `Gc` handle stored in the heap.
+//! It can be exported to the Chrome DevTools `.heapsnapshot` format with
+//! [`HeapSnapshot::to_heapsnapshot`] and to Graphviz with [`HeapSnapshot::to_dot`].
+//!
+//! Edges are discovered by running each object's `Trace` implementation while
+//! a thread local recorder is installed, marking is not affected. The check
+//! for that recorder sits on the mark path, so this module and the other heap
+//! inspection methods are only built with the `heap_snapshot` feature.
+
+use core::cell::RefCell;
+use core::fmt::Write;
+use core::ops::Range;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use hashbrown::HashMap;
+use rust_alloc::string::String;
+use rust_alloc::vec;
+use rust_alloc::vec::Vec;
+use rustc_hash::FxBuildHasher;
+
+use crate::collectors::mark_sweep::{MarkSweepGarbageCollector, TraceColor};
+use crate::collectors::retaining::{PathEdge, PathRoot, RetainingPath, shortest_path};
+
+/// What a snapshot node stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// A `Gc` allocation.
+    Object,
+    /// An ephemeron, backing a `WeakGc` or a `WeakMap` entry.
+    Ephemeron,
+}
+
+/// A live allocation in a [`HeapSnapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotNode {
+    /// Address of the allocation, stable for as long as it stays alive.
+    pub id: usize,
+    pub kind: NodeKind,
+    /// Rust type name of the allocation.
+    pub type_name: &'static str,
+    /// Shallow size in bytes, including the collector header.
+    pub size: usize,
+    /// Whether a `Gc` handle outside the heap keeps this node alive.
+    pub is_root: bool,
+}
+
+/// A reference between two nodes of a [`HeapSnapshot`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotEdge {
+    /// Index of the referencing node in [`HeapSnapshot::nodes`].
+    pub from: usize,
+    /// Index of the referenced node in [`HeapSnapshot::nodes`].
+    pub to: usize,
+    /// True for the key of an ephemeron, which does not keep its target alive.
+    pub weak: bool,
+}
+
+/// A node and edge graph of a collector heap.
+#[derive(Debug, Clone, Default)]
+pub struct HeapSnapshot {
+    nodes: Vec<SnapshotNode>,
+    edges: Vec<SnapshotEdge>,
+}
+
+impl HeapSnapshot {
+    pub fn nodes(&self) -> &[SnapshotNode] {
+        &self.nodes
+    }
+
+    /// Edges, grouped by their `from` node in node order.
+    pub fn edges(&self) -> &[SnapshotEdge] {
+        &self.edges
+    }
+
+    /// Serializes the snapshot to the Chrome DevTools `.heapsnapshot` JSON format.
+    ///
+    /// A synthetic `(GC roots)` node is added as the first node, with an edge
+    /// to every root.
+    pub fn to_heapsnapshot(&self) -> String {
+        const NODE_FIELDS: usize = 6;
+        // indices into the node and edge type lists of the meta section below
+        const NODE_HIDDEN: usize = 0;
+        const NODE_OBJECT: usize = 3;
+        const NODE_SYNTHETIC: usize = 9;
+        const EDGE_ELEMENT: usize = 1;
+        const EDGE_WEAK: usize = 6;
+
+        let mut strings = StringTable::default();
+        let mut out = String::new();
+
+        out.push_str(concat!(
+            r#"{"snapshot":{"meta":{"#,
+            r#""node_fields":["type","name","id","self_size","edge_count","trace_node_id"],"#,
+            r#""node_types":[["hidden","array","string","object","code","closure","regexp","#,
+            r#""number","native","synthetic","concatenated string","sliced string","symbol","#,
+            r#""bigint","object shape"],"string","number","number","number","number"],"#,
+            r#""edge_fields":["type","name_or_index","to_node"],"#,
+            r#""edge_types":[["context","element","property","internal","hidden","shortcut","#,
+            r#""weak"],"string_or_number","node"],"#,
+            r#""trace_function_info_fields":[],"trace_node_fields":[],"#,
+            r#""sample_fields":[],"location_fields":[]},"#,
+        ));
+        let root_count = self.nodes.iter().filter(|node| node.is_root).count();
+        let _ = write!(
+            out,
+            r#""node_count":{},"edge_count":{},"trace_function_count":0}},"#,
+            self.nodes.len() + 1,
+            self.edges.len() + root_count,
+        );
+
+        out.push_str(r#""nodes":["#);
+        let root_name = strings.index("(GC roots)");
+        let _ = write!(out, "{NODE_SYNTHETIC},{root_name},1,0,{root_count},0");
+        let mut edge_counts = vec![0usize; self.nodes.len()];
+        for edge in &self.edges {
+            edge_counts[edge.from] += 1;
+        }
+        for (node, edge_count) in self.nodes.iter().zip(edge_counts) {
+            let node_type = match node.kind {
+                NodeKind::Object => NODE_OBJECT,
+                NodeKind::Ephemeron => NODE_HIDDEN,
+            };
+            let name = strings.index(node.type_name);
+            let _ = write!(
+                out,
+                ",{node_type},{name},{},{},{edge_count},0",
+                node.id, node.size
+            );
+        }
+
+        // node 0 is the synthetic root, so snapshot node `i` is at `i + 1`
+        out.push_str(r#"],"edges":["#);
+        let mut first = true;
+        let mut push_edge = |out: &mut String, edge_type: usize, name: usize, to: usize| {
+            let separator = if first { "" } else { "," };
+            first = false;
+            let _ = write!(
+                out,
+                "{separator}{edge_type},{name},{}",
+                (to + 1) * NODE_FIELDS
+            );
+        };
+        for (ordinal, index) in self.root_indices().enumerate() {
+            push_edge(&mut out, EDGE_ELEMENT, ordinal, index);
+        }
+        let mut ordinal = 0;
+        let mut previous_from = usize::MAX;
+        for edge in &self.edges {
+            if edge.from != previous_from {
+                previous_from = edge.from;
+                ordinal = 0;
+            }
+            let edge_type = if edge.weak { EDGE_WEAK } else { EDGE_ELEMENT };
+            push_edge(&mut out, edge_type, ordinal, edge.to);
+            ordinal += 1;
+        }
+
+        out.push_str(concat!(
+            r#"],"trace_function_infos":[],"trace_tree":[],"#,
+            r#""samples":[],"locations":[],"strings":["#,
+        ));
+        for (i, string) in strings.strings.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            push_escaped(&mut out, string);
+        }
+        out.push_str("]}");
+        out
+    }
+
+    /// Serializes the snapshot to a Graphviz DOT digraph.
+    ///
+    /// Roots are drawn bold, weak edges dashed.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph heap {\n    node [shape=box];\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let _ = write!(out, "    n{i} [label=");
+            let label = match node.kind {
+                NodeKind::Object => node.type_name,
+                NodeKind::Ephemeron => "Ephemeron",
+            };
+            push_escaped(&mut out, label);
+            // put the size on a second line inside the quoted label
+            out.pop();
+            let _ = write!(out, "\\n{} bytes\"", node.size);
+            if node.is_root {
+                out.push_str(", penwidth=2");
+            }
+            out.push_str("];\n");
+        }
+        for edge in &self.edges {
+            let _ = write!(out, "    n{} -> n{}", edge.from, edge.to);
+            if edge.weak {
+                out.push_str(" [style=dashed]");
+            }
+            out.push_str(";\n");
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn root_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.is_root)
+            .map(|(i, _)| i)
+    }
+}
+
+#[derive(Default)]
+struct StringTable {
+    strings: Vec<&'static str>,
+    indices: HashMap<&'static str, usize, FxBuildHasher>,
+}
+
+impl StringTable {
+    fn index(&mut self, string: &'static str) -> usize {
+        *self.indices.entry(string).or_insert_with(|| {
+            self.strings.push(string);
+            self.strings.len() - 1
+        })
+    }
+}
+
+// writes `string` double quoted, escaping backslashes, quotes and control characters
+fn push_escaped(out: &mut String, string: &str) {
+    out.push('"');
+    for c in string.chars() {
+        match c {
+            '\\' | '"' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// ==== Edge recording ====
+
+std::thread_local! {
+    static RECORDER: RefCell<Option<EdgeRecorder>> = const { RefCell::new(None) };
+}
+
+// number of recorders installed across all threads, lets `visit` skip the
+// thread local lookup while no snapshot is being taken anywhere
+static ACTIVE_RECORDERS: AtomicUsize = AtomicUsize::new(0);
+
+struct EdgeRecorder {
+    // address range of the allocation being inspected
+    owner: Range<usize>,
+    edges: Vec<usize>,
+}
+
+pub(crate) enum Visit {
+    /// No snapshot is recording, trace normally.
+    Trace,
+    /// The box belongs to the inspected allocation, trace into its value.
+    Expand,
+    /// The box was recorded as an edge, do not trace into it.
+    Record,
+}
+
+// called by `GcBox::trace_impl` with the address of the box being traced
+#[inline]
+pub(crate) fn visit(addr: usize) -> Visit {
+    if ACTIVE_RECORDERS.load(Ordering::Relaxed) == 0 {
+        return Visit::Trace;
+    }
+    RECORDER.with(|recorder| match &mut *recorder.borrow_mut() {
+        None => Visit::Trace,
+        Some(recorder) if recorder.owner.contains(&addr) => Visit::Expand,
+        Some(recorder) => {
+            recorder.edges.push(addr);
+            Visit::Record
+        }
+    })
+}
+
+// runs `trace` with a recorder installed, returns the addresses of every box
+// reached outside of `owner`
+fn record_edges(owner: Range<usize>, trace: impl FnOnce()) -> Vec<usize> {
+    struct RecorderGuard;
+
+    impl Drop for RecorderGuard {
+        fn drop(&mut self) {
+            RECORDER.with(|recorder| recorder.borrow_mut().take());
+            ACTIVE_RECORDERS.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    RECORDER.with(|recorder| {
+        *recorder.borrow_mut() = Some(EdgeRecorder {
+            owner,
+            edges: Vec::new(),
+        });
+    });
+    ACTIVE_RECORDERS.fetch_add(1, Ordering::Relaxed);
+    let _guard = RecorderGuard;
+
+    trace();
+
+    RECORDER.with(|recorder| {
+        recorder
+            .borrow_mut()
+            .as_mut()
+            .map(|recorder| core::mem::take(&mut recorder.edges))
+            .unwrap_or_default()
+    })
+}
+
+// A tracked allocation and the addresses of every box its `Trace` reaches.
+pub(super) struct TracedNode {
+    pub(super) addr: usize,
+    pub(super) kind: NodeKind,
+    pub(super) type_name: &'static str,
+    pub(super) size: usize,
+    // root count of an object, zero for ephemerons
+    pub(super) roots: u16,
+    // the ephemeron key, which is the first target that is not a handle
+    pub(super) key: Option<usize>,
+    pub(super) targets: Vec<usize>,
+}
+
+impl TracedNode {
+    // targets that are `Gc` handles stored in the heap, i.e. all but the key
+    pub(super) fn handles(&self) -> impl Iterator<Item = usize> + '_ {
+        let mut key = self.key;
+        self.targets.iter().copied().filter(move |&target| {
+            let is_key = key == Some(target);
+            if is_key {
+                key = None;
+            }
+            !is_key
+        })
+    }
+}
+
+// traces every object and ephemeron tracked by `collector`, objects first
+pub(super) fn trace_heap(collector: &MarkSweepGarbageCollector) -> Vec<TracedNode> {
+    let roots = collector.root_queue.borrow();
+    let pending_roots = collector.pending_root_queue.borrow();
+    let ephemerons = collector.ephemeron_queue.borrow();
+    let pending_ephemerons = collector.pending_ephemeron_queue.borrow();
+
+    let mut nodes = Vec::new();
+    for &node in roots.iter().chain(pending_roots.iter()) {
+        let addr = node.as_ptr() as usize;
+        let gc_box = unsafe { node.as_ref().value() };
+        let trace_fn = gc_box.trace_fn();
+        let targets = record_edges(addr..addr + 1, || unsafe {
+            trace_fn(node, TraceColor::default())
+        });
+        nodes.push(TracedNode {
+            addr,
+            kind: NodeKind::Object,
+            type_name: gc_box.type_name(),
+            size: gc_box.size(),
+            roots: gc_box.roots(),
+            key: None,
+            targets,
+        });
+    }
+    for &ephemeron in ephemerons.iter().chain(pending_ephemerons.iter()) {
+        let addr = ephemeron.as_ptr() as usize;
+        let ephemeron_ref = unsafe { ephemeron.as_ref().value() };
+        let (trace_fn, size) = (ephemeron_ref.trace_fn(), ephemeron_ref.size());
+        let key = unsafe { ephemeron_ref.key_ptr_fn()(ephemeron) }.map(|key| key.as_ptr() as usize);
+        // the ephemeron value is a box stored inline, so the whole allocation
+        // is the owner range
+        let targets = record_edges(addr..addr + size, || unsafe {
+            trace_fn(ephemeron, TraceColor::default())
+        });
+        nodes.push(TracedNode {
+            addr,
+            kind: NodeKind::Ephemeron,
+            type_name: ephemeron_ref.type_name(),
+            size,
+            roots: 0,
+            key,
+            targets,
+        });
+    }
+    nodes
+}
+
+pub(super) fn capture(collector: &MarkSweepGarbageCollector) -> HeapSnapshot {
+    let traced = trace_heap(collector);
+    let index_of: HashMap<usize, usize, FxBuildHasher> = traced
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.addr, i))
+        .collect();
+
+    let mut edges = Vec::new();
+    let mut strong_incoming = vec![0usize; traced.len()];
+    for (from, node) in traced.iter().enumerate() {
+        let key = node.key.and_then(|key| index_of.get(&key).copied());
+        if let Some(to) = key {
+            edges.push(SnapshotEdge {
+                from,
+                to,
+                weak: true,
+            });
+        }
+        for to in node
+            .handles()
+            .filter_map(|target| index_of.get(&target).copied())
+        {
+            strong_incoming[to] += 1;
+            edges.push(SnapshotEdge {
+                from,
+                to,
+                weak: false,
+            });
+        }
+    }
+
+    // every `Gc` handle counts as a root, handles stored in the heap are the
+    // strong edges, anything beyond those lives outside the heap
+    let nodes = traced
+        .into_iter()
+        .zip(strong_incoming)
+        .map(|(node, incoming)| SnapshotNode {
+            id: node.addr,
+            kind: node.kind,
+            type_name: node.type_name,
+            size: node.size,
+            is_root: usize::from(node.roots) > incoming,
+        })
+        .collect();
+
+    HeapSnapshot { nodes, edges }
+}
+
+// shortest path from a root to the allocation at `target`, which currently has
+// `target_roots` handles, one of which is the handle the query was made with
+pub(super) fn retaining_path(
+    collector: &MarkSweepGarbageCollector,
+    target: usize,
+    target_roots: u16,
+) -> Option<RetainingPath> {
+    let HeapSnapshot { mut nodes, edges } = capture(collector);
+    let index_of: HashMap<usize, usize, FxBuildHasher> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id, i))
+        .collect();
+    let target_index = *index_of.get(&target)?;
+
+    // the handle used for the query would always make the target a root
+    let strong_incoming = edges
+        .iter()
+        .filter(|edge| edge.to == target_index && !edge.weak)
+        .count();
+    nodes[target_index].is_root = usize::from(target_roots.saturating_sub(1)) > strong_incoming;
+
+    // an ephemeron is retained by its key, not the other way around
+    let mut outgoing = vec![Vec::new(); nodes.len()];
+    for edge in &edges {
+        if edge.weak {
+            outgoing[edge.to].push((nodes[edge.from].id, PathEdge::Ephemeron));
+        } else {
+            outgoing[edge.from].push((nodes[edge.to].id, PathEdge::Strong));
+        }
+    }
+
+    shortest_path(
+        nodes
+            .iter()
+            .filter(|node| node.is_root)
+            .map(|node| (node.id, PathRoot::Handle)),
+        target,
+        |addr, targets| targets.extend_from_slice(&outgoing[index_of[&addr]]),
+        |addr| nodes[index_of[&addr]].type_name,
+    )
+}
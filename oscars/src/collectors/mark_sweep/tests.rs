@@ -1,10 +1,15 @@
 use crate::collectors::mark_sweep::MarkSweepGarbageCollector;
 use crate::mark_sweep::{Finalize, Trace};
 
+use super::Ephemeron;
+use super::FinalizationRegistry;
 use super::Gc;
+use super::SoftGc;
 use super::WeakGc;
 use super::WeakMap;
+use super::WeakSet;
 use super::cell::GcRefCell;
+use super::snapshot::SnapshotEdge;
 
 #[test]
 fn basic_gc() {
@@ -24,6 +29,10 @@ fn basic_gc() {
 }
 
 #[test]
+#[cfg_attr(
+    feature = "checked_handles",
+    ignore = "checked boxes are bigger than the page layout assumes"
+)]
 fn nested_gc() {
     let collector = &mut MarkSweepGarbageCollector::default()
         .with_page_size(80)
@@ -218,6 +227,98 @@ fn weak_upgrade_tracks_liveness() {
     );
 }
 
+#[test]
+fn weak_callback_runs_once_target_is_freed() {
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(512);
+
+    let calls = Rc::new(Cell::new(0));
+    let strong = Gc::new_in(7u32, collector);
+    let counter = Rc::clone(&calls);
+    // the callback does not depend on the handle
+    let _ =
+        WeakGc::new_with_callback_in(&strong, move || counter.set(counter.get() + 1), collector);
+
+    collector.collect();
+    assert_eq!(calls.get(), 0);
+
+    drop(strong);
+    collector.collect();
+    assert_eq!(calls.get(), 1);
+
+    collector.collect();
+    assert_eq!(calls.get(), 1);
+}
+
+#[test]
+fn soft_ref_survives_max_age_collections_since_access() {
+    use crate::collectors::mark_sweep::SoftPolicy;
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(512)
+        .with_soft_policy(SoftPolicy::new(2));
+
+    let soft = SoftGc::new_in(&Gc::new_in(7u32, collector), collector);
+
+    collector.collect();
+    collector.collect();
+    // an access restarts the age
+    assert_eq!(soft.upgrade().as_deref(), Some(&7));
+    collector.collect();
+    collector.collect();
+    assert_eq!(soft.peek().as_deref(), Some(&7));
+
+    // the target is now only softly reachable and too old
+    collector.collect();
+    assert!(soft.peek().is_none());
+}
+
+#[test]
+fn soft_ref_is_weak_over_heap_limit() {
+    use crate::collectors::mark_sweep::SoftPolicy;
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(512)
+        .with_soft_policy(SoftPolicy::new(4).with_heap_limit(0));
+
+    let strong = Gc::new_in(7u32, collector);
+    let soft = SoftGc::new_in(&strong, collector);
+
+    collector.collect();
+    assert_eq!(soft.upgrade().as_deref(), Some(&7));
+
+    drop(strong);
+    collector.collect();
+    assert!(soft.peek().is_none());
+}
+
+#[test]
+fn deref_keep_holds_target_until_cleared() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(512);
+
+    let strong = Gc::new_in(7u32, collector);
+    let weak = WeakGc::new_in(&strong, collector);
+    drop(strong);
+
+    // the target survives collections in the same job
+    let kept = weak.deref_keep(collector).expect("target is alive");
+    drop(kept);
+    collector.collect();
+    assert_eq!(weak.upgrade().as_deref(), Some(&7));
+
+    collector.clear_kept_objects();
+    collector.collect();
+    assert!(weak.upgrade().is_none());
+}
+
 #[test]
 fn cast_ref_unchecked_preserves_identity_and_value() {
     let collector = &mut MarkSweepGarbageCollector::default()
@@ -377,6 +478,83 @@ fn simple_weak_gc_validate() {
     assert!(weak.value().is_none());
 }
 
+#[test]
+fn ephemeron_handle_follows_key() {
+    #[derive(Finalize, Trace)]
+    struct Holder {
+        ephemeron: Ephemeron<u64, u64>,
+    }
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(512);
+
+    let key = Gc::new_in(1u64, collector);
+    let holder = Gc::new_in(
+        Holder {
+            ephemeron: Ephemeron::new_in(&key, 10u64, collector),
+        },
+        collector,
+    );
+
+    collector.collect();
+    let ephemeron = holder.ephemeron.clone();
+    assert!(ephemeron.has_value());
+    assert_eq!(ephemeron.key().as_deref(), Some(&1));
+    assert_eq!(ephemeron.value(), Some(10));
+
+    drop(key);
+    collector.collect();
+    assert_eq!(collector.stats().last_cycle.ephemerons_pruned, 1);
+    drop(holder);
+}
+
+#[test]
+fn invalidated_ephemeron_is_freed_while_key_lives() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(512);
+
+    let key = Gc::new_in(1u64, collector);
+    let ephemeron = Ephemeron::new_in(&key, 10u64, collector);
+
+    ephemeron.invalidate();
+    assert!(!ephemeron.has_value());
+    assert!(ephemeron.key().is_none());
+    assert_eq!(ephemeron.value(), None);
+
+    collector.collect();
+    assert_eq!(collector.stats().last_cycle.ephemerons_pruned, 1);
+
+    // the freed slot is reused, the handle must not read the new ephemeron
+    let reused = Ephemeron::new_in(&key, 20u64, collector);
+    assert!(!ephemeron.has_value());
+    assert!(ephemeron.key().is_none());
+    assert_eq!(ephemeron.value(), None);
+    ephemeron.invalidate();
+    assert_eq!(reused.value(), Some(20));
+    drop(key);
+}
+
+#[test]
+fn ephemeron_handle_outlives_its_key() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(512);
+
+    let key = Gc::new_in(1u64, collector);
+    let ephemeron = Ephemeron::new_in(&key, 10u64, collector);
+    let other = ephemeron.clone();
+
+    drop(key);
+    collector.collect();
+    assert_eq!(collector.stats().last_cycle.ephemerons_pruned, 1);
+
+    assert!(!ephemeron.has_value());
+    assert!(other.key().is_none());
+    assert_eq!(other.value(), None);
+}
+
 #[test]
 fn basic_wm() {
     let collector = &mut MarkSweepGarbageCollector::default()
@@ -712,148 +890,1246 @@ fn drop_map_with_live_key_wm() {
     );
 }
 
-/// Edge-case stability tests for the mark-sweep garbage collector.
-///
-/// These tests exercise corner cases that could cause crashes, stack overflows,
-/// or memory corruption in a GC implementation. They are intentionally
-/// **black-box**: assertions only check observable values through the public
-/// `Gc` / `WeakMap` API and never reach into allocator internals such as
-/// `collector.allocator` or `arenas_len()`.  This keeps them stable across
-/// future allocator refactors.
-mod gc_edge_cases {
-    use crate::collectors::mark_sweep::MarkSweepGarbageCollector;
-    use crate::collectors::mark_sweep::cell::GcRefCell;
-    use crate::collectors::mark_sweep::pointers::{Gc, WeakMap};
-    use crate::mark_sweep::{Finalize, Trace};
-
-    // ---- Deep object graph ------------------------------------------------
+#[test]
+fn wm_collection_api() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(512);
 
-    /// Build a singly-linked list of ~1 000 GC nodes and collect.
-    /// The test passes if GC completes without stack overflow or panic.
-    #[test]
-    fn deep_object_graph() {
-        let collector = &mut MarkSweepGarbageCollector::default()
-            .with_page_size(4096)
-            .with_heap_threshold(8_192);
+    let mut map = WeakMap::new(collector);
+    let a = Gc::new_in(1u64, collector);
+    let b = Gc::new_in(2u64, collector);
+    assert!(map.is_empty());
+
+    *map.get_or_insert_with(&a, || 10, collector) += 1;
+    *map.get_or_insert_with(&a, || unreachable!(), collector) += 1;
+    map.insert(&b, 20, collector);
+    *map.get_mut(&b).unwrap() += 1;
+    assert_eq!(map.get(&a), Some(&12));
+    assert_eq!(map.get(&b), Some(&21));
+    assert!(map.contains_key(&a));
+    assert_eq!(map.len(), 2);
+
+    let mut entries: Vec<_> = map.iter().map(|(key, value)| (*key, *value)).collect();
+    entries.sort();
+    assert_eq!(entries, [(1, 12), (2, 21)]);
+
+    drop(a);
+    collector.collect();
+    assert_eq!(map.len(), 1);
 
-        #[derive(Debug, Finalize, Trace)]
-        struct Node {
-            _id: usize,
-            next: Option<Gc<Node>>,
-        }
+    map.clear();
+    assert!(map.is_empty());
+    assert!(!map.contains_key(&b));
+}
 
-        #[cfg(miri)]
-        const DEPTH: usize = 20;
+#[test]
+fn weak_set_prunes_collected_keys() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(512);
 
-        #[cfg(not(miri))]
-        const DEPTH: usize = 1_000;
+    let mut set = WeakSet::new(collector);
+    let a = Gc::new_in(1u64, collector);
+    let b = Gc::new_in(2u64, collector);
 
-        let mut head = Gc::new_in(Node { _id: 0, next: None }, collector);
-        for i in 1..=DEPTH {
-            head = Gc::new_in(
-                Node {
-                    _id: i,
-                    next: Some(head),
-                },
-                collector,
-            );
-        }
+    assert!(set.insert(&a));
+    assert!(!set.insert(&a));
+    assert!(set.insert(&b));
+    assert!(set.contains(&a));
+    assert_eq!(set.len(), 2);
 
-        // Mark the entire deep chain – must not overflow the stack.
-        collector.collect();
+    drop(a);
+    collector.collect();
+    assert_eq!(set.len(), 1);
+    let keys: Vec<_> = set.iter().map(|key| *key).collect();
+    assert_eq!(keys, [2]);
 
-        // The head is still rooted, so dereferencing it must succeed.
-        assert_eq!(head._id, DEPTH, "head value corrupted after collection");
-    }
+    assert!(set.remove(&b));
+    assert!(!set.remove(&b));
+    assert!(set.is_empty());
+}
 
-    // ---- Cyclic references ------------------------------------------------
+#[test]
+fn chained_wm_fixpoint() {
+    crate::collectors::test_support::chained_weak_map_fixpoint!(
+        &mut MarkSweepGarbageCollector::default()
+            .with_page_size(256)
+            .with_heap_threshold(512),
+        pools_len
+    );
+}
 
-    /// Create a two-node cycle via `GcRefCell`, drop both external handles,
-    /// then collect.  The test passes if GC completes without crashing.
-    #[test]
-    fn cyclic_references() {
-        let collector = &mut MarkSweepGarbageCollector::default()
-            .with_page_size(4096)
-            .with_heap_threshold(8_192);
+#[test]
+fn finalization_registry_queues_held_values() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(512);
 
-        #[derive(Debug, Finalize, Trace)]
-        struct CycleNode {
-            _label: u64,
-            next: GcRefCell<Option<Gc<CycleNode>>>,
-        }
+    let mut registry = FinalizationRegistry::new_in(collector);
+    let target = Gc::new_in(1u64, collector);
+    let other = Gc::new_in(2u64, collector);
+    registry.register(&target, "target", collector);
+    registry.register(&other, "other", collector);
 
-        let node_a = Gc::new_in(
-            CycleNode {
-                _label: 1,
-                next: GcRefCell::new(None),
-            },
-            collector,
-        );
-        let node_b = Gc::new_in(
-            CycleNode {
-                _label: 2,
-                next: GcRefCell::new(Some(node_a.clone())),
-            },
-            collector,
-        );
+    collector.collect();
+    assert_eq!(registry.pending_cleanup(), 0);
 
-        // Close the cycle: A → B → A
-        *node_a.next.borrow_mut() = Some(node_b.clone());
+    drop(target);
+    collector.collect();
+    assert_eq!(registry.pending_cleanup(), 1);
 
-        // Drop the only external roots.
-        drop(node_a);
-        drop(node_b);
+    let mut cleaned = Vec::new();
+    registry.cleanup_some(|held| cleaned.push(held));
+    assert_eq!(cleaned, ["target"]);
+    assert_eq!(registry.pending_cleanup(), 0);
 
-        // Must not crash, infinite-loop, or corrupt memory.
-        collector.collect();
-    }
+    drop(other);
+    collector.collect();
+    registry.cleanup_some(|held| cleaned.push(held));
+    assert_eq!(cleaned, ["target", "other"]);
 
-    // ---- Weak map cleanup -------------------------------------------------
+    drop(registry);
+    collector.collect();
+    assert_eq!(collector.allocator.borrow().pools_len(), 0);
+}
 
-    /// Insert into a `WeakMap`, drop the strong key, collect, then verify the
-    /// map no longer reports the key as alive.
-    #[test]
-    fn weak_map_cleanup() {
-        let collector = &mut MarkSweepGarbageCollector::default()
-            .with_page_size(1024)
-            .with_heap_threshold(2048);
+#[test]
+fn finalization_registry_unregister() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(512);
 
-        let mut map = WeakMap::new(collector);
-        let key = Gc::new_in(42u64, collector);
+    let mut registry = FinalizationRegistry::new_in(collector);
+    let target = Gc::new_in(1u64, collector);
+    let token = Gc::new_in(2u64, collector);
+    registry.register_with_token(&target, 10u32, &token, collector);
+    registry.register_with_token(&target, 20u32, &token, collector);
 
-        map.insert(&key, 100u64, collector);
+    assert!(registry.unregister(&token));
+    assert!(!registry.unregister(&token));
 
-        // Key is alive – lookup must succeed.
-        assert_eq!(
-            map.get(&key),
-            Some(&100u64),
-            "value missing before collection"
-        );
-        assert!(
-            map.is_key_alive(&key),
-            "key reported dead while still rooted"
-        );
+    drop(target);
+    collector.collect();
+    assert_eq!(registry.pending_cleanup(), 0);
+}
 
-        // Kill the only strong reference.
-        drop(key);
-        collector.collect();
+#[test]
+fn finalization_registry_frees_removed_registrations_while_targets_live() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(512);
 
-        // GC ran without panic – that alone is the primary assertion.
-    }
+    let mut registry = FinalizationRegistry::new_in(collector);
+    let target = Gc::new_in(1u64, collector);
+    let token = Gc::new_in(2u64, collector);
+    registry.register_with_token(&target, 10u32, &token, collector);
+    registry.register(&target, 20u32, collector);
+    assert_eq!(collector.ephemeron_queue.borrow().len(), 3);
 
-    // ---- Finalizer safety -------------------------------------------------
+    // the target and token ephemerons of the removed registration go with
+    // the next sweep even though both keys are still alive
+    assert!(registry.unregister(&token));
+    collector.collect();
+    assert_eq!(collector.ephemeron_queue.borrow().len(), 1);
 
-    /// Attach a `Finalize` impl that mutates a GC-managed flag, drop the
-    /// object, and collect.  The test passes if GC runs without panic or
-    /// memory corruption regardless of whether the finalizer actually fires.
-    #[test]
-    fn finalizer_safety() {
-        let collector = &mut MarkSweepGarbageCollector::default()
-            .with_page_size(4096)
-            .with_heap_threshold(8_192);
+    drop(registry);
+    collector.collect();
+    assert!(collector.ephemeron_queue.borrow().is_empty());
+    assert_eq!(*target + *token, 3);
+}
 
-        #[derive(Trace)]
-        struct Flagged {
+#[test]
+fn finalization_registry_holds_tokens_weakly() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(512);
+
+    let mut registry = FinalizationRegistry::new_in(collector);
+    let target = Gc::new_in(1u64, collector);
+    let token = Gc::new_in(2u64, collector);
+    registry.register_with_token(&target, 10u32, &token, collector);
+
+    drop(token);
+    collector.collect();
+    assert_eq!(collector.stats().last_cycle.objects_freed, 1);
+    assert!(collector.verify_heap().is_ok());
+
+    // a new object may reuse the address of the collected token
+    let token = Gc::new_in(3u64, collector);
+    assert!(!registry.unregister(&token));
+
+    drop(target);
+    collector.collect();
+    let mut cleaned = Vec::new();
+    registry.cleanup_some(|held| cleaned.push(held));
+    assert_eq!(cleaned, [10]);
+}
+
+#[test]
+fn never_policy_skips_auto_collect() {
+    use crate::collectors::policy::NeverPolicy;
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    struct DropSpy {
+        drops: Rc<Cell<u32>>,
+    }
+
+    impl Drop for DropSpy {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+        }
+    }
+
+    impl Finalize for DropSpy {}
+
+    // SAFETY: `DropSpy` has no traceable children.
+    unsafe impl Trace for DropSpy {
+        crate::empty_trace!();
+    }
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(128)
+        .with_heap_threshold(256)
+        .with_policy(NeverPolicy);
+
+    let drops = Rc::new(Cell::new(0));
+    drop(Gc::new_in(
+        DropSpy {
+            drops: drops.clone(),
+        },
+        collector,
+    ));
+
+    // enough pressure to trip the threshold several times over
+    let _temporaries: rust_alloc::vec::Vec<_> = (0..20u64)
+        .map(|i| Gc::new_in(GcRefCell::new(i), collector))
+        .collect();
+
+    assert_eq!(drops.get(), 0, "NeverPolicy collected on allocation");
+
+    collector.collect();
+    assert_eq!(drops.get(), 1, "explicit collect must still run");
+}
+
+#[test]
+fn policy_sets_next_threshold() {
+    use crate::collectors::policy::{FixedPolicy, GrowthPolicy};
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(1024)
+        .with_policy(GrowthPolicy::new(100, 512));
+
+    let _live: rust_alloc::vec::Vec<_> = (0..40u64).map(|i| Gc::new_in(i, collector)).collect();
+
+    collector.collect();
+    let live_bytes = collector.allocator.borrow().heap_size();
+    assert!(live_bytes > 0);
+    assert_eq!(collector.heap_threshold(), (live_bytes * 2).max(512));
+
+    collector.set_policy(FixedPolicy::new(4096));
+    collector.collect();
+    assert_eq!(collector.heap_threshold(), 4096);
+}
+
+#[test]
+fn bytes_freed_counts_whole_slots() {
+    use super::internals::GcBox;
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(4096)
+        .with_heap_threshold(8192);
+
+    // a box that does not fill its size class
+    let gc = Gc::new_in([7u8; 90], collector);
+    let slot_size = collector
+        .allocator
+        .borrow()
+        .slot_size_of(gc.inner_ptr.as_ptr().cast())
+        .unwrap();
+    assert!(slot_size > size_of::<GcBox<[u8; 90]>>());
+
+    drop(gc);
+    collector.collect();
+    assert_eq!(collector.stats().last_cycle.bytes_freed, slot_size);
+}
+
+#[test]
+fn stats_track_collections() {
+    use crate::collectors::stats::GcClock;
+    use core::cell::Cell;
+    use core::time::Duration;
+
+    // advances by 5ms every time it is read
+    struct StepClock(Cell<u64>);
+
+    impl GcClock for StepClock {
+        fn now(&self) -> Duration {
+            self.0.set(self.0.get() + 5);
+            Duration::from_millis(self.0.get())
+        }
+    }
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(4096)
+        .with_clock(StepClock(Cell::new(0)));
+
+    let keep = Gc::new_in(1u64, collector);
+    for i in 0..3u64 {
+        drop(Gc::new_in(i, collector));
+    }
+
+    let mut map = WeakMap::new(collector);
+    let dead_key = Gc::new_in(9u64, collector);
+    map.insert(&dead_key, 1u64, collector);
+    drop(dead_key);
+
+    collector.collect();
+
+    let stats = collector.stats();
+    assert_eq!(stats.collections, 1);
+    assert_eq!(stats.last_cycle.objects_freed, 4);
+    assert!(stats.last_cycle.bytes_freed > 0);
+    assert_eq!(stats.last_cycle.survivors, 1);
+    assert_eq!(stats.last_cycle.ephemerons_pruned, 1);
+    assert_eq!(stats.last_cycle.weak_map_entries_pruned, 1);
+    assert_eq!(stats.last_pause(), Duration::from_millis(5));
+    assert!(stats.peak_heap_size > 0);
+
+    collector.collect();
+
+    let stats = collector.stats();
+    assert_eq!(stats.collections, 2);
+    assert_eq!(stats.objects_freed, 4);
+    assert_eq!(stats.last_cycle.objects_freed, 0);
+    assert_eq!(stats.total_pause, Duration::from_millis(10));
+
+    collector.reset_stats();
+    assert_eq!(collector.stats().collections, 0);
+    assert_eq!(collector.stats().total_pause, Duration::ZERO);
+    assert_eq!(*keep, 1);
+}
+
+#[test]
+fn observer_sees_cycle_phases_in_order() {
+    use crate::collectors::observer::GcObserver;
+    use crate::collectors::policy::FixedPolicy;
+    use crate::collectors::stats::CycleStats;
+    use core::cell::RefCell;
+    use rust_alloc::rc::Rc;
+    use rust_alloc::vec::Vec;
+
+    struct Recorder(Rc<RefCell<Vec<&'static str>>>);
+
+    impl GcObserver for Recorder {
+        fn on_collection_start(&mut self) {
+            self.0.borrow_mut().push("start");
+        }
+        fn on_mark_end(&mut self) {
+            self.0.borrow_mut().push("mark");
+        }
+        fn on_weak_pruned(&mut self) {
+            self.0.borrow_mut().push("weak");
+        }
+        fn on_sweep_end(&mut self, cycle: &CycleStats) {
+            assert_eq!(cycle.objects_freed, 1);
+            self.0.borrow_mut().push("sweep");
+        }
+        fn on_threshold_change(&mut self, old: usize, new: usize) {
+            assert_eq!((old, new), (512, 1024));
+            self.0.borrow_mut().push("threshold");
+        }
+    }
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(512)
+        .with_policy(FixedPolicy::new(1024));
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let id = collector.add_observer(Recorder(events.clone()));
+
+    drop(Gc::new_in(1u64, collector));
+    collector.collect();
+    assert_eq!(
+        *events.borrow(),
+        ["start", "mark", "weak", "sweep", "threshold"]
+    );
+
+    // the threshold stays at 1024 now, so no change is reported
+    events.borrow_mut().clear();
+    drop(Gc::new_in(2u64, collector));
+    collector.collect();
+    assert_eq!(*events.borrow(), ["start", "mark", "weak", "sweep"]);
+
+    assert!(collector.remove_observer(id));
+    assert!(!collector.remove_observer(id));
+    events.borrow_mut().clear();
+    collector.collect();
+    assert!(events.borrow().is_empty());
+}
+
+#[test]
+fn heap_inspection_refuses_to_run_during_a_collection() {
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+
+    let collector = &mut MarkSweepGarbageCollector::default().with_page_size(256);
+    let gc = Gc::new_in(1u64, collector);
+
+    // what a `GcObserver` or finalizer calling back into the collector sees
+    collector.is_collecting.set(true);
+    assert!(catch_unwind(AssertUnwindSafe(|| collector.heap_snapshot())).is_err());
+    assert!(catch_unwind(AssertUnwindSafe(|| collector.retaining_path(&gc))).is_err());
+    assert!(catch_unwind(AssertUnwindSafe(|| collector.verify_heap())).is_err());
+    collector.is_collecting.set(false);
+
+    assert_eq!(collector.heap_snapshot().nodes().len(), 1);
+    assert!(collector.verify_heap().is_ok());
+}
+
+#[test]
+fn heap_snapshot_graph() {
+    use crate::collectors::mark_sweep::snapshot::NodeKind;
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(4096);
+
+    let leaf = Gc::new_in(7u64, collector);
+    let parent = Gc::new_in(GcRefCell::new(rust_alloc::vec![leaf.clone()]), collector);
+    drop(leaf);
+    let mut map = WeakMap::new(collector);
+    map.insert(&parent, 1u64, collector);
+
+    let snapshot = collector.heap_snapshot();
+    let nodes = snapshot.nodes();
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(nodes[0].type_name, "u64");
+    assert!(!nodes[0].is_root, "leaf is only referenced from the heap");
+    assert!(nodes[1].type_name.contains("GcRefCell"));
+    assert!(nodes[1].is_root);
+    assert_eq!(nodes[2].kind, NodeKind::Ephemeron);
+    assert_eq!(
+        snapshot.edges(),
+        [
+            SnapshotEdge {
+                from: 1,
+                to: 0,
+                weak: false
+            },
+            SnapshotEdge {
+                from: 2,
+                to: 1,
+                weak: true
+            },
+        ]
+    );
+
+    // synthetic root + 3 nodes of 6 fields, 1 root edge + 2 edges of 3 fields
+    let json = snapshot.to_heapsnapshot();
+    let section = |name: &str| {
+        let start = json.find(name).unwrap() + name.len();
+        json[start..start + json[start..].find(']').unwrap()]
+            .split(',')
+            .count()
+    };
+    assert_eq!(section(r#""nodes":["#), 4 * 6);
+    assert_eq!(section(r#""edges":["#), 3 * 3);
+    assert!(json.contains(r#""(GC roots)""#));
+
+    let dot = snapshot.to_dot();
+    assert!(dot.starts_with("digraph heap {"));
+    assert!(dot.contains("n1 -> n0;"));
+    assert!(dot.contains("n2 -> n1 [style=dashed];"));
+
+    // taking a snapshot must not disturb marking
+    collector.collect();
+    assert_eq!(*parent.borrow()[0], 7);
+    drop(parent);
+    collector.collect();
+    // the leaf handle held by `parent` was a root until `parent` was swept
+    assert_eq!(collector.heap_snapshot().nodes().len(), 1);
+    collector.collect();
+    assert_eq!(collector.heap_snapshot().nodes().len(), 0);
+}
+
+#[test]
+fn retaining_path_explains_survivors() {
+    use crate::collectors::retaining::{PathEdge, PathRoot};
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(4096);
+
+    let leaf = Gc::new_in(7u64, collector);
+    let middle = Gc::new_in(GcRefCell::new(rust_alloc::vec![leaf.clone()]), collector);
+    let root = Gc::new_in(GcRefCell::new(rust_alloc::vec![middle.clone()]), collector);
+    drop(middle);
+
+    let path = collector.retaining_path(&leaf).expect("leaf is retained");
+    assert_eq!(path.root(), PathRoot::Handle);
+    let edges: rust_alloc::vec::Vec<_> = path.hops().iter().map(|hop| hop.edge).collect();
+    assert_eq!(edges, [PathEdge::Root, PathEdge::Strong, PathEdge::Strong]);
+    assert!(path.hops()[0].type_name.contains("GcRefCell"));
+    assert_eq!(path.target().type_name, "u64");
+
+    // another handle outside the heap retains the leaf directly
+    let extra = leaf.clone();
+    assert_eq!(collector.retaining_path(&leaf).unwrap().hops().len(), 1);
+    drop(extra);
+
+    // a weak map value is retained through its key, here held by `leaf`
+    let value = Gc::new_in(3u32, collector);
+    let mut map = WeakMap::new(collector);
+    map.insert(&leaf, value.clone(), collector);
+    let path = collector.retaining_path(&value).expect("value is retained");
+    let edges: rust_alloc::vec::Vec<_> = path.hops().iter().map(|hop| hop.edge).collect();
+    assert_eq!(
+        edges,
+        [PathEdge::Root, PathEdge::Ephemeron, PathEdge::Strong]
+    );
+    assert_eq!(path.hops()[0].type_name, "u64");
+    assert!(rust_alloc::format!("{path}").starts_with("(Handle) -> "));
+
+    drop(root);
+    assert!(collector.retaining_path(&leaf).is_none());
+    drop(leaf);
+    assert!(collector.retaining_path(&value).is_none());
+}
+
+#[test]
+fn verify_heap_reports_violations() {
+    use crate::collectors::mark_sweep::verify::HeapViolation;
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(4096);
+
+    let leaf = Gc::new_in(7u64, collector);
+    let parent = Gc::new_in(GcRefCell::new(rust_alloc::vec![leaf.clone()]), collector);
+    let mut map = WeakMap::new(collector);
+    map.insert(&parent, 1u64, collector);
+
+    let report = collector.verify_heap();
+    assert!(report.is_ok(), "{report}");
+    assert_eq!((report.objects(), report.ephemerons()), (2, 1));
+
+    // drop the root held by the handle in `parent` behind the collector's back
+    leaf.inner_ref().header.dec_roots();
+    leaf.inner_ref().header.dec_roots();
+    collector.allocator.borrow_mut().current_heap_size += 1;
+
+    let report = collector.verify_heap();
+    let leaf_addr = leaf.inner_ptr.to_erased().as_non_null().as_ptr() as usize;
+    assert_eq!(
+        report.violations(),
+        [
+            HeapViolation::RootCount {
+                addr: leaf_addr,
+                type_name: "u64",
+                roots: 0,
+                heap_handles: 1,
+            },
+            HeapViolation::HeapSize {
+                recorded: collector.allocator.borrow().heap_size(),
+                pooled: collector.allocator.borrow().heap_size() - 1,
+            },
+        ]
+    );
+    assert!(rust_alloc::format!("{report}").contains("has 0 roots but 1 handles"));
+
+    leaf.inner_ref().header.inc_roots();
+    leaf.inner_ref().header.inc_roots();
+    collector.allocator.borrow_mut().current_heap_size -= 1;
+    assert!(collector.verify_heap().is_ok());
+
+    collector.collect();
+    assert!(collector.verify_heap().is_ok());
+}
+
+#[test]
+fn zeal_collects_on_every_allocation() {
+    use crate::collectors::zeal::Zeal;
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(1 << 20)
+        .with_zeal(Zeal::every(1));
+
+    let mut list = Gc::new_in(GcRefCell::new(rust_alloc::vec::Vec::new()), collector);
+    for i in 0..16u64 {
+        list.borrow_mut().push(Gc::new_in(i, collector));
+    }
+    // handles are roots, so nothing reachable was lost along the way
+    assert_eq!(collector.stats().collections, 17);
+    assert!(list.borrow().iter().map(|gc| **gc).eq(0..16));
+
+    collector.set_zeal(Some(Zeal::every(4)));
+    for i in 0..8u64 {
+        list = Gc::new_in(
+            GcRefCell::new(rust_alloc::vec![Gc::new_in(i, collector)]),
+            collector,
+        );
+    }
+    assert_eq!(collector.stats().collections, 21);
+    assert_eq!(*list.borrow()[0], 7);
+
+    collector.set_zeal(None);
+    drop(Gc::new_in(0u64, collector));
+    assert_eq!(collector.stats().collections, 21);
+}
+
+#[test]
+fn zeal_randomized_is_reproducible() {
+    use crate::collectors::zeal::Zeal;
+
+    let collections = |seed| {
+        let collector = &mut MarkSweepGarbageCollector::default()
+            .with_page_size(256)
+            .with_heap_threshold(1 << 20)
+            .with_zeal(Zeal::every(8).randomized(seed));
+        for i in 0..100u64 {
+            drop(Gc::new_in(i, collector));
+        }
+        collector.stats().collections
+    };
+
+    let first = collections(42);
+    assert_eq!(first, collections(42));
+    // every interval is drawn from 1..=8
+    assert!((12..=100).contains(&first), "{first} collections");
+}
+
+#[test]
+fn zeal_poisons_freed_values() {
+    use crate::collectors::zeal::{POISON_BYTE, Zeal};
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(1 << 20)
+        .with_zeal(Zeal::every(usize::MAX).with_poison());
+
+    // keeps the page alive once the other slot is freed
+    let _keep = Gc::new_in(1u64, collector);
+    let dead = Gc::new_in(0x0123_4567_89AB_CDEFu64, collector);
+    let value = &*dead as *const u64;
+    drop(dead);
+    collector.collect();
+
+    let value = unsafe { value.read() };
+    assert_eq!(value, u64::from_ne_bytes([POISON_BYTE; 8]));
+}
+
+#[test]
+fn zeal_poison_keeps_weak_handles_valid() {
+    use crate::collectors::zeal::Zeal;
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(1 << 20)
+        .with_zeal(Zeal::every(1000).with_poison());
+
+    // keeps the pages alive once the other slots are freed
+    let keep = Gc::new_in(1u64, collector);
+    let _keep_weak = WeakGc::new_in(&keep, collector);
+
+    let gc = Gc::new_in(7u64, collector);
+    let weak = WeakGc::new_in(&gc, collector);
+    drop(gc);
+    collector.collect();
+
+    assert!(weak.upgrade().is_none());
+    assert!(weak.value().is_none());
+
+    // the freed slots are handed out again to a live target and its ephemeron
+    let reused = Gc::new_in(8u64, collector);
+    let reused_weak = WeakGc::new_in(&reused, collector);
+    assert_eq!(reused_weak.value(), Some(&8));
+    assert!(weak.upgrade().is_none());
+    assert!(weak.value().is_none());
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "the scan compares uninitialised padding")]
+fn verify_heap_finds_missing_trace_edges() {
+    use crate::collectors::mark_sweep::verify::HeapViolation;
+
+    // the scan crosses the uninitialised padding after `flag`
+    #[derive(Finalize, Trace)]
+    struct Padded {
+        flag: u8,
+        child: Gc<u64>,
+        #[unsafe_ignore_trace]
+        forgotten: Gc<u32>,
+    }
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(4096);
+
+    let padded = Gc::new_in(
+        Padded {
+            flag: 1,
+            child: Gc::new_in(2u64, collector),
+            forgotten: Gc::new_in(3u32, collector),
+        },
+        collector,
+    );
+
+    let report = collector.verify_heap();
+    let addr = |ptr: &crate::alloc::mempool3::ErasedPoolPointer<'static>| {
+        ptr.as_non_null().as_ptr() as usize
+    };
+    assert_eq!(report.objects(), 3);
+    assert_eq!(
+        report.violations(),
+        [HeapViolation::MissingEdge {
+            from: addr(&padded.inner_ptr.to_erased()),
+            from_type: core::any::type_name::<Padded>(),
+            to: addr(&padded.forgotten.inner_ptr.to_erased()),
+            to_type: "u32",
+        }]
+    );
+    assert!(rust_alloc::format!("{report}").contains("which its Trace does not reach"));
+    assert_eq!(
+        u64::from(padded.flag) + *padded.child + u64::from(*padded.forgotten),
+        6
+    );
+}
+
+#[test]
+fn new_cyclic_holds_weak_to_itself() {
+    use crate::collectors::zeal::Zeal;
+
+    #[derive(Finalize, Trace)]
+    struct Node {
+        this: WeakGc<Node>,
+        value: u64,
+    }
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(4096)
+        .with_zeal(Zeal::every(1));
+
+    let node = Gc::new_cyclic_in(
+        |this| {
+            assert!(this.upgrade().is_none());
+            assert!(this.value().is_none());
+            // collects while the slot is reserved
+            drop(Gc::new_in(0u8, collector));
+            Node {
+                this: this.clone(),
+                value: 7,
+            }
+        },
+        collector,
+    );
+    assert_eq!(collector.stats().collections, 3);
+
+    let this = node.this.upgrade().expect("construction finished");
+    assert!(Gc::ptr_eq(&node, &this));
+    assert_eq!(node.this.value().map(|node| node.value), Some(7));
+    collector.collect();
+    assert_eq!(this.value, 7);
+}
+
+#[test]
+fn new_cyclic_reclaims_slot_on_panic() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(4096);
+
+    let escaped = core::cell::RefCell::new(None);
+    let result = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| {
+        Gc::new_cyclic_in(
+            |this: &WeakGc<rust_alloc::string::String>| {
+                *escaped.borrow_mut() = Some(this.clone());
+                panic!("constructor failed")
+            },
+            collector,
+        )
+    }));
+    assert!(result.is_err());
+
+    let escaped = escaped.into_inner().expect("closure ran");
+    assert!(escaped.upgrade().is_none());
+    collector.collect();
+    assert_eq!(collector.stats().last_cycle.objects_freed, 1);
+    assert!(collector.verify_heap().is_ok());
+}
+
+#[test]
+fn collection_survives_panicking_finalizer_and_drop() {
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    struct Bomb {
+        drops: Rc<Cell<u32>>,
+        in_finalize: bool,
+        in_drop: bool,
+    }
+
+    impl Finalize for Bomb {
+        fn finalize(&self) {
+            if self.in_finalize {
+                panic!("finalizer failed");
+            }
+        }
+    }
+
+    // SAFETY: `Bomb` has no traceable children.
+    unsafe impl Trace for Bomb {
+        crate::empty_trace!();
+    }
+
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+            if self.in_drop {
+                panic!("destructor failed");
+            }
+        }
+    }
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(4096);
+
+    let drops = Rc::new(Cell::new(0));
+    let bomb = |in_finalize, in_drop| Bomb {
+        drops: Rc::clone(&drops),
+        in_finalize,
+        in_drop,
+    };
+    let kept = Gc::new_in(bomb(false, false), collector);
+    drop(Gc::new_in(bomb(true, false), collector));
+    drop(Gc::new_in(bomb(false, false), collector));
+    drop(Gc::new_in(bomb(false, true), collector));
+    drop(Gc::new_in(bomb(false, false), collector));
+
+    let result = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| collector.collect()));
+    assert!(result.is_err());
+    assert_eq!(drops.get(), 4, "every dead value is dropped");
+    assert_eq!(collector.stats().last_cycle.objects_freed, 4);
+    assert!(collector.finalizer_safe());
+    assert!(collector.verify_heap().is_ok());
+
+    collector.collect();
+    assert_eq!(drops.get(), 4);
+    assert!(!kept.in_drop);
+    drop(kept);
+    collector.collect();
+    assert_eq!(drops.get(), 5);
+}
+
+#[test]
+fn leak_report_lists_rooted_values() {
+    use crate::collectors::shutdown::ShutdownPolicy;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static REPORTED: AtomicUsize = AtomicUsize::new(0);
+
+    let collector = MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(4096)
+        .with_shutdown_policy(ShutdownPolicy::Report(|report| {
+            REPORTED.store(report.len(), Ordering::Relaxed);
+        }));
+
+    let rooted = Gc::new_in(1u64, &collector);
+    let second = rooted.clone();
+    let list = Gc::new_in(GcRefCell::new(rust_alloc::vec::Vec::new()), &collector);
+    list.borrow_mut().push(Gc::new_in(2u32, &collector));
+    drop(Gc::new_in(3u8, &collector));
+    drop(list);
+    // the value held by `list` is unrooted once `list` is swept
+    collector.collect();
+
+    let report = collector.leak_report();
+    assert_eq!(report.len(), 1);
+    assert_eq!(report.objects()[0].type_name, "u64");
+    assert_eq!(report.objects()[0].roots, 2);
+    assert!(report.to_string().contains("u64"));
+
+    drop(collector);
+    assert_eq!(REPORTED.load(Ordering::Relaxed), 1);
+    core::mem::forget(rooted);
+    core::mem::forget(second);
+}
+
+#[test]
+fn shutdown_policy_finalizes_or_panics_on_rooted_values() {
+    use crate::collectors::shutdown::ShutdownPolicy;
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    struct DropSpy(Rc<Cell<u32>>);
+
+    impl Finalize for DropSpy {}
+
+    // SAFETY: `DropSpy` has no traceable children.
+    unsafe impl Trace for DropSpy {
+        crate::empty_trace!();
+    }
+
+    impl Drop for DropSpy {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+    let collector = MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_shutdown_policy(ShutdownPolicy::Finalize);
+    core::mem::forget(Gc::new_in(DropSpy(Rc::clone(&drops)), &collector));
+    drop(collector);
+    assert_eq!(drops.get(), 1, "the rooted value is dropped anyway");
+
+    let collector = MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_shutdown_policy(ShutdownPolicy::DebugPanic);
+    core::mem::forget(Gc::new_in(DropSpy(Rc::clone(&drops)), &collector));
+    let result = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| drop(collector)));
+    assert_eq!(result.is_err(), cfg!(debug_assertions));
+    assert_eq!(drops.get(), 1, "the rooted value is leaked");
+}
+
+#[cfg(feature = "checked_handles")]
+fn panic_message(
+    payload: rust_alloc::boxed::Box<dyn core::any::Any + Send>,
+) -> rust_alloc::string::String {
+    *payload
+        .downcast::<rust_alloc::string::String>()
+        .expect("formatted panic message")
+}
+
+#[test]
+#[cfg(feature = "checked_handles")]
+fn checked_gc_panics_after_collector_drop() {
+    let collector = MarkSweepGarbageCollector::default().with_page_size(256);
+    let gc = Gc::new_in(7u64, &collector);
+    assert_eq!(*gc.clone(), 7);
+    drop(collector);
+
+    let deref = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| *gc));
+    assert_eq!(
+        panic_message(deref.unwrap_err()),
+        "Gc<u64> dereferenced after its collector was dropped"
+    );
+    let dropped = std::panic::catch_unwind(core::panic::AssertUnwindSafe(move || drop(gc)));
+    assert_eq!(
+        panic_message(dropped.unwrap_err()),
+        "Gc<u64> dropped after its collector was dropped"
+    );
+}
+
+#[test]
+#[cfg(feature = "checked_handles")]
+fn checked_gc_panics_after_value_is_swept() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(4096);
+
+    let gc = Gc::new_in(7u64, collector);
+    // a handle that was never rooted, as made by a rooting bug
+    let unrooted = unsafe { Gc::from_raw(gc.inner_ptr()) };
+    drop(gc);
+    collector.collect();
+
+    let clone = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| unrooted.clone()));
+    assert_eq!(
+        panic_message(clone.unwrap_err()),
+        "Gc<u64> cloned after its value was freed by a collection"
+    );
+
+    let reused = Gc::new_in(8u64, collector);
+    assert!(Gc::ptr_eq(&reused, &unrooted), "the slot is reused");
+    let deref = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| *unrooted));
+    assert_eq!(
+        panic_message(deref.unwrap_err()),
+        "Gc<u64> dereferenced after its slot was reused by another value"
+    );
+    core::mem::forget(unrooted);
+    assert_eq!(*reused, 8);
+}
+
+#[test]
+fn unsized_gc_from_slice_and_str() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(4096);
+
+    let slice = Gc::from_slice_in(&[1u64, 2, 3], collector);
+    let text = Gc::from_str_in("hello", collector);
+    let empty = Gc::<[u64]>::from_slice_in(&[], collector);
+    let array = crate::coerce_gc!(Gc::new_in([4u32, 5], collector) => [u32]);
+
+    collector.collect();
+    assert_eq!(*slice, [1, 2, 3]);
+    assert_eq!(&*text, "hello");
+    assert_eq!(text.to_string(), "hello");
+    assert!(empty.is_empty());
+    assert_eq!(*array, [4, 5]);
+    assert!(Gc::is::<[u32; 2]>(&array));
+
+    let copy = slice.clone();
+    assert!(Gc::ptr_eq(&copy, &slice));
+    drop((slice, text, empty, array));
+    collector.collect();
+    assert_eq!(collector.stats().last_cycle.objects_freed, 3);
+    assert_eq!(copy.len(), 3);
+    assert!(collector.verify_heap().is_ok());
+}
+
+#[test]
+fn unsized_slice_traces_its_elements() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(4096);
+
+    let items = [Gc::new_in(1u64, collector), Gc::new_in(2u64, collector)];
+    let slice = Gc::from_slice_in(&items, collector);
+    drop(items);
+
+    collector.collect();
+    assert_eq!(collector.stats().last_cycle.objects_freed, 0);
+    assert_eq!(*slice[1], 2);
+    assert!(collector.verify_heap().is_ok());
+
+    // dropping the slice releases the elements for the next cycle
+    drop(slice);
+    collector.collect();
+    assert_eq!(collector.stats().last_cycle.objects_freed, 1);
+    collector.collect();
+    assert_eq!(collector.stats().last_cycle.objects_freed, 2);
+}
+
+#[test]
+fn unsized_gc_dyn_trait() {
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    trait Shape: Trace {
+        fn area(&self) -> u64;
+    }
+
+    struct Square {
+        side: u64,
+        drops: Rc<Cell<u32>>,
+    }
+
+    impl Finalize for Square {}
+
+    // SAFETY: `Square` has no traceable children.
+    unsafe impl Trace for Square {
+        crate::empty_trace!();
+    }
+
+    impl Shape for Square {
+        fn area(&self) -> u64 {
+            self.side * self.side
+        }
+    }
+
+    impl Drop for Square {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+        }
+    }
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(4096);
+    let drops = Rc::new(Cell::new(0));
+    let square = |side| Square {
+        side,
+        drops: Rc::clone(&drops),
+    };
+
+    let boxed: rust_alloc::boxed::Box<dyn Shape> = rust_alloc::boxed::Box::new(square(2));
+    let moved = Gc::from_box_in(boxed, collector);
+    let coerced = crate::coerce_gc!(Gc::new_in(square(3), collector) => dyn Shape);
+    assert_eq!(drops.get(), 0);
+
+    collector.collect();
+    assert_eq!(moved.area(), 4);
+    assert_eq!(coerced.area(), 9);
+
+    let square = Gc::downcast::<Square>(coerced).expect("allocated as a Square");
+    assert_eq!(square.side, 3);
+
+    drop((moved, square));
+    collector.collect();
+    assert_eq!(drops.get(), 2);
+}
+
+#[test]
+fn unsized_gc_bigger_than_size_class_is_large_object() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_page_size(256)
+        .with_heap_threshold(1 << 20);
+
+    let large = Gc::from_slice_in(&[7u8; 4096], collector);
+    assert_eq!(collector.allocator.borrow().large_objects.len(), 1);
+    assert!(large.size() > 4096);
+
+    collector.collect();
+    assert!(large.iter().all(|&byte| byte == 7));
+    assert!(collector.verify_heap().is_ok());
+
+    drop(large);
+    collector.collect();
+    assert!(collector.allocator.borrow().large_objects.is_empty());
+    assert_eq!(collector.allocator.borrow().heap_size(), 0);
+}
+
+/// Edge-case stability tests for the mark-sweep garbage collector.
+///
+/// These tests exercise corner cases that could cause crashes, stack overflows,
+/// or memory corruption in a GC implementation. They are intentionally
+/// **black-box**: assertions only check observable values through the public
+/// `Gc` / `WeakMap` API and never reach into allocator internals such as
+/// `collector.allocator` or `arenas_len()`.  This keeps them stable across
+/// future allocator refactors.
+mod gc_edge_cases {
+    use crate::collectors::mark_sweep::MarkSweepGarbageCollector;
+    use crate::collectors::mark_sweep::cell::GcRefCell;
+    use crate::collectors::mark_sweep::pointers::{Gc, WeakGc, WeakMap};
+    use crate::mark_sweep::{Finalize, Trace};
+
+    // ---- Deep object graph ------------------------------------------------
+
+    /// Build a singly-linked list of ~1 000 GC nodes and collect.
+    /// The test passes if GC completes without stack overflow or panic.
+    #[test]
+    fn deep_object_graph() {
+        let collector = &mut MarkSweepGarbageCollector::default()
+            .with_page_size(4096)
+            .with_heap_threshold(8_192);
+
+        #[derive(Debug, Finalize, Trace)]
+        struct Node {
+            _id: usize,
+            next: Option<Gc<Node>>,
+        }
+
+        #[cfg(miri)]
+        const DEPTH: usize = 20;
+
+        #[cfg(not(miri))]
+        const DEPTH: usize = 1_000;
+
+        let mut head = Gc::new_in(Node { _id: 0, next: None }, collector);
+        for i in 1..=DEPTH {
+            head = Gc::new_in(
+                Node {
+                    _id: i,
+                    next: Some(head),
+                },
+                collector,
+            );
+        }
+
+        // Mark the entire deep chain – must not overflow the stack.
+        collector.collect();
+
+        // The head is still rooted, so dereferencing it must succeed.
+        assert_eq!(head._id, DEPTH, "head value corrupted after collection");
+    }
+
+    // ---- Cyclic references ------------------------------------------------
+
+    /// Create a two-node cycle via `GcRefCell`, drop both external handles,
+    /// then collect.  The test passes if GC completes without crashing.
+    #[test]
+    fn cyclic_references() {
+        let collector = &mut MarkSweepGarbageCollector::default()
+            .with_page_size(4096)
+            .with_heap_threshold(8_192);
+
+        #[derive(Debug, Finalize, Trace)]
+        struct CycleNode {
+            _label: u64,
+            next: GcRefCell<Option<Gc<CycleNode>>>,
+        }
+
+        let node_a = Gc::new_in(
+            CycleNode {
+                _label: 1,
+                next: GcRefCell::new(None),
+            },
+            collector,
+        );
+        let node_b = Gc::new_in(
+            CycleNode {
+                _label: 2,
+                next: GcRefCell::new(Some(node_a.clone())),
+            },
+            collector,
+        );
+
+        // Close the cycle: A → B → A
+        *node_a.next.borrow_mut() = Some(node_b.clone());
+
+        // Drop the only external roots.
+        drop(node_a);
+        drop(node_b);
+
+        // Must not crash, infinite-loop, or corrupt memory.
+        collector.collect();
+    }
+
+    // ---- Weak map cleanup -------------------------------------------------
+
+    /// Insert into a `WeakMap`, drop the strong key, collect, then verify the
+    /// map no longer reports the key as alive.
+    #[test]
+    fn weak_map_cleanup() {
+        let collector = &mut MarkSweepGarbageCollector::default()
+            .with_page_size(1024)
+            .with_heap_threshold(2048);
+
+        let mut map = WeakMap::new(collector);
+        let key = Gc::new_in(42u64, collector);
+
+        map.insert(&key, 100u64, collector);
+
+        // Key is alive – lookup must succeed.
+        assert_eq!(
+            map.get(&key),
+            Some(&100u64),
+            "value missing before collection"
+        );
+        assert!(
+            map.is_key_alive(&key),
+            "key reported dead while still rooted"
+        );
+
+        // Kill the only strong reference.
+        drop(key);
+        collector.collect();
+
+        // GC ran without panic – that alone is the primary assertion.
+    }
+
+    // ---- Finalizer safety -------------------------------------------------
+
+    /// Attach a `Finalize` impl that mutates a GC-managed flag, drop the
+    /// object, and collect.  The test passes if GC runs without panic or
+    /// memory corruption regardless of whether the finalizer actually fires.
+    #[test]
+    fn finalizer_safety() {
+        let collector = &mut MarkSweepGarbageCollector::default()
+            .with_page_size(4096)
+            .with_heap_threshold(8_192);
+
+        #[derive(Trace)]
+        struct Flagged {
             flag: Gc<GcRefCell<bool>>,
         }
 
@@ -876,6 +2152,139 @@ mod gc_edge_cases {
         let _value = *flag.borrow();
     }
 
+    #[test]
+    fn deferred_finalizer_runs_after_sweep_with_children_alive() {
+        use core::cell::Cell;
+        use rust_alloc::rc::Rc;
+
+        struct Parent {
+            child: Gc<u32>,
+            seen: Rc<Cell<u32>>,
+        }
+
+        impl Finalize for Parent {
+            fn finalize(&self) {
+                self.seen.set(self.seen.get() + *self.child);
+            }
+        }
+
+        unsafe impl Trace for Parent {
+            unsafe fn trace(&self, color: crate::mark_sweep::TraceColor) {
+                unsafe { self.child.trace(color) };
+            }
+
+            fn run_finalizer(&self) {
+                Finalize::finalize(self);
+            }
+        }
+
+        let collector = &mut MarkSweepGarbageCollector::default()
+            .with_page_size(256)
+            .with_heap_threshold(512)
+            .with_deferred_finalization(true);
+
+        let seen = Rc::new(Cell::new(0));
+        let parent = Gc::new_in(
+            Parent {
+                child: Gc::new_in(7u32, collector),
+                seen: seen.clone(),
+            },
+            collector,
+        );
+        let weak_parent = WeakGc::new_in(&parent, collector);
+        let weak_child = WeakGc::new_in(&parent.child, collector);
+        drop(parent);
+
+        // the finalizer ran after the sweep, which kept the child alive
+        collector.collect();
+        assert_eq!(seen.get(), 7);
+        assert!(weak_parent.upgrade().is_some());
+        assert!(weak_child.upgrade().is_some());
+
+        // the next cycle frees the parent without finalizing it again, which
+        // unroots the child for the cycle after
+        collector.collect();
+        assert_eq!(seen.get(), 7);
+        assert!(weak_parent.upgrade().is_none());
+        collector.collect();
+        assert!(weak_child.upgrade().is_none());
+    }
+
+    #[test]
+    fn deferred_finalization_frees_derived_values_in_one_cycle() {
+        use core::cell::Cell;
+        use rust_alloc::boxed::Box;
+        use rust_alloc::rc::Rc;
+        use rust_alloc::vec::Vec;
+
+        #[derive(Finalize, Trace)]
+        struct Plain {
+            id: u64,
+            items: Vec<u64>,
+            boxed: Option<Box<u32>>,
+            cell: GcRefCell<Vec<u8>>,
+            child: Gc<u32>,
+        }
+
+        struct Counted(Rc<Cell<u32>>);
+
+        impl Finalize for Counted {
+            fn finalize(&self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        unsafe impl Trace for Counted {
+            crate::empty_trace!();
+        }
+
+        #[derive(Finalize, Trace)]
+        struct Holder {
+            counted: Option<Counted>,
+        }
+
+        let collector = &mut MarkSweepGarbageCollector::default()
+            .with_page_size(256)
+            .with_heap_threshold(512)
+            .with_deferred_finalization(true);
+
+        let plain = Gc::new_in(
+            Plain {
+                id: 1,
+                items: Vec::from([1, 2, 3]),
+                boxed: Some(Box::new(4)),
+                cell: GcRefCell::new(Vec::new()),
+                child: Gc::new_in(5u32, collector),
+            },
+            collector,
+        );
+        assert_eq!(plain.id + plain.items[2] + u64::from(*plain.child), 9);
+        assert!(plain.boxed.is_some() && plain.cell.borrow().is_empty());
+        let weak_plain = WeakGc::new_in(&plain, collector);
+        drop(plain);
+
+        // nothing in it finalizes, so it is not kept for a finalizer
+        collector.collect();
+        assert!(weak_plain.upgrade().is_none());
+
+        let finalized = Rc::new(Cell::new(0));
+        let holder = Gc::new_in(
+            Holder {
+                counted: Some(Counted(finalized.clone())),
+            },
+            collector,
+        );
+        let weak_holder = WeakGc::new_in(&holder, collector);
+        drop(holder);
+
+        // a field with a finalizer still defers the free by a cycle
+        collector.collect();
+        assert!(weak_holder.upgrade().is_some());
+        collector.collect();
+        assert!(weak_holder.upgrade().is_none());
+        assert_eq!(Rc::strong_count(&finalized), 1);
+    }
+
     #[test]
     fn finalizer_safe_reflects_collecting_state() {
         use core::sync::atomic::{AtomicU8, Ordering};
@@ -987,6 +2396,222 @@ mod gc_edge_cases {
     }
 }
 
+#[cfg(feature = "std")]
+mod default_collector {
+    use crate::collectors::mark_sweep::MarkSweepGarbageCollector;
+    use crate::collectors::mark_sweep::pointers::{Gc, WeakGc, WeakMap};
+    use crate::collectors::mark_sweep::{
+        finalizer_safe, force_collect, with_collector, with_scoped_collector,
+    };
+    use crate::mark_sweep::{Finalize, Trace};
+
+    #[test]
+    fn allocates_without_a_collector() {
+        let gc = Gc::new(5u64);
+        let weak = WeakGc::new(&gc);
+        let mut map = WeakMap::default();
+        with_collector(|collector| map.insert(&gc, 6u64, collector));
+
+        force_collect();
+        assert_eq!(weak.upgrade().as_deref(), Some(&5));
+        assert_eq!(map.get(&gc), Some(&6));
+
+        drop(gc);
+        force_collect();
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn scoped_collector_shadows_the_thread_collector() {
+        let outer = MarkSweepGarbageCollector::default();
+        let inner = MarkSweepGarbageCollector::default();
+
+        let kept = with_scoped_collector(&outer, || {
+            let kept = Gc::new(1u32);
+            with_scoped_collector(&inner, || {
+                drop(Gc::new(2u32));
+                force_collect();
+            });
+            force_collect();
+            kept
+        });
+
+        assert_eq!(inner.stats().last_cycle.objects_freed, 1);
+        assert_eq!(outer.stats().collections, 1);
+        assert_eq!(outer.stats().last_cycle.survivors, 1);
+        assert!(with_collector(
+            |collector| collector.stats().collections == 0
+        ));
+        assert_eq!(*kept, 1);
+    }
+
+    #[test]
+    fn finalizer_safe_is_false_while_collecting() {
+        use core::cell::Cell;
+
+        std::thread_local! {
+            static SEEN: Cell<Option<bool>> = const { Cell::new(None) };
+        }
+
+        struct Probe;
+
+        impl Finalize for Probe {
+            fn finalize(&self) {
+                SEEN.set(Some(finalizer_safe()));
+            }
+        }
+
+        // SAFETY: `Probe` has no traceable children.
+        unsafe impl Trace for Probe {
+            crate::empty_trace!();
+        }
+
+        let collector = MarkSweepGarbageCollector::default();
+        with_scoped_collector(&collector, || {
+            assert!(finalizer_safe());
+            drop(Gc::new(Probe));
+            force_collect();
+        });
+        assert_eq!(SEEN.get(), Some(false));
+    }
+
+    #[test]
+    fn weak_callback_runs_outside_the_collection() {
+        use core::cell::Cell;
+        use rust_alloc::rc::Rc;
+
+        let seen = Rc::new(Cell::new(None));
+        let collector = MarkSweepGarbageCollector::default();
+        with_scoped_collector(&collector, || {
+            let target = Gc::new(1u32);
+            let probe = Rc::clone(&seen);
+            let _weak = WeakGc::new_with_callback(&target, move || {
+                // the heap can be used again
+                let replacement = Gc::new(2u32);
+                probe.set(Some((finalizer_safe(), *replacement)));
+            });
+            drop(target);
+            force_collect();
+        });
+        assert_eq!(seen.get(), Some((true, 2)));
+    }
+}
+
+#[cfg(feature = "boa_gc_compat")]
+mod boa_gc_compat {
+    use crate::boa_gc_compat::{
+        Ephemeron, Finalize, Gc, GcRefCell, Trace, WeakGc, WeakMap, force_collect,
+    };
+
+    #[derive(Trace, Finalize)]
+    struct Node {
+        value: u32,
+        next: Option<Gc<GcRefCell<Node>>>,
+    }
+
+    struct Opaque(u32);
+
+    impl Finalize for Opaque {}
+
+    // SAFETY: `Opaque` has no traceable children.
+    unsafe impl Trace for Opaque {
+        crate::unsafe_empty_trace!();
+    }
+
+    struct Pair {
+        left: Gc<Opaque>,
+        right: Gc<Opaque>,
+    }
+
+    impl Finalize for Pair {}
+
+    // SAFETY: both fields are marked.
+    unsafe impl Trace for Pair {
+        crate::custom_trace!(this, mark, {
+            mark(&this.left);
+            mark(&this.right);
+        });
+    }
+
+    #[test]
+    fn engine_style_code_compiles_against_the_facade() {
+        let tail = Gc::new(GcRefCell::new(Node {
+            value: 2,
+            next: None,
+        }));
+        let head = Gc::new(GcRefCell::new(Node {
+            value: 1,
+            next: Some(tail.clone()),
+        }));
+        drop(tail);
+        let pair = Gc::new(Pair {
+            left: Gc::new(Opaque(3)),
+            right: Gc::new(Opaque(4)),
+        });
+
+        force_collect();
+        head.borrow_mut().value = 10;
+        let next = head.borrow().next.clone().unwrap();
+        assert_eq!(next.borrow().value, 2);
+        assert_eq!(head.borrow().value, 10);
+        assert_eq!(pair.left.0 + pair.right.0, 7);
+    }
+
+    #[test]
+    fn new_cyclic_holds_a_weak_handle_to_itself() {
+        struct Cyclic {
+            this: WeakGc<Cyclic>,
+        }
+
+        impl Finalize for Cyclic {}
+
+        // SAFETY: weak handles are not traced.
+        unsafe impl Trace for Cyclic {
+            crate::empty_trace!();
+        }
+
+        let gc = Gc::new_cyclic(|this| Cyclic { this: this.clone() });
+        let this = gc.this.upgrade().unwrap();
+        assert!(Gc::ptr_eq(&gc, &this));
+    }
+
+    #[test]
+    fn weak_map_crud() {
+        let map = WeakMap::default();
+        let key = Gc::new(1u32);
+
+        assert!(!map.has(&key));
+        assert_eq!(map.get_or_insert(&key, 5u32), 5);
+        assert_eq!(map.get_or_insert_computed(&key, || unreachable!()), 5);
+        map.set(&key, 6);
+        assert_eq!(map.get(&key), Some(6));
+        assert!(map.delete(&key));
+        assert!(!map.delete(&key));
+        assert_eq!(map.get(&key), None);
+
+        map.set(&key, 7);
+        force_collect();
+        assert!(map.has(&key));
+    }
+
+    #[test]
+    fn ephemeron_value_follows_the_key() {
+        let key = Gc::new(1u32);
+        let ephemeron = Ephemeron::new(&key, 2u32);
+
+        force_collect();
+        assert!(ephemeron.has_value());
+        assert_eq!(ephemeron.value(), Some(2));
+        assert!(Gc::ptr_eq(&ephemeron.key().unwrap(), &key));
+
+        drop(key);
+        force_collect();
+        assert!(!ephemeron.has_value());
+        assert_eq!(ephemeron.value(), None);
+        assert!(ephemeron.key().is_none());
+    }
+}
+
 #[cfg(feature = "thin-vec")]
 mod thin_vec_trace {
     use thin_vec::ThinVec;
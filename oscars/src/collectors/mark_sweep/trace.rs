@@ -277,10 +277,10 @@ unsafe impl<T: Trace + ?Sized> Trace for Box<T> {
     }
 }
 
-// SAFETY: All the inner elements of the `Box` array are correctly marked.
-unsafe impl<T: Trace> Trace for Box<[T]> {
+// SAFETY: All the elements of the slice are correctly marked.
+unsafe impl<T: Trace> Trace for [T] {
     custom_trace!(this, mark, {
-        for e in &**this {
+        for e in this {
             mark(e);
         }
     });
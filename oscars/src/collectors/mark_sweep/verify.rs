@@ -0,0 +1,309 @@
+//! Heap verification for the mark-sweep collector.
+//!
+//! [`MarkSweepGarbageCollector::verify_heap`] checks the invariants the
+//! collector relies on and reports every violation it finds, so that a broken
+//! `Trace` implementation or a collector bug shows up as a readable report
+//! instead of a use after free much later. It checks that:
+//!
+//! - every `Gc` reached by tracing points at a live slot tracked by the collector;
+//! - every object address stored inline in an object is reached by its `Trace`;
+//! - no object is left grey outside of a mark phase;
+//! - every object has at least as many roots as handles to it are stored in the heap;
+//! - weak map entries only reference live ephemerons;
+//! - the recorded heap size matches the pools actually held.
+//!
+//! The `Trace` check is conservative: it scans the words of every tracked
+//! object for one equal to the address of another live object, which
+//! catches a field the derive was told to skip or a `custom_trace!` body that
+//! forgets one. Handles behind an indirection, such as the elements of a
+//! `Vec`, are not scanned, and a plain integer that happens to equal an object
+//! address is reported too. The words are read as `MaybeUninit` since they
+//! include the padding of the value, Miri rejects the comparison of that
+//! padding so the scan is not compiled there. Every handle is also a root in
+//! this collector, so a missing edge rarely frees anything here, but it is a
+//! bug in the `Trace` implementation all the same.
+//!
+//! With the `verify_heap` feature the check also runs before every collection,
+//! right after marking and after the sweep, and panics with the report on the
+//! first violation.
+
+use core::fmt;
+
+use hashbrown::{HashMap, HashSet};
+use rust_alloc::vec::Vec;
+use rustc_hash::FxBuildHasher;
+
+use crate::collectors::mark_sweep::MarkSweepGarbageCollector;
+use crate::collectors::mark_sweep::snapshot::{NodeKind, TracedNode, trace_heap};
+
+/// A broken collector invariant found by [`MarkSweepGarbageCollector::verify_heap`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeapViolation {
+    /// A reference traced from `from` points at memory that is not a live slot.
+    DanglingEdge {
+        from: usize,
+        from_type: &'static str,
+        to: usize,
+    },
+    /// A reference traced from `from` points at a live slot the collector does
+    /// not track as an object.
+    UntrackedEdge {
+        from: usize,
+        from_type: &'static str,
+        to: usize,
+    },
+    /// The object at `from` stores the address of the object at `to` inline,
+    /// but tracing `from` does not reach it.
+    MissingEdge {
+        from: usize,
+        from_type: &'static str,
+        to: usize,
+        to_type: &'static str,
+    },
+    /// An object is still grey outside of a mark phase.
+    Grey {
+        addr: usize,
+        type_name: &'static str,
+    },
+    /// An object has fewer roots than there are handles to it in the heap.
+    RootCount {
+        addr: usize,
+        type_name: &'static str,
+        roots: u16,
+        heap_handles: usize,
+    },
+    /// A weak map entry references an ephemeron that is not alive.
+    DeadWeakMapEntry { ephemeron: usize },
+    /// The recorded heap size differs from the size of the pools held.
+    HeapSize { recorded: usize, pooled: usize },
+}
+
+impl fmt::Display for HeapViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DanglingEdge {
+                from,
+                from_type,
+                to,
+            } => write!(
+                f,
+                "{from_type} at {from:#x} references {to:#x}, which is not a live slot"
+            ),
+            Self::UntrackedEdge {
+                from,
+                from_type,
+                to,
+            } => write!(
+                f,
+                "{from_type} at {from:#x} references {to:#x}, which is not a tracked object"
+            ),
+            Self::MissingEdge {
+                from,
+                from_type,
+                to,
+                to_type,
+            } => write!(
+                f,
+                "{from_type} at {from:#x} holds {to_type} at {to:#x}, which its Trace does not reach"
+            ),
+            Self::Grey { addr, type_name } => {
+                write!(f, "{type_name} at {addr:#x} is grey outside of marking")
+            }
+            Self::RootCount {
+                addr,
+                type_name,
+                roots,
+                heap_handles,
+            } => write!(
+                f,
+                "{type_name} at {addr:#x} has {roots} roots but {heap_handles} handles in the heap"
+            ),
+            Self::DeadWeakMapEntry { ephemeron } => write!(
+                f,
+                "weak map entry references ephemeron {ephemeron:#x}, which is not alive"
+            ),
+            Self::HeapSize { recorded, pooled } => write!(
+                f,
+                "heap size is recorded as {recorded} bytes but the pools hold {pooled} bytes"
+            ),
+        }
+    }
+}
+
+/// The result of [`MarkSweepGarbageCollector::verify_heap`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeapReport {
+    objects: usize,
+    ephemerons: usize,
+    violations: Vec<HeapViolation>,
+}
+
+impl HeapReport {
+    /// Returns true if no invariant is broken.
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    pub fn violations(&self) -> &[HeapViolation] {
+        &self.violations
+    }
+
+    /// Number of objects checked.
+    pub fn objects(&self) -> usize {
+        self.objects
+    }
+
+    /// Number of ephemerons checked.
+    pub fn ephemerons(&self) -> usize {
+        self.ephemerons
+    }
+}
+
+impl fmt::Display for HeapReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "checked {} objects and {} ephemerons, {} violations",
+            self.objects,
+            self.ephemerons,
+            self.violations.len()
+        )?;
+        for violation in &self.violations {
+            write!(f, "\n  - {violation}")?;
+        }
+        Ok(())
+    }
+}
+
+pub(super) fn verify(collector: &MarkSweepGarbageCollector) -> HeapReport {
+    let traced = trace_heap(collector);
+    let allocator = collector.allocator.borrow();
+    let mut report = HeapReport::default();
+
+    let mut objects: HashMap<usize, usize, FxBuildHasher> = HashMap::default();
+    let mut ephemerons: HashSet<usize, FxBuildHasher> = HashSet::default();
+    for (i, node) in traced.iter().enumerate() {
+        match node.kind {
+            NodeKind::Object => {
+                objects.insert(node.addr, i);
+            }
+            NodeKind::Ephemeron => {
+                ephemerons.insert(node.addr);
+            }
+        }
+    }
+    report.objects = objects.len();
+    report.ephemerons = ephemerons.len();
+
+    let is_live = |addr: usize| {
+        core::ptr::NonNull::new(addr as *mut u8).is_some_and(|ptr| allocator.is_live_slot(ptr))
+    };
+
+    let mut heap_handles = rust_alloc::vec![0usize; traced.len()];
+    for node in &traced {
+        for &target in &node.targets {
+            let violation = if !is_live(target) {
+                HeapViolation::DanglingEdge {
+                    from: node.addr,
+                    from_type: node.type_name,
+                    to: target,
+                }
+            } else if !objects.contains_key(&target) {
+                HeapViolation::UntrackedEdge {
+                    from: node.addr,
+                    from_type: node.type_name,
+                    to: target,
+                }
+            } else {
+                continue;
+            };
+            report.violations.push(violation);
+        }
+        for target in node.handles() {
+            if let Some(&i) = objects.get(&target) {
+                heap_handles[i] += 1;
+            }
+        }
+    }
+
+    #[cfg(not(miri))]
+    for node in traced.iter().filter(|node| node.kind == NodeKind::Object) {
+        for to in stored_addresses(node) {
+            if to != node.addr
+                && let Some(&i) = objects.get(&to)
+                && !node.targets.contains(&to)
+            {
+                report.violations.push(HeapViolation::MissingEdge {
+                    from: node.addr,
+                    from_type: node.type_name,
+                    to,
+                    to_type: traced[i].type_name,
+                });
+            }
+        }
+    }
+
+    for &node in collector
+        .root_queue
+        .borrow()
+        .iter()
+        .chain(collector.pending_root_queue.borrow().iter())
+    {
+        let gc_box = unsafe { node.as_ref().value() };
+        if gc_box.header.is_grey() {
+            report.violations.push(HeapViolation::Grey {
+                addr: node.as_ptr() as usize,
+                type_name: gc_box.type_name(),
+            });
+        }
+    }
+
+    for (node, heap_handles) in traced.iter().zip(heap_handles) {
+        if node.kind == NodeKind::Object && usize::from(node.roots) < heap_handles {
+            report.violations.push(HeapViolation::RootCount {
+                addr: node.addr,
+                type_name: node.type_name,
+                roots: node.roots,
+                heap_handles,
+            });
+        }
+    }
+
+    for &map in collector.weak_maps.borrow().iter() {
+        let map = unsafe { map.as_ref() };
+        if !map.is_alive() {
+            continue;
+        }
+        map.for_each_ephemeron(&mut |ephemeron| {
+            let addr = ephemeron.as_ptr() as usize;
+            if !ephemerons.contains(&addr) || !is_live(addr) {
+                report
+                    .violations
+                    .push(HeapViolation::DeadWeakMapEntry { ephemeron: addr });
+            }
+        });
+    }
+
+    let (recorded, pooled) = (allocator.heap_size(), allocator.pooled_size());
+    if recorded != pooled {
+        report
+            .violations
+            .push(HeapViolation::HeapSize { recorded, pooled });
+    }
+
+    report
+}
+
+// every word of the allocation at `node`, the header included since it never
+// holds the address of another object
+#[cfg(not(miri))]
+fn stored_addresses(node: &TracedNode) -> impl Iterator<Item = usize> + '_ {
+    const WORD: usize = size_of::<usize>();
+    (0..node.size / WORD).map(move |i| {
+        let word = (node.addr + i * WORD) as *const core::mem::MaybeUninit<usize>;
+        // SAFETY: `node` is a live allocation of `node.size` bytes. A word of
+        // padding is uninitialised, it is only compared against the object
+        // addresses and never dereferenced
+        unsafe { core::ptr::read_unaligned(word).assume_init() }
+    })
+}
@@ -4,7 +4,7 @@ use core::marker::PhantomData;
 use crate::{
     alloc::arena2::ArenaHeapItem,
     collectors::mark_sweep_arena2::{
-        ErasedEphemeron, Finalize, TraceColor,
+        ErasedEphemeron, Finalize, GcErasedPointer, TraceColor,
         internals::{GcBox, WeakGcBox},
         pointers::Gc,
         trace::Trace,
@@ -43,6 +43,13 @@ impl<K: Trace, V: Trace> Ephemeron<K, V> {
         None
     }
 
+    pub(crate) fn value_mut(&mut self) -> Option<&mut V> {
+        if self.key().is_some() {
+            return Some(self.value.value_mut());
+        }
+        None
+    }
+
     pub fn is_reachable(&self, color: TraceColor) -> bool {
         self.key.is_reachable(color)
     }
@@ -80,6 +87,10 @@ impl<K: Trace, V: Trace> Ephemeron<K, V> {
     pub(crate) fn drop_fn(&self) -> EphemeronDropFn {
         self.vtable.drop_fn
     }
+
+    pub(crate) fn key_ptr_fn(&self) -> EphemeronKeyPtrFn {
+        self.vtable.key_ptr_fn
+    }
 }
 
 impl<K: Trace, V: Trace> Finalize for Ephemeron<K, V> {}
@@ -137,11 +148,11 @@ pub(crate) const fn vtable_of<K: Trace + 'static, V: Trace + 'static>() -> &'sta
         // SAFETY: The caller must ensure that the passed erased pointer is `GcBox<Self>`.
         unsafe fn drop_fn<K: Trace + 'static, V: Trace + 'static>(this: ErasedEphemeron) {
             // SAFETY: The caller must ensure that the passed erased pointer is `ArenaHeapItem<Ephemeron<K, V>>`.
-            let mut this = this.cast::<ArenaHeapItem<Ephemeron<K, V>>>();
+            let this = this.cast::<ArenaHeapItem<Ephemeron<K, V>>>();
 
             // drop the Ephemeron value in place, the arena bitmap is cleared
             // by the sweep loop after this function returns
-            unsafe { core::ptr::drop_in_place(this.as_mut()) };
+            unsafe { core::ptr::drop_in_place(ArenaHeapItem::as_value_ptr(this)) };
         }
     }
 
@@ -163,6 +174,13 @@ pub(crate) const fn vtable_of<K: Trace + 'static, V: Trace + 'static>() -> &'sta
                     .value();
                 Finalize::finalize(ephemeron);
             },
+            key_ptr_fn: |this| unsafe {
+                let ephemeron = this
+                    .cast::<ArenaHeapItem<Ephemeron<K, V>>>()
+                    .as_ref()
+                    .value();
+                ephemeron.key.as_heap_ptr()
+            },
             _key_type_id: TypeId::of::<K>(),
             _key_size: size_of::<WeakGcBox<K>>(),
             _value_type_id: TypeId::of::<V>(),
@@ -177,12 +195,14 @@ type EphemeronTraceFn = unsafe fn(this: ErasedEphemeron, color: TraceColor);
 type EphemeronDropFn = unsafe fn(this: ErasedEphemeron);
 type EphemeronIsReachableFn = unsafe fn(this: ErasedEphemeron, color: TraceColor) -> bool;
 type EphemeronFinalizeFn = unsafe fn(this: ErasedEphemeron);
+type EphemeronKeyPtrFn = unsafe fn(this: ErasedEphemeron) -> Option<GcErasedPointer>;
 
 pub struct EphemeronVTable {
     trace_fn: EphemeronTraceFn,
     drop_fn: EphemeronDropFn,
     is_reachable_fn: EphemeronIsReachableFn,
     finalize_fn: EphemeronFinalizeFn,
+    key_ptr_fn: EphemeronKeyPtrFn,
     _key_type_id: TypeId,
     _key_size: usize,
     _value_type_id: TypeId,
@@ -7,7 +7,7 @@ use crate::collectors::mark_sweep_arena2::Finalize;
 use crate::collectors::mark_sweep_arena2::internals::gc_header::{GcHeader, HeaderColor};
 use crate::collectors::mark_sweep_arena2::{Trace, TraceColor};
 
-use super::{DropFn, TraceFn, VTable, vtable_of};
+use super::{DropFn, TraceFn, Uninit, VTable, vtable_of};
 
 pub struct NonTraceable(());
 
@@ -78,6 +78,13 @@ impl<T: Trace> WeakGcBox<T> {
     pub(crate) fn inner_ptr(
         &self,
     ) -> Option<crate::alloc::arena2::ArenaPointer<'static, GcBox<T>>> {
+        // a box reserved by `Gc::new_cyclic_in` has no value yet
+        if self
+            .inner_ref()
+            .is_some_and(|inner| inner.header.is_constructing())
+        {
+            return None;
+        }
         // SAFETY: This pointer started out as a `GcBox<T>`, so it's safe to cast
         // it back, the `PhantomData` guarantees that the type `T` is still correct
         unsafe {
@@ -148,6 +155,10 @@ impl<T: Trace + ?Sized> GcBox<T> {
         &self.value
     }
 
+    pub(crate) fn value_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+
     pub(crate) fn is_reachable(&self, color: TraceColor) -> bool {
         match color {
             TraceColor::Black => self.header.is_black() || self.header.is_grey(),
@@ -193,6 +204,11 @@ impl<T: Trace + ?Sized> GcBox<T> {
         self.vtable.type_id()
     }
 
+    #[cfg(any(test, feature = "std"))]
+    pub(crate) fn type_name(&self) -> &'static str {
+        self.vtable.type_name()
+    }
+
     #[inline]
     fn trace_impl(&self, color: TraceColor) {
         match color {
@@ -219,6 +235,20 @@ impl<T: Trace + ?Sized> GcBox<T> {
     }
 }
 
+impl<T: Trace> GcBox<Uninit<T>> {
+    // writes the value of a box reserved by `Gc::new_cyclic_in` and switches
+    // it over to the vtable of `T`
+    //
+    // SAFETY: `this` must point at a live box whose value was never written
+    pub(crate) unsafe fn init(this: NonNull<Self>, value: T) {
+        let this = this.as_ptr();
+        unsafe {
+            (&raw mut (*this).value).cast::<T>().write(value);
+            (*this).vtable = vtable_of::<T>();
+        }
+    }
+}
+
 impl<T: Trace> Finalize for GcBox<T> {
     fn finalize(&self) {
         self.value.finalize();
@@ -7,3 +7,4 @@ pub(crate) use ephemeron::Ephemeron;
 pub(crate) use vtable::{DropFn, TraceFn, VTable, vtable_of};
 
 pub use self::gc_box::{GcBox, NonTraceable, WeakGcBox};
+pub(crate) use crate::collectors::mark_sweep::internals::Uninit;
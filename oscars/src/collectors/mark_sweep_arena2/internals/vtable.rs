@@ -22,10 +22,13 @@ pub(crate) const fn vtable_of<T: Trace + 'static>() -> &'static VTable {
         // SAFETY: The caller must ensure that the passed erased pointer is `GcBox<Self>`.
         unsafe fn drop_fn(this: GcErasedPointer) {
             // SAFETY: The caller must ensure that the passed erased pointer is `GcBox<Self>`.
-            let mut this = this.cast::<ArenaHeapItem<GcBox<Self>>>();
+            let this = this.cast::<ArenaHeapItem<GcBox<Self>>>();
 
+            // drop only the value, dropping the whole `ArenaHeapItem` would run
+            // its `Drop` impl and then drop the value a second time
+            //
             // SAFETY: The caller must ensure the erased pointer is not dropped or deallocated.
-            unsafe { core::ptr::drop_in_place(this.as_mut()) };
+            unsafe { core::ptr::drop_in_place(ArenaHeapItem::as_value_ptr(this)) };
         }
     }
 
@@ -34,6 +37,8 @@ pub(crate) const fn vtable_of<T: Trace + 'static>() -> &'static VTable {
             trace_fn: T::trace_fn,
             drop_fn: T::drop_fn,
             type_id: TypeId::of::<T>(),
+            #[cfg(any(test, feature = "std"))]
+            type_name: core::any::type_name::<T>,
             size: size_of::<GcBox<T>>(),
         };
     }
@@ -49,6 +54,8 @@ pub(crate) struct VTable {
     trace_fn: TraceFn,
     drop_fn: DropFn,
     type_id: TypeId,
+    #[cfg(any(test, feature = "std"))]
+    type_name: fn() -> &'static str,
     size: usize,
 }
 
@@ -65,6 +72,11 @@ impl VTable {
         self.type_id
     }
 
+    #[cfg(any(test, feature = "std"))]
+    pub(crate) fn type_name(&self) -> &'static str {
+        (self.type_name)()
+    }
+
     pub(crate) fn size(&self) -> usize {
         self.size
     }
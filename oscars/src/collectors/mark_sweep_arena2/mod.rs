@@ -6,10 +6,19 @@
 use core::cell::{Cell, RefCell};
 use core::ptr::NonNull;
 
+use hashbrown::HashMap;
+use rustc_hash::FxBuildHasher;
+
 use crate::{
     alloc::arena2::{ArenaAllocator, ArenaHeapItem, ArenaPointer},
-    collectors::mark_sweep_arena2::internals::{Ephemeron, GcBox, NonTraceable},
+    collectors::mark_sweep_arena2::internals::{GcBox, NonTraceable},
+    collectors::policy::{GcPolicy, Scheduler},
+    collectors::shutdown::{LeakReport, LeakedObject, ShutdownPolicy},
+    collectors::stats::{GcClock, GcStats, StatsRecorder},
+    collectors::unwind::PanicSink,
+    collectors::zeal::{Zeal, ZealState},
 };
+use rust_alloc::boxed::Box;
 use rust_alloc::vec::Vec;
 
 mod pointers;
@@ -24,7 +33,7 @@ pub(crate) mod internals;
 
 #[doc(hidden)]
 pub use pointers::ErasedWeakMap;
-pub use pointers::{Gc, WeakGc, WeakMap};
+pub use pointers::{Ephemeron, Gc, WeakGc, WeakMap, WeakSet};
 pub use trace::{Finalize, Trace, TraceColor};
 
 pub trait Collector {
@@ -63,7 +72,8 @@ pub trait Collector {
 }
 
 type GcErasedPointer = NonNull<ArenaHeapItem<GcBox<NonTraceable>>>;
-pub(crate) type ErasedEphemeron = NonNull<ArenaHeapItem<Ephemeron<NonTraceable, NonTraceable>>>;
+pub(crate) type ErasedEphemeron =
+    NonNull<ArenaHeapItem<internals::Ephemeron<NonTraceable, NonTraceable>>>;
 
 /* TODO: Figure out the best way to adapt the thread local concept in no_std
 *
@@ -97,6 +107,15 @@ pub struct MarkSweepGarbageCollector {
     pending_root_queue: RefCell<Vec<GcErasedPointer>>,
     pending_ephemeron_queue: RefCell<Vec<ErasedEphemeron>>,
     pub(crate) weak_maps: RefCell<Vec<NonNull<dyn ErasedWeakMap>>>,
+    // what `Drop` does with a heap that still has rooted values
+    shutdown_policy: Cell<ShutdownPolicy>,
+    // decides when to collect and how the heap threshold moves between cycles
+    scheduler: Scheduler,
+    stats: StatsRecorder,
+    zeal: ZealState,
+    // the first panic of a finalizer or destructor, raised once the
+    // collection or teardown that ran it is over
+    panics: PanicSink,
 }
 
 impl MarkSweepGarbageCollector {
@@ -112,6 +131,98 @@ impl MarkSweepGarbageCollector {
         self
     }
 
+    /// Sets the [`GcPolicy`] deciding when this collector runs.
+    pub fn with_policy(self, policy: impl GcPolicy + 'static) -> Self {
+        self.set_policy(policy);
+        self
+    }
+
+    /// Replaces the [`GcPolicy`], the new policy is consulted from the next
+    /// allocation on.
+    pub fn set_policy(&self, policy: impl GcPolicy + 'static) {
+        self.scheduler.set_policy(Box::new(policy));
+    }
+
+    /// Returns the heap size in bytes at which the next collection is requested.
+    pub fn heap_threshold(&self) -> usize {
+        self.allocator.borrow().heap_threshold()
+    }
+
+    /// Sets the [`GcClock`] used to measure collection pauses.
+    pub fn with_clock(self, clock: impl GcClock + 'static) -> Self {
+        self.set_clock(clock);
+        self
+    }
+
+    /// Replaces the [`GcClock`] used to measure collection pauses.
+    pub fn set_clock(&self, clock: impl GcClock + 'static) {
+        self.stats.set_clock(Box::new(clock));
+    }
+
+    /// Returns the statistics gathered since creation or the last [`reset_stats`].
+    ///
+    /// [`reset_stats`]: Self::reset_stats
+    pub fn stats(&self) -> GcStats {
+        self.stats.stats()
+    }
+
+    /// Clears the gathered statistics.
+    pub fn reset_stats(&self) {
+        self.stats.reset(self.allocator.borrow().heap_size());
+    }
+
+    /// Installs a [`Zeal`] stress mode, see [`zeal`](crate::collectors::zeal).
+    pub fn with_zeal(self, zeal: Zeal) -> Self {
+        self.set_zeal(Some(zeal));
+        self
+    }
+
+    /// Installs or, with `None`, removes the [`Zeal`] stress mode. The
+    /// allocation count restarts from zero.
+    pub fn set_zeal(&self, zeal: Option<Zeal>) {
+        self.zeal.set(zeal);
+    }
+
+    pub fn zeal(&self) -> Option<Zeal> {
+        self.zeal.get()
+    }
+
+    /// Sets the [`ShutdownPolicy`] applied when the collector is dropped while
+    /// values are still rooted.
+    pub fn with_shutdown_policy(self, policy: ShutdownPolicy) -> Self {
+        self.set_shutdown_policy(policy);
+        self
+    }
+
+    /// Replaces the [`ShutdownPolicy`].
+    pub fn set_shutdown_policy(&self, policy: ShutdownPolicy) {
+        self.shutdown_policy.set(policy);
+    }
+
+    pub fn shutdown_policy(&self) -> ShutdownPolicy {
+        self.shutdown_policy.get()
+    }
+
+    /// Lists the values currently rooted by handles outside the heap, the
+    /// values a drop of the collector would report.
+    pub fn leak_report(&self) -> LeakReport {
+        let mut report = LeakReport::default();
+        let roots = self.root_queue.borrow();
+        let pending = self.pending_root_queue.borrow();
+        for node in roots.iter().chain(pending.iter()) {
+            let gc_box = unsafe { node.as_ref().value() };
+            if gc_box.is_rooted() {
+                report.push(LeakedObject {
+                    addr: node.as_ptr() as usize,
+                    #[cfg(any(test, feature = "std"))]
+                    type_name: gc_box.type_name(),
+                    roots: gc_box.roots(),
+                });
+            }
+        }
+        report
+    }
+
     //returns the number of live arenas held by this collector
     //
     //prefer this over accessing `self.allocator` directly in tests so that
@@ -139,20 +250,33 @@ impl Drop for MarkSweepGarbageCollector {
 
         // SAFETY:
         // `Gc<T>` pointers act as if they live forever (`'static`).
-        // if the GC drops while they exist, we leak the memory to prevent a UAF
+        // if the GC drops while rooted values still exist, we leak memory to
+        // prevent a UAF unless the shutdown policy asks to finalize anyway.
+        let has_rooted_values = self
+            .root_queue
+            .borrow()
+            .iter()
+            .chain(self.pending_root_queue.borrow().iter())
+            .any(|node| unsafe { node.as_ref().value().is_rooted() });
+
         if self.arenas_len() > 0
-            && (!self.root_queue.borrow().is_empty()
-                || !self.pending_root_queue.borrow().is_empty())
+            && has_rooted_values
+            && !self
+                .shutdown_policy
+                .get()
+                .sweeps_rooted(|| self.leak_report())
         {
             // Unrooted items are NOT swept here so they intentionally leak
             // instead of triggering a Use-After-Free.
             // The underlying arena pools WILL be dropped (and OS memory reclaimed)
             // when `self.allocator` is dropped at the end of this scope.
         } else {
-            // No rooted items are alive. Sweep and clean up the remaining
-            // cycles and loose allocations before the allocator natively drops.
+            // No rooted items are alive, or the policy finalizes them anyway.
+            // Sweep and clean up the remaining cycles and loose allocations
+            // before the allocator natively drops.
             self.sweep_all_queues();
         }
+        self.panics.resume();
     }
 }
 
@@ -178,6 +302,8 @@ impl MarkSweepGarbageCollector {
         // the guard resets is_collecting even if a Trace/Finalize impl panics
         self.is_collecting.set(true);
         let _guard = CollectingGuard(&self.is_collecting);
+        let heap_size_before = self.allocator.borrow().heap_size();
+        let started = self.stats.begin_cycle();
 
         self.run_mark_phase();
 
@@ -188,6 +314,31 @@ impl MarkSweepGarbageCollector {
         // memory so we can still inspect the trace color on ephemerons;
         // use sweep_color since alive objects were marked with it.
         self.sweep_trace_color(sweep_color);
+
+        let pause = self.stats.end_cycle(started);
+        let mut alloc = self.allocator.borrow_mut();
+        let next_threshold = self.scheduler.on_cycle_end(
+            heap_size_before,
+            alloc.heap_size(),
+            pause,
+            alloc.heap_threshold(),
+        );
+        alloc.set_heap_threshold(next_threshold);
+        drop(alloc);
+
+        self.panics.resume();
+    }
+
+    // records an allocation of `bytes` and asks the policy whether the heap
+    // now needs a collection
+    fn should_collect(&self, alloc: &ArenaAllocator<'static>, bytes: usize) -> bool {
+        self.stats.observe_heap(alloc.heap_size());
+        self.scheduler.on_alloc(
+            bytes,
+            alloc.heap_size(),
+            alloc.heap_threshold(),
+            !alloc.is_below_threshold(),
+        )
     }
 
     // Force drops all elements in the internal tracking queues and clears
@@ -197,40 +348,33 @@ impl MarkSweepGarbageCollector {
     // arena3 uses`free_slot` calls to reclaim memory.
     // arena2 uses a bitmap (`mark_dropped`) and reclaims automatically
     fn sweep_all_queues(&self) {
+        // the queues hold erased boxes, values are dropped through their vtable
         let ephemerons = core::mem::take(&mut *self.ephemeron_queue.borrow_mut());
         for ephemeron in ephemerons {
-            unsafe {
-                let e_ptr = ephemeron.as_ptr();
-                core::ptr::drop_in_place(ArenaHeapItem::as_value_ptr(ephemeron));
-                (*e_ptr).mark_dropped();
-            }
+            let drop_fn = unsafe { ephemeron.as_ref().value().drop_fn() };
+            self.panics.call(|| unsafe { drop_fn(ephemeron) });
+            unsafe { (*ephemeron.as_ptr()).mark_dropped() };
         }
 
         let roots = core::mem::take(&mut *self.root_queue.borrow_mut());
         for node in roots {
-            unsafe {
-                let n_ptr = node.as_ptr();
-                core::ptr::drop_in_place(ArenaHeapItem::as_value_ptr(node));
-                (*n_ptr).mark_dropped();
-            }
+            let drop_fn = unsafe { node.as_ref().value().drop_fn() };
+            self.panics.call(|| unsafe { drop_fn(node) });
+            unsafe { (*node.as_ptr()).mark_dropped() };
         }
 
         let pending_e = core::mem::take(&mut *self.pending_ephemeron_queue.borrow_mut());
         for ephemeron in pending_e {
-            unsafe {
-                let e_ptr = ephemeron.as_ptr();
-                core::ptr::drop_in_place(ArenaHeapItem::as_value_ptr(ephemeron));
-                (*e_ptr).mark_dropped();
-            }
+            let drop_fn = unsafe { ephemeron.as_ref().value().drop_fn() };
+            self.panics.call(|| unsafe { drop_fn(ephemeron) });
+            unsafe { (*ephemeron.as_ptr()).mark_dropped() };
         }
 
         let pending_r = core::mem::take(&mut *self.pending_root_queue.borrow_mut());
         for node in pending_r {
-            unsafe {
-                let n_ptr = node.as_ptr();
-                core::ptr::drop_in_place(ArenaHeapItem::as_value_ptr(node));
-                (*n_ptr).mark_dropped();
-            }
+            let drop_fn = unsafe { node.as_ref().value().drop_fn() };
+            self.panics.call(|| unsafe { drop_fn(node) });
+            unsafe { (*node.as_ptr()).mark_dropped() };
         }
     }
 
@@ -243,7 +387,9 @@ impl MarkSweepGarbageCollector {
             let map = unsafe { map_ptr.as_ref() };
             if map.is_alive() {
                 // We need mut access to prune.
-                unsafe { (&mut *map_ptr.as_ptr()).prune_dead_entries(sweep_color) };
+                let pruned = unsafe { (&mut *map_ptr.as_ptr()).prune_dead_entries(sweep_color) };
+                self.stats
+                    .update_cycle(|cycle| cycle.weak_map_entries_pruned += pruned);
                 true
             } else {
                 // WeakMap was dropped, reclaim the inner allocation.
@@ -290,15 +436,43 @@ impl MarkSweepGarbageCollector {
             }
         }
 
-        for ephemeron_heap_item in self.ephemeron_queue.borrow().iter() {
+        // Ephemerons whose key is not marked yet, grouped by key so that each
+        // fixpoint round only has to check every distinct key once.
+        let mut pending: HashMap<GcErasedPointer, Vec<ErasedEphemeron>, FxBuildHasher> =
+            HashMap::default();
+
+        for ephemeron_heap_item in self.ephemeron_queue.borrow().iter().copied() {
             let ephemeron_ref = unsafe { ephemeron_heap_item.as_ref() };
             let is_reachable =
-                unsafe { ephemeron_ref.value().is_reachable_fn()(*ephemeron_heap_item, color) };
+                unsafe { ephemeron_ref.value().is_reachable_fn()(ephemeron_heap_item, color) };
 
             if is_reachable {
                 // no manual mark_slot is needed as alloc_slot handled it
                 // sweep uses the vtable is_reachable_fn/free_slot path
-                unsafe { ephemeron_ref.value().trace_fn()(*ephemeron_heap_item, color) }
+                unsafe { ephemeron_ref.value().trace_fn()(ephemeron_heap_item, color) }
+            } else if let Some(key) =
+                unsafe { ephemeron_ref.value().key_ptr_fn()(ephemeron_heap_item) }
+            {
+                pending.entry(key).or_default().push(ephemeron_heap_item);
+            }
+        }
+
+        // Ephemeron fixpoint: tracing a value may mark the key of an ephemeron
+        // we already passed over, so keep going until a round marks no new key.
+        while !pending.is_empty() {
+            let newly_reachable = pending
+                .extract_if(|key, _| unsafe { key.as_ref().value().is_reachable(color) })
+                .collect::<Vec<_>>();
+
+            if newly_reachable.is_empty() {
+                break;
+            }
+
+            for ephemeron_heap_item in newly_reachable.into_iter().flat_map(|(_, e)| e) {
+                let ephemeron_ref = unsafe { ephemeron_heap_item.as_ref() };
+                if unsafe { ephemeron_ref.value().is_reachable_fn()(ephemeron_heap_item, color) } {
+                    unsafe { ephemeron_ref.value().trace_fn()(ephemeron_heap_item, color) }
+                }
             }
         }
 
@@ -320,7 +494,7 @@ impl MarkSweepGarbageCollector {
                 // Check if the value is not reachable, i.e. dead.
                 if !gc_box.is_reachable(color) {
                     // Finalize the dead item
-                    gc_box.finalize();
+                    self.panics.call(|| gc_box.finalize());
                     // Recheck if the value is now rooted again after finalization.
                     if gc_box.is_rooted() {
                         unsafe { gc_box.trace_fn()(*node, color) };
@@ -340,7 +514,7 @@ impl MarkSweepGarbageCollector {
 
                 let is_reachable = unsafe { vtable.is_reachable_fn()(*node, color) };
                 if !is_reachable {
-                    unsafe { vtable.finalize_fn()(*node) };
+                    self.panics.call(|| unsafe { vtable.finalize_fn()(*node) });
                     // Recheck after finalization
                     if unsafe { vtable.is_reachable_fn()(*node, color) } {
                         unsafe { vtable.trace_fn()(*node, color) };
@@ -356,6 +530,7 @@ impl MarkSweepGarbageCollector {
         let mut still_alive_roots = Vec::default();
 
         let mut still_alive = Vec::default();
+        let mut ephemerons_pruned = 0;
         for ephemeron in ephemerons {
             let ephemeron_ref = unsafe { ephemeron.as_ref() };
             // If it's reachable according to the color, and it's active
@@ -368,20 +543,19 @@ impl MarkSweepGarbageCollector {
             }
             // copy ptrs for aliasing safety
             let drop_fn = ephemeron_ref.value().drop_fn();
+            ephemerons_pruned += 1;
 
-            unsafe {
-                drop_fn(ephemeron);
-                let e_mut = ephemeron.as_ptr();
-                (*e_mut).mark_dropped();
-            }
+            self.panics.call(|| unsafe { drop_fn(ephemeron) });
+            unsafe { (*ephemeron.as_ptr()).mark_dropped() };
         }
         self.ephemeron_queue.borrow_mut().extend(still_alive);
 
+        let (mut objects_freed, mut bytes_freed) = (0, 0);
         for node in droppables {
             // copy ptrs for aliasing safety
-            let (is_rooted, drop_fn) = {
+            let (is_rooted, drop_fn, size) = {
                 let r = unsafe { node.as_ref() };
-                (r.value().is_rooted(), r.value().drop_fn())
+                (r.value().is_rooted(), r.value().drop_fn(), r.value().size())
             };
             // Check one last time if the values are alive in case they were deemed
             // alive while checking the ephemerons.
@@ -396,13 +570,29 @@ impl MarkSweepGarbageCollector {
                 self.is_collecting.get(),
                 "free_slot called outside a collection — ordering invariant violated"
             );
+            self.panics.call(|| unsafe { drop_fn(node) });
             unsafe {
-                drop_fn(node);
                 let n_mut = node.as_ptr();
+                if self.zeal.poisons() {
+                    // the item header is kept, the arena still reads it
+                    let value = ArenaHeapItem::as_value_ptr(node).cast::<u8>();
+                    crate::collectors::zeal::poison(value, size);
+                }
                 (*n_mut).mark_dropped();
             }
+            objects_freed += 1;
+            bytes_freed += size;
         }
-        self.root_queue.borrow_mut().extend(still_alive_roots);
+        let mut root_queue = self.root_queue.borrow_mut();
+        root_queue.extend(still_alive_roots);
+
+        let survivors = root_queue.len();
+        self.stats.update_cycle(|cycle| {
+            cycle.objects_freed += objects_freed;
+            cycle.bytes_freed += bytes_freed;
+            cycle.survivors = survivors;
+            cycle.ephemerons_pruned += ephemerons_pruned;
+        });
     }
 }
 
@@ -423,7 +613,8 @@ impl Collector for MarkSweepGarbageCollector {
         &'gc self,
         value: T,
     ) -> Result<ArenaPointer<'gc, GcBox<T>>, crate::alloc::arena2::ArenaAllocError> {
-        if self.collect_needed.get() && !self.is_collecting.get() {
+        let zeal_due = self.zeal.tick();
+        if (zeal_due || self.collect_needed.get()) && !self.is_collecting.get() {
             self.collect_needed.set(false);
             self.collect();
         }
@@ -432,7 +623,7 @@ impl Collector for MarkSweepGarbageCollector {
 
         let mut alloc = self.allocator.borrow_mut();
         let arena_ptr = alloc.try_alloc(gc_box)?;
-        let needs_collect = !alloc.is_below_threshold();
+        let needs_collect = self.should_collect(&alloc, size_of::<ArenaHeapItem<GcBox<T>>>());
         drop(alloc);
 
         if needs_collect {
@@ -457,17 +648,22 @@ impl Collector for MarkSweepGarbageCollector {
         &'gc self,
         key: &Gc<K>,
         value: V,
-    ) -> Result<ArenaPointer<'gc, Ephemeron<K, V>>, crate::alloc::arena2::ArenaAllocError> {
-        if self.collect_needed.get() && !self.is_collecting.get() {
+    ) -> Result<ArenaPointer<'gc, internals::Ephemeron<K, V>>, crate::alloc::arena2::ArenaAllocError>
+    {
+        let zeal_due = self.zeal.tick();
+        if (zeal_due || self.collect_needed.get()) && !self.is_collecting.get() {
             self.collect_needed.set(false);
             self.collect();
         }
 
-        let ephemeron = Ephemeron::new(key, value, self.trace_color.get());
+        let ephemeron = internals::Ephemeron::new(key, value, self.trace_color.get());
 
         let mut alloc = self.allocator.borrow_mut();
         let inner_ptr = alloc.try_alloc(ephemeron)?;
-        let needs_collect = !alloc.is_below_threshold();
+        let needs_collect = self.should_collect(
+            &alloc,
+            size_of::<ArenaHeapItem<internals::Ephemeron<K, V>>>(),
+        );
         drop(alloc);
 
         if needs_collect {
@@ -476,7 +672,7 @@ impl Collector for MarkSweepGarbageCollector {
 
         let eph_ptr = inner_ptr
             .as_ptr()
-            .cast::<ArenaHeapItem<Ephemeron<NonTraceable, NonTraceable>>>();
+            .cast::<ArenaHeapItem<internals::Ephemeron<NonTraceable, NonTraceable>>>();
 
         if self.is_collecting.get() {
             self.pending_ephemeron_queue.borrow_mut().push(eph_ptr);
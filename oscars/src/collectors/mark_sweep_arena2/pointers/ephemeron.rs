@@ -0,0 +1,75 @@
+use crate::{
+    alloc::arena2::ArenaPointer,
+    collectors::mark_sweep_arena2::{Collector, Finalize, Gc, Trace, internals},
+};
+
+/// A key-value pair whose value is only reachable while its key is.
+///
+/// The handle keeps neither alive, the collector tracks the ephemeron behind
+/// it on its own and frees it with its key.
+pub struct Ephemeron<K: Trace + 'static, V: Trace + 'static> {
+    inner_ptr: ArenaPointer<'static, internals::Ephemeron<K, V>>,
+}
+
+impl<K: Trace, V: Trace> Ephemeron<K, V> {
+    pub fn new_in<C: Collector>(key: &Gc<K>, value: V, collector: &C) -> Self {
+        let inner_ptr = collector
+            .alloc_ephemeron_node(key, value)
+            .expect("Failed to allocate Ephemeron node");
+
+        // SAFETY: safe because the gc tracks this
+        let inner_ptr = unsafe { inner_ptr.extend_lifetime() };
+
+        Self { inner_ptr }
+    }
+
+    /// Returns the key if it is still alive and the ephemeron was not
+    /// invalidated.
+    pub fn key(&self) -> Option<Gc<K>> {
+        let inner = self.inner_ptr.as_inner_ref();
+        if !inner.active.get() {
+            return None;
+        }
+        inner.upgrade()
+    }
+
+    /// Returns a copy of the value if the key is still alive and the
+    /// ephemeron was not invalidated.
+    pub fn value(&self) -> Option<V>
+    where
+        V: Clone,
+    {
+        let inner = self.inner_ptr.as_inner_ref();
+        if !inner.active.get() {
+            return None;
+        }
+        inner.value().cloned()
+    }
+
+    /// Returns true if the value can still be read.
+    pub fn has_value(&self) -> bool {
+        let inner = self.inner_ptr.as_inner_ref();
+        inner.active.get() && inner.value().is_some()
+    }
+
+    /// Detaches the value from the key, it is freed by the next collection
+    /// even if the key stays alive.
+    pub fn invalidate(&self) {
+        self.inner_ptr.as_inner_ref().invalidate();
+    }
+}
+
+impl<K: Trace, V: Trace> Clone for Ephemeron<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner_ptr: self.inner_ptr,
+        }
+    }
+}
+
+impl<K: Trace, V: Trace> Finalize for Ephemeron<K, V> {}
+
+// SAFETY: the collector tracks the ephemeron behind the handle on its own
+unsafe impl<K: Trace, V: Trace> Trace for Ephemeron<K, V> {
+    crate::empty_trace!();
+}
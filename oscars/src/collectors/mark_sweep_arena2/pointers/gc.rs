@@ -1,7 +1,8 @@
 use crate::alloc::arena2::{ArenaHeapItem, ArenaPointer, ErasedArenaPointer};
 
 use crate::collectors::mark_sweep_arena2::Finalize;
-use crate::collectors::mark_sweep_arena2::internals::NonTraceable;
+use crate::collectors::mark_sweep_arena2::internals::{NonTraceable, Uninit};
+use crate::collectors::mark_sweep_arena2::pointers::WeakGc;
 use crate::collectors::mark_sweep_arena2::{Trace, internals::GcBox};
 use core::any::TypeId;
 use core::cmp::Ordering;
@@ -37,6 +38,40 @@ impl<T: Trace> Gc<T> {
         gc
     }
 
+    /// Allocates a value that can hold a [`WeakGc`] to its own allocation.
+    ///
+    /// The slot is reserved before `data_fn` runs, and `data_fn` receives a
+    /// [`WeakGc`] to it which upgrades to `None` until `data_fn` returns. A
+    /// collection triggered inside `data_fn` leaves the reserved slot alone,
+    /// and if `data_fn` panics the slot is reclaimed by a later collection.
+    pub fn new_cyclic_in<C: crate::collectors::mark_sweep_arena2::Collector, F>(
+        data_fn: F,
+        collector: &C,
+    ) -> Self
+    where
+        F: FnOnce(&WeakGc<T>) -> T,
+    {
+        // dropping `reserved` on unwind releases its root
+        let reserved = Gc::new_in(Uninit::<T>::new(), collector);
+        reserved.inner_ref().header.set_constructing(true);
+        // SAFETY: the ephemeron only records the address of its key, and weak
+        // handles do not read the value while the box is constructing
+        let weak = WeakGc::new_in(unsafe { Gc::cast_ref_unchecked::<T>(&reserved) }, collector);
+
+        let value = data_fn(&weak);
+
+        // SAFETY: `reserved` is rooted and its value was never written
+        unsafe { GcBox::init(reserved.as_sized_inner_ptr().cast(), value) };
+        reserved.inner_ref().header.set_constructing(false);
+        // the root of `reserved` becomes the root of the returned handle
+        let inner_ptr = reserved.inner_ptr;
+        core::mem::forget(reserved);
+        Self {
+            inner_ptr,
+            marker: PhantomData,
+        }
+    }
+
     /// Converts a `Gc` into a raw [`ArenaPointer`].
     pub fn into_raw(this: Self) -> ArenaPointer<'static, GcBox<T>> {
         let ptr = this.inner_ptr();
@@ -1,9 +1,13 @@
 //! Pointers represents the External types returned by the Boa Garbage Collector
 
+mod ephemeron;
 mod gc;
 mod weak;
 pub(crate) mod weak_map;
+mod weak_set;
 
+pub use ephemeron::Ephemeron;
 pub use gc::Gc;
 pub use weak::WeakGc;
 pub use weak_map::{ErasedWeakMap, WeakMap};
+pub use weak_set::WeakSet;
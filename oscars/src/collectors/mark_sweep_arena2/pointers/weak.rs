@@ -3,7 +3,7 @@
 // optimized in the future
 use crate::{
     alloc::arena2::ArenaPointer,
-    collectors::mark_sweep_arena2::{Gc, Trace, internals::Ephemeron},
+    collectors::mark_sweep_arena2::{Finalize, Gc, Trace, internals::Ephemeron},
 };
 
 #[repr(transparent)]
@@ -38,3 +38,19 @@ impl<T: Trace> WeakGc<T> {
         self.inner_ptr.as_inner_ref().upgrade()
     }
 }
+
+impl<T: Trace> Clone for WeakGc<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner_ptr: self.inner_ptr,
+        }
+    }
+}
+
+impl<T: Trace> Finalize for WeakGc<T> {}
+
+// SAFETY: a weak handle keeps nothing alive, the collector tracks the
+// ephemeron behind it on its own
+unsafe impl<T: Trace> Trace for WeakGc<T> {
+    crate::empty_trace!();
+}
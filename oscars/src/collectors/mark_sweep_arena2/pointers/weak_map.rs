@@ -2,7 +2,7 @@ use hashbrown::HashTable;
 use rustc_hash::FxHasher;
 
 use crate::{
-    alloc::arena2::ArenaPointer,
+    alloc::arena2::{ArenaHeapItem, ArenaPointer},
     collectors::mark_sweep_arena2::{Finalize, TraceColor, internals::Ephemeron, trace::Trace},
 };
 use core::{hash::Hasher, ptr::NonNull};
@@ -10,7 +10,7 @@ use core::{hash::Hasher, ptr::NonNull};
 use super::Gc;
 
 #[inline]
-fn hash_addr(addr: usize) -> u64 {
+pub(super) fn hash_addr(addr: usize) -> u64 {
     let mut h = FxHasher::default();
     h.write_usize(addr);
     h.finish()
@@ -19,7 +19,8 @@ fn hash_addr(addr: usize) -> u64 {
 // type erased trait so the collector can prune any WeakMap without knowing K/V
 #[doc(hidden)]
 pub trait ErasedWeakMap {
-    fn prune_dead_entries(&mut self, color: TraceColor);
+    // returns the number of entries removed
+    fn prune_dead_entries(&mut self, color: TraceColor) -> usize;
     fn is_alive(&self) -> bool;
 }
 
@@ -67,6 +68,15 @@ impl<K: Trace, V: Trace> WeakMapInner<K, V> {
             .and_then(|(_, p)| p.as_inner_ref().value())
     }
 
+    fn get_mut(&mut self, key: &Gc<K>) -> Option<&mut V> {
+        let key_addr = key.inner_ptr.as_non_null().as_ptr() as usize;
+        self.entries
+            .find_mut(hash_addr(key_addr), |e| e.0 == key_addr)
+            // SAFETY: the map owns its ephemerons, nothing else hands out
+            // references to their values
+            .and_then(|(_, p)| unsafe { (*ArenaHeapItem::as_value_ptr(p.as_ptr())).value_mut() })
+    }
+
     fn is_key_alive(&self, key: &Gc<K>) -> bool {
         let key_addr = key.inner_ptr.as_non_null().as_ptr() as usize;
         self.entries
@@ -89,12 +99,20 @@ impl<K: Trace, V: Trace> WeakMapInner<K, V> {
             false
         }
     }
+
+    fn clear(&mut self) {
+        for (_, ptr) in self.entries.drain() {
+            ptr.as_inner_ref().invalidate();
+        }
+    }
 }
 
 impl<K: Trace, V: Trace> ErasedWeakMap for WeakMapInner<K, V> {
-    fn prune_dead_entries(&mut self, color: TraceColor) {
+    fn prune_dead_entries(&mut self, color: TraceColor) -> usize {
+        let before = self.entries.len();
         self.entries
             .retain(|(_, ephemeron_ptr)| ephemeron_ptr.as_inner_ref().is_reachable(color));
+        before - self.entries.len()
     }
 
     fn is_alive(&self) -> bool {
@@ -158,15 +176,65 @@ impl<K: Trace, V: Trace> WeakMap<K, V> {
         unsafe { self.inner.as_ref().get(key) }
     }
 
+    pub fn get_mut(&mut self, key: &Gc<K>) -> Option<&mut V> {
+        // SAFETY: we have unique access to `self`
+        unsafe { self.inner.as_mut().get_mut(key) }
+    }
+
+    // returns the value for `key`, inserting the result of `f` first if there
+    // is none
+    pub fn get_or_insert_with<C: crate::collectors::mark_sweep_arena2::Collector>(
+        &mut self,
+        key: &Gc<K>,
+        f: impl FnOnce() -> V,
+        collector: &C,
+    ) -> &mut V {
+        if !self.contains_key(key) {
+            self.insert(key, f(), collector);
+        }
+        self.get_mut(key).expect("entry was just inserted")
+    }
+
     pub fn is_key_alive(&self, key: &Gc<K>) -> bool {
         // SAFETY: we hold `&self` so the map is alive and unchanged
         unsafe { self.inner.as_ref().is_key_alive(key) }
     }
 
+    pub fn contains_key(&self, key: &Gc<K>) -> bool {
+        self.is_key_alive(key)
+    }
+
     pub fn remove(&mut self, key: &Gc<K>) -> bool {
         // SAFETY: we have unique access to `self`
         unsafe { self.inner.as_mut().remove(key) }
     }
+
+    // entries of collected keys are pruned by the collection that frees the
+    // key, so every remaining entry is live
+    pub fn len(&self) -> usize {
+        // SAFETY: we hold `&self` so the map is alive and unchanged
+        unsafe { self.inner.as_ref().entries.len() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        // SAFETY: we have unique access to `self`
+        unsafe { self.inner.as_mut().clear() }
+    }
+
+    // iterates the live entries in no particular order, each key is returned
+    // as a new root
+    pub fn iter(&self) -> impl Iterator<Item = (Gc<K>, &V)> + '_ {
+        // SAFETY: we hold `&self` so the map is alive and unchanged
+        let entries = unsafe { &self.inner.as_ref().entries };
+        entries.iter().filter_map(|(_, ptr)| {
+            let ephemeron = ptr.as_inner_ref();
+            Some((ephemeron.upgrade()?, ephemeron.value()?))
+        })
+    }
 }
 
 impl<K: Trace, V: Trace> Finalize for WeakMap<K, V> {}
@@ -0,0 +1,135 @@
+use hashbrown::HashTable;
+
+use crate::collectors::mark_sweep_arena2::{
+    Collector, Finalize, TraceColor, internals::WeakGcBox, pointers::weak_map::ErasedWeakMap,
+    trace::Trace,
+};
+use core::ptr::NonNull;
+
+use super::Gc;
+use super::weak_map::hash_addr;
+
+// the actual weak set store, managed by the collector like a `WeakMapInner`
+//
+// entries are bare weak boxes, no ephemeron is allocated since there is no
+// value to keep alive
+struct WeakSetInner<K: Trace + 'static> {
+    entries: HashTable<(usize, WeakGcBox<K>)>,
+    is_alive: core::cell::Cell<bool>,
+}
+
+impl<K: Trace> ErasedWeakMap for WeakSetInner<K> {
+    fn prune_dead_entries(&mut self, color: TraceColor) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|(_, key)| key.is_reachable(color));
+        before - self.entries.len()
+    }
+
+    fn is_alive(&self) -> bool {
+        self.is_alive.get()
+    }
+}
+
+// set that prunes entries automatically when their GC keys are collected,
+// the same ownership rules as `WeakMap` apply
+pub struct WeakSet<K: Trace + 'static> {
+    // raw pointer to collector owned memory
+    inner: NonNull<WeakSetInner<K>>,
+}
+
+impl<K: Trace> WeakSet<K> {
+    // create a new set and give the collector ownership of its memory
+    pub fn new<C: Collector>(collector: &C) -> Self {
+        let boxed = rust_alloc::boxed::Box::new(WeakSetInner::<K> {
+            entries: HashTable::new(),
+            is_alive: core::cell::Cell::new(true),
+        });
+        // SAFETY: pointer returned from `Box::into_raw` is non-null
+        let inner = unsafe { NonNull::new_unchecked(rust_alloc::boxed::Box::into_raw(boxed)) };
+
+        collector.track_weak_map(inner);
+        Self { inner }
+    }
+
+    // adds `key`, returns false if it was already present
+    pub fn insert(&mut self, key: &Gc<K>) -> bool {
+        let key_addr = key.inner_ptr.as_non_null().as_ptr() as usize;
+        // SAFETY: `&mut self` gives exclusive access to `inner`
+        let entries = unsafe { &mut self.inner.as_mut().entries };
+        let hash = hash_addr(key_addr);
+        if entries.find(hash, |e| e.0 == key_addr).is_some() {
+            return false;
+        }
+        let key = WeakGcBox::new(key.inner_ptr);
+        entries.insert_unique(hash, (key_addr, key), |e| hash_addr(e.0));
+        true
+    }
+
+    pub fn contains(&self, key: &Gc<K>) -> bool {
+        let key_addr = key.inner_ptr.as_non_null().as_ptr() as usize;
+        // SAFETY: we hold `&self` so the set is alive and unchanged
+        let entries = unsafe { &self.inner.as_ref().entries };
+        entries
+            .find(hash_addr(key_addr), |e| e.0 == key_addr)
+            .is_some()
+    }
+
+    // removes `key`, returns false if it was not present
+    pub fn remove(&mut self, key: &Gc<K>) -> bool {
+        let key_addr = key.inner_ptr.as_non_null().as_ptr() as usize;
+        // SAFETY: we have unique access to `self`
+        let entries = unsafe { &mut self.inner.as_mut().entries };
+        entries
+            .find_entry(hash_addr(key_addr), |e| e.0 == key_addr)
+            .map(|entry| entry.remove())
+            .is_ok()
+    }
+
+    // keys are pruned by the collection that frees them, so every remaining
+    // entry is live
+    pub fn len(&self) -> usize {
+        // SAFETY: we hold `&self` so the set is alive and unchanged
+        unsafe { self.inner.as_ref().entries.len() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        // SAFETY: we have unique access to `self`
+        unsafe { self.inner.as_mut().entries.clear() }
+    }
+
+    // iterates the live keys in no particular order, each is returned as a
+    // new root
+    pub fn iter(&self) -> impl Iterator<Item = Gc<K>> + '_ {
+        // SAFETY: we hold `&self` so the set is alive and unchanged
+        let entries = unsafe { &self.inner.as_ref().entries };
+        entries.iter().filter_map(|(_, key)| {
+            let ptr = key.inner_ptr()?;
+            ptr.as_inner_ref().inc_roots();
+            // SAFETY: the key is alive until the collection that prunes it,
+            // and the new handle holds the root taken above
+            Some(unsafe { Gc::from_raw(ptr) })
+        })
+    }
+}
+
+impl<K: Trace> Finalize for WeakSet<K> {}
+
+// keys are held weakly, nothing to trace
+unsafe impl<K: Trace + 'static> Trace for WeakSet<K> {
+    unsafe fn trace(&self, _color: TraceColor) {}
+    fn run_finalizer(&self) {
+        Finalize::finalize(self);
+    }
+}
+
+impl<K: Trace> Drop for WeakSet<K> {
+    fn drop(&mut self) {
+        // signal the collector that this set is gone so it can drop the inner allocation
+        // SAFETY: `inner` pointer remains valid until `is_alive` is set false here
+        unsafe { self.inner.as_ref().is_alive.set(false) }
+    }
+}
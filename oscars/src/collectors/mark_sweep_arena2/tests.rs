@@ -1,9 +1,11 @@
 use crate::collectors::mark_sweep_arena2::MarkSweepGarbageCollector;
 use crate::collectors::mark_sweep_arena2::trace::{Finalize, Trace, TraceColor};
 
+use super::Ephemeron;
 use super::Gc;
 use super::WeakGc;
 use super::WeakMap;
+use super::WeakSet;
 use super::cell::GcRefCell;
 
 #[test]
@@ -388,6 +390,65 @@ fn simple_weak_gc_validate() {
     assert!(weak.value().is_none());
 }
 
+#[test]
+fn ephemeron_handle_follows_key() {
+    struct Holder {
+        ephemeron: Ephemeron<u64, u64>,
+    }
+
+    impl Finalize for Holder {}
+
+    unsafe impl Trace for Holder {
+        unsafe fn trace(&self, color: TraceColor) {
+            unsafe { self.ephemeron.trace(color) };
+        }
+
+        fn run_finalizer(&self) {}
+    }
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_arena_size(256)
+        .with_heap_threshold(512);
+
+    let key = Gc::new_in(1u64, collector);
+    let holder = Gc::new_in(
+        Holder {
+            ephemeron: Ephemeron::new_in(&key, 10u64, collector),
+        },
+        collector,
+    );
+
+    collector.collect();
+    let ephemeron = holder.ephemeron.clone();
+    assert!(ephemeron.has_value());
+    assert_eq!(ephemeron.key().as_deref(), Some(&1));
+    assert_eq!(ephemeron.value(), Some(10));
+
+    drop(key);
+    collector.collect();
+    assert_eq!(collector.stats().last_cycle.ephemerons_pruned, 1);
+    drop(holder);
+}
+
+#[test]
+fn invalidated_ephemeron_is_freed_while_key_lives() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_arena_size(256)
+        .with_heap_threshold(512);
+
+    let key = Gc::new_in(1u64, collector);
+    let ephemeron = Ephemeron::new_in(&key, 10u64, collector);
+
+    ephemeron.invalidate();
+    assert!(!ephemeron.has_value());
+    assert!(ephemeron.key().is_none());
+    assert_eq!(ephemeron.value(), None);
+
+    collector.collect();
+    assert_eq!(collector.stats().last_cycle.ephemerons_pruned, 1);
+    drop(key);
+}
+
 #[test]
 fn basic_wm() {
     let collector = &mut MarkSweepGarbageCollector::default()
@@ -731,6 +792,416 @@ fn drop_map_with_live_key_wm() {
     );
 }
 
+#[test]
+fn wm_collection_api() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_arena_size(256)
+        .with_heap_threshold(512);
+
+    let mut map = WeakMap::new(collector);
+    let a = Gc::new_in(1u64, collector);
+    let b = Gc::new_in(2u64, collector);
+    assert!(map.is_empty());
+
+    *map.get_or_insert_with(&a, || 10, collector) += 1;
+    *map.get_or_insert_with(&a, || unreachable!(), collector) += 1;
+    map.insert(&b, 20, collector);
+    *map.get_mut(&b).unwrap() += 1;
+    assert_eq!(map.get(&a), Some(&12));
+    assert_eq!(map.get(&b), Some(&21));
+    assert!(map.contains_key(&a));
+    assert_eq!(map.len(), 2);
+
+    let mut entries: Vec<_> = map.iter().map(|(key, value)| (*key, *value)).collect();
+    entries.sort();
+    assert_eq!(entries, [(1, 12), (2, 21)]);
+
+    drop(a);
+    collector.collect();
+    assert_eq!(map.len(), 1);
+
+    map.clear();
+    assert!(map.is_empty());
+    assert!(!map.contains_key(&b));
+}
+
+#[test]
+fn weak_set_prunes_collected_keys() {
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_arena_size(256)
+        .with_heap_threshold(512);
+
+    let mut set = WeakSet::new(collector);
+    let a = Gc::new_in(1u64, collector);
+    let b = Gc::new_in(2u64, collector);
+
+    assert!(set.insert(&a));
+    assert!(!set.insert(&a));
+    assert!(set.insert(&b));
+    assert!(set.contains(&a));
+    assert_eq!(set.len(), 2);
+
+    drop(a);
+    collector.collect();
+    assert_eq!(set.len(), 1);
+    let keys: Vec<_> = set.iter().map(|key| *key).collect();
+    assert_eq!(keys, [2]);
+
+    assert!(set.remove(&b));
+    assert!(!set.remove(&b));
+    assert!(set.is_empty());
+}
+
+#[test]
+fn chained_wm_fixpoint() {
+    crate::collectors::test_support::chained_weak_map_fixpoint!(
+        &mut MarkSweepGarbageCollector::default()
+            .with_arena_size(256)
+            .with_heap_threshold(512),
+        arenas_len
+    );
+}
+
+#[test]
+fn sweep_drops_each_value_once() {
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    struct DropSpy {
+        drops: Rc<Cell<u32>>,
+    }
+
+    impl Drop for DropSpy {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+        }
+    }
+
+    impl Finalize for DropSpy {}
+
+    // SAFETY: `DropSpy` has no traceable children.
+    unsafe impl Trace for DropSpy {
+        crate::empty_trace!();
+    }
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_arena_size(256)
+        .with_heap_threshold(512);
+
+    // a box value goes through the `GcBox` drop_fn
+    let box_drops = Rc::new(Cell::new(0));
+    drop(Gc::new_in(
+        DropSpy {
+            drops: box_drops.clone(),
+        },
+        collector,
+    ));
+
+    // a weak map value is stored inline in its ephemeron and goes through
+    // the ephemeron drop_fn
+    let ephemeron_drops = Rc::new(Cell::new(0));
+    let mut map = WeakMap::new(collector);
+    let key = Gc::new_in(1u64, collector);
+    map.insert(
+        &key,
+        DropSpy {
+            drops: ephemeron_drops.clone(),
+        },
+        collector,
+    );
+    drop(key);
+
+    collector.collect();
+    assert_eq!(box_drops.get(), 1, "box value dropped more than once");
+    assert_eq!(
+        ephemeron_drops.get(),
+        1,
+        "ephemeron value dropped more than once"
+    );
+}
+
+#[test]
+fn never_policy_skips_auto_collect() {
+    use crate::collectors::policy::NeverPolicy;
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    struct DropSpy {
+        drops: Rc<Cell<u32>>,
+    }
+
+    impl Drop for DropSpy {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+        }
+    }
+
+    impl Finalize for DropSpy {}
+
+    // SAFETY: `DropSpy` has no traceable children.
+    unsafe impl Trace for DropSpy {
+        crate::empty_trace!();
+    }
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_arena_size(128)
+        .with_heap_threshold(256)
+        .with_policy(NeverPolicy);
+
+    let drops = Rc::new(Cell::new(0));
+    drop(Gc::new_in(
+        DropSpy {
+            drops: drops.clone(),
+        },
+        collector,
+    ));
+
+    // enough pressure to trip the threshold several times over
+    let _temporaries: rust_alloc::vec::Vec<_> = (0..20u64)
+        .map(|i| Gc::new_in(GcRefCell::new(i), collector))
+        .collect();
+
+    assert_eq!(drops.get(), 0, "NeverPolicy collected on allocation");
+
+    collector.collect();
+    assert_eq!(drops.get(), 1, "explicit collect must still run");
+}
+
+#[test]
+fn policy_sets_next_threshold() {
+    use crate::collectors::policy::{FixedPolicy, GrowthPolicy};
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_arena_size(256)
+        .with_heap_threshold(1024)
+        .with_policy(GrowthPolicy::new(100, 512));
+
+    let _live: rust_alloc::vec::Vec<_> = (0..40u64).map(|i| Gc::new_in(i, collector)).collect();
+
+    collector.collect();
+    let live_bytes = collector.allocator.borrow().heap_size();
+    assert!(live_bytes > 0);
+    assert_eq!(collector.heap_threshold(), (live_bytes * 2).max(512));
+
+    collector.set_policy(FixedPolicy::new(4096));
+    collector.collect();
+    assert_eq!(collector.heap_threshold(), 4096);
+}
+
+#[test]
+fn stats_track_collections() {
+    use crate::collectors::stats::GcClock;
+    use core::cell::Cell;
+    use core::time::Duration;
+
+    // advances by 5ms every time it is read
+    struct StepClock(Cell<u64>);
+
+    impl GcClock for StepClock {
+        fn now(&self) -> Duration {
+            self.0.set(self.0.get() + 5);
+            Duration::from_millis(self.0.get())
+        }
+    }
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_arena_size(256)
+        .with_heap_threshold(4096)
+        .with_clock(StepClock(Cell::new(0)));
+
+    let keep = Gc::new_in(1u64, collector);
+    for i in 0..3u64 {
+        drop(Gc::new_in(i, collector));
+    }
+
+    let mut map = WeakMap::new(collector);
+    let dead_key = Gc::new_in(9u64, collector);
+    map.insert(&dead_key, 1u64, collector);
+    drop(dead_key);
+
+    collector.collect();
+
+    let stats = collector.stats();
+    assert_eq!(stats.collections, 1);
+    assert_eq!(stats.last_cycle.objects_freed, 4);
+    assert!(stats.last_cycle.bytes_freed > 0);
+    assert_eq!(stats.last_cycle.survivors, 1);
+    assert_eq!(stats.last_cycle.ephemerons_pruned, 1);
+    assert_eq!(stats.last_cycle.weak_map_entries_pruned, 1);
+    assert_eq!(stats.last_pause(), Duration::from_millis(5));
+    assert!(stats.peak_heap_size > 0);
+
+    collector.collect();
+
+    let stats = collector.stats();
+    assert_eq!(stats.collections, 2);
+    assert_eq!(stats.objects_freed, 4);
+    assert_eq!(stats.last_cycle.objects_freed, 0);
+    assert_eq!(stats.total_pause, Duration::from_millis(10));
+
+    collector.reset_stats();
+    assert_eq!(collector.stats().collections, 0);
+    assert_eq!(collector.stats().total_pause, Duration::ZERO);
+    assert_eq!(*keep, 1);
+}
+
+#[test]
+fn zeal_collects_on_every_allocation() {
+    use crate::collectors::zeal::Zeal;
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_arena_size(256)
+        .with_heap_threshold(1 << 20)
+        .with_zeal(Zeal::every(2).with_poison());
+
+    let list = Gc::new_in(GcRefCell::new(rust_alloc::vec::Vec::new()), collector);
+    for i in 0..16u64 {
+        list.borrow_mut().push(Gc::new_in(i, collector));
+        drop(Gc::new_in(i, collector));
+    }
+    assert_eq!(collector.stats().collections, 16);
+    assert!(list.borrow().iter().map(|gc| **gc).eq(0..16));
+    assert_eq!(collector.zeal(), Some(Zeal::every(2).with_poison()));
+}
+
+#[test]
+fn new_cyclic_holds_weak_to_itself() {
+    struct Node {
+        this: WeakGc<Node>,
+        value: u64,
+    }
+
+    impl Finalize for Node {}
+
+    // SAFETY: `this` is weak and keeps nothing alive.
+    unsafe impl Trace for Node {
+        crate::empty_trace!();
+    }
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_arena_size(256)
+        .with_heap_threshold(4096);
+
+    let node = Gc::new_cyclic_in(
+        |this| {
+            assert!(this.upgrade().is_none());
+            collector.collect();
+            Node {
+                this: this.clone(),
+                value: 7,
+            }
+        },
+        collector,
+    );
+
+    let this = node.this.upgrade().expect("construction finished");
+    assert!(Gc::ptr_eq(&node, &this));
+    collector.collect();
+    assert_eq!(node.this.value().map(|node| node.value), Some(7));
+}
+
+#[test]
+fn shutdown_policy_applies_to_rooted_values() {
+    use crate::collectors::shutdown::ShutdownPolicy;
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    struct DropSpy(Rc<Cell<u32>>);
+
+    impl Finalize for DropSpy {}
+
+    // SAFETY: `DropSpy` has no traceable children.
+    unsafe impl Trace for DropSpy {
+        crate::empty_trace!();
+    }
+
+    impl Drop for DropSpy {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+    let collector = MarkSweepGarbageCollector::default().with_arena_size(256);
+    let rooted = Gc::new_in(DropSpy(Rc::clone(&drops)), &collector);
+    drop(Gc::new_in(DropSpy(Rc::clone(&drops)), &collector));
+    let report = collector.leak_report();
+    assert_eq!(report.len(), 1);
+    assert!(report.objects()[0].type_name.contains("DropSpy"));
+    assert_eq!(report.objects()[0].roots, 1);
+    drop(collector);
+    assert_eq!(drops.get(), 0, "the default policy leaks");
+    core::mem::forget(rooted);
+
+    let collector = MarkSweepGarbageCollector::default()
+        .with_arena_size(256)
+        .with_shutdown_policy(ShutdownPolicy::Finalize);
+    core::mem::forget(Gc::new_in(DropSpy(Rc::clone(&drops)), &collector));
+    drop(Gc::new_in(DropSpy(Rc::clone(&drops)), &collector));
+    drop(collector);
+    assert_eq!(drops.get(), 2, "every value is dropped anyway");
+}
+
+#[test]
+fn collection_survives_panicking_finalizer_and_drop() {
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    struct Bomb {
+        drops: Rc<Cell<u32>>,
+        in_finalize: bool,
+        in_drop: bool,
+    }
+
+    impl Finalize for Bomb {
+        fn finalize(&self) {
+            if self.in_finalize {
+                panic!("finalizer failed");
+            }
+        }
+    }
+
+    // SAFETY: `Bomb` has no traceable children.
+    unsafe impl Trace for Bomb {
+        crate::empty_trace!();
+    }
+
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+            if self.in_drop {
+                panic!("destructor failed");
+            }
+        }
+    }
+
+    let collector = &mut MarkSweepGarbageCollector::default()
+        .with_arena_size(256)
+        .with_heap_threshold(4096);
+
+    let drops = Rc::new(Cell::new(0));
+    let bomb = |in_finalize, in_drop| Bomb {
+        drops: Rc::clone(&drops),
+        in_finalize,
+        in_drop,
+    };
+    let kept = Gc::new_in(bomb(false, false), collector);
+    drop(Gc::new_in(bomb(true, false), collector));
+    drop(Gc::new_in(bomb(false, false), collector));
+    drop(Gc::new_in(bomb(false, true), collector));
+
+    let result = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| collector.collect()));
+    assert!(result.is_err());
+    assert_eq!(drops.get(), 3, "every dead value is dropped");
+    assert!(collector.finalizer_safe());
+
+    collector.collect();
+    assert_eq!(drops.get(), 3);
+    drop(kept);
+    collector.collect();
+    assert_eq!(drops.get(), 4);
+}
+
 /// Edge-case stability tests for the mark-sweep garbage collector.
 ///
 /// These tests exercise corner cases that could cause crashes, stack overflows,
@@ -5,6 +5,7 @@ use crate::{
         gc_box::GcBox,
         mutation_ctx::MutationContext,
         trace::{Finalize, Trace, Tracer},
+        weak::WeakSlot,
     },
 };
 use core::marker::PhantomData;
@@ -16,7 +17,7 @@ use core::marker::PhantomData;
 /// and the entry is eligible for cleanup.
 pub struct Ephemeron<'id, K: Trace + ?Sized, V: Trace> {
     pub(crate) key_ptr: Option<PoolPointer<'static, GcBox<K>>>,
-    pub(crate) key_alloc_id: usize,
+    pub(crate) key_slot: WeakSlot,
     pub(crate) value_ptr: PoolPointer<'static, GcBox<V>>,
     pub(crate) _marker: PhantomData<*mut &'id ()>,
 }
@@ -24,12 +25,12 @@ pub struct Ephemeron<'id, K: Trace + ?Sized, V: Trace> {
 impl<'id, K: Trace + ?Sized, V: Trace> Ephemeron<'id, K, V> {
     pub(crate) fn new_raw(
         key_ptr: Option<PoolPointer<'static, GcBox<K>>>,
-        key_alloc_id: usize,
+        key_slot: WeakSlot,
         value_ptr: PoolPointer<'static, GcBox<V>>,
     ) -> Self {
         Self {
             key_ptr,
-            key_alloc_id,
+            key_slot,
             value_ptr,
             _marker: PhantomData,
         }
@@ -59,11 +60,8 @@ impl<'id, K: Trace + ?Sized, V: Trace> Ephemeron<'id, K, V> {
 
 impl<'id, K: Trace + ?Sized, V: Trace> Ephemeron<'id, K, V> {
     /// Returns the value if the key is still alive, or `None` if collected.
-    pub fn get_value<'gc>(&self, _cx: &MutationContext<'id, 'gc>) -> Option<Gc<'gc, V>> {
-        let key_alive = self
-            .key_ptr
-            .is_some_and(|p| unsafe { (*p.as_ptr().as_ptr()).0.alloc_id == self.key_alloc_id });
-        if key_alive {
+    pub fn get_value<'gc>(&self, cx: &MutationContext<'id, 'gc>) -> Option<Gc<'gc, V>> {
+        if self.has_value(cx) {
             Some(Gc::with_pointer(self.value_ptr))
         } else {
             None
@@ -71,11 +69,8 @@ impl<'id, K: Trace + ?Sized, V: Trace> Ephemeron<'id, K, V> {
     }
 
     /// Returns the key if still alive, or `None` if collected.
-    pub fn key<'gc>(&self, _cx: &MutationContext<'id, 'gc>) -> Option<Gc<'gc, K>> {
-        let key_alive = self
-            .key_ptr
-            .is_some_and(|p| unsafe { (*p.as_ptr().as_ptr()).0.alloc_id == self.key_alloc_id });
-        if key_alive {
+    pub fn key<'gc>(&self, cx: &MutationContext<'id, 'gc>) -> Option<Gc<'gc, K>> {
+        if self.has_value(cx) {
             self.key_ptr.map(|ptr| Gc::with_pointer(ptr))
         } else {
             None
@@ -90,9 +85,10 @@ impl<'id, K: Trace + ?Sized, V: Trace> Ephemeron<'id, K, V> {
     }
 
     /// Returns `true` if the key is still alive (the value is reachable).
-    pub fn has_value(&self) -> bool {
-        self.key_ptr
-            .is_some_and(|p| unsafe { (*p.as_ptr().as_ptr()).0.alloc_id == self.key_alloc_id })
+    ///
+    /// The key is checked through its weak slot, a swept key is never read.
+    pub fn has_value<'gc>(&self, cx: &MutationContext<'id, 'gc>) -> bool {
+        self.key_ptr.is_some() && cx.collector.is_live(self.key_slot)
     }
 }
 
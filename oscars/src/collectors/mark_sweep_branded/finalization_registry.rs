@@ -0,0 +1,218 @@
+//! `FinalizationRegistry<'id, H>` for cleanup callbacks outside collection.
+
+use crate::{
+    alloc::mempool3::PoolPointer,
+    collectors::mark_sweep_branded::{
+        gc::Gc,
+        gc_box::{GcBox, GcColor},
+        mutation_ctx::MutationContext,
+        trace::{Finalize, Trace, Tracer},
+        weak::WeakSlot,
+    },
+};
+use core::cell::RefCell;
+use core::marker::PhantomData;
+use rust_alloc::rc::Rc;
+use rust_alloc::vec::Vec;
+
+/// A registered target and its held value, with the weak slot of the
+/// optional unregister token.
+struct RegistryCell {
+    target: PoolPointer<'static, GcBox<()>>,
+    held: PoolPointer<'static, GcBox<()>>,
+    token: Option<WeakSlot>,
+}
+
+/// Type-erased registry state shared between the handle and the collector.
+#[derive(Default)]
+pub(crate) struct RegistryInner {
+    cells: RefCell<Vec<RegistryCell>>,
+    /// Held values of collected targets, waiting for the embedder.
+    cleanup: RefCell<Vec<PoolPointer<'static, GcBox<()>>>>,
+}
+
+fn is_white(ptr: PoolPointer<'static, GcBox<()>>) -> bool {
+    // SAFETY: cells are dropped before their target is swept
+    unsafe { (*ptr.as_ptr().as_ptr()).0.color.get() == GcColor::White }
+}
+
+impl RegistryInner {
+    /// Marks the queued held values, they stay alive until they are taken.
+    pub(crate) fn trace_cleanup(&self, tracer: &mut Tracer) {
+        for held in self.cleanup.borrow().iter() {
+            tracer.mark_raw(held.as_ptr().cast::<u8>());
+        }
+    }
+
+    /// Marks the held values of reachable targets, returning `true` if any
+    /// was newly marked.
+    pub(crate) fn trace_live_cells(&self, tracer: &mut Tracer) -> bool {
+        let mut any_newly_marked = false;
+        for cell in self.cells.borrow().iter() {
+            if !is_white(cell.target) {
+                any_newly_marked |= tracer.mark_raw(cell.held.as_ptr().cast::<u8>());
+            }
+        }
+        any_newly_marked
+    }
+
+    /// Moves the held values of unreachable targets to the cleanup queue,
+    /// marking them so they survive the sweep. Returns `true` if any was newly
+    /// marked.
+    pub(crate) fn queue_dead_cells(&self, tracer: &mut Tracer) -> bool {
+        let mut any_newly_marked = false;
+        let mut cleanup = self.cleanup.borrow_mut();
+        for cell in self
+            .cells
+            .borrow_mut()
+            .extract_if(.., |cell| is_white(cell.target))
+        {
+            any_newly_marked |= tracer.mark_raw(cell.held.as_ptr().cast::<u8>());
+            cleanup.push(cell.held);
+        }
+        any_newly_marked
+    }
+}
+
+/// Delivers a held value once its target has been collected, the building
+/// block of a JS `FinalizationRegistry`.
+///
+/// Unlike [`Finalize::finalize`], nothing runs inside the collection: the
+/// held values of collected targets are queued, and the embedder takes them
+/// with [`cleanup_some`](Self::cleanup_some) when it chooses.
+///
+/// Held values are kept alive by the registry while their target is
+/// reachable, and while they wait in the queue. Targets and unregister tokens
+/// are held weakly.
+pub struct FinalizationRegistry<'id, H: Trace> {
+    pub(crate) inner: Rc<RegistryInner>,
+    pub(crate) _marker: PhantomData<(*mut &'id (), *const H)>,
+}
+
+impl<'id, H: Trace> FinalizationRegistry<'id, H> {
+    /// Creates an empty registry tracked by the collector of `cx`.
+    pub fn new<'gc>(cx: &MutationContext<'id, 'gc>) -> Self {
+        let inner = Rc::new(RegistryInner::default());
+        cx.collector.registries.borrow_mut().push(inner.clone());
+        Self {
+            inner,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Queues `held` once `target` is collected.
+    pub fn register<'gc, T: Trace + ?Sized>(
+        &self,
+        cx: &MutationContext<'id, 'gc>,
+        target: &Gc<'gc, T>,
+        held: H,
+    ) where
+        H: Finalize + 'gc,
+    {
+        self.push(cx, target, held, None);
+    }
+
+    /// Queues `held` once `target` is collected, unless the registration is
+    /// first removed by [`unregister`](Self::unregister) with `token`.
+    pub fn register_with_token<'gc, T: Trace + ?Sized, U: Trace + ?Sized>(
+        &self,
+        cx: &MutationContext<'id, 'gc>,
+        target: &Gc<'gc, T>,
+        held: H,
+        token: &Gc<'gc, U>,
+    ) where
+        H: Finalize + 'gc,
+    {
+        let token = cx.collector.weak_slot_of(token.ptr);
+        self.push(cx, target, held, Some(token));
+    }
+
+    fn push<'gc, T: Trace + ?Sized>(
+        &self,
+        cx: &MutationContext<'id, 'gc>,
+        target: &Gc<'gc, T>,
+        held: H,
+        token: Option<WeakSlot>,
+    ) where
+        H: Finalize + 'gc,
+    {
+        let held = cx
+            .try_alloc(held)
+            .expect("FinalizationRegistry held value alloc");
+        // SAFETY: GcBox<T> and GcBox<H> are erased to GcBox<()>, the collector
+        // only reads the fixed size prefix fields via these pointers
+        let (target, held) = unsafe {
+            (
+                target.ptr.to_erased().to_typed_pool_pointer::<GcBox<()>>(),
+                held.ptr.to_erased().to_typed_pool_pointer::<GcBox<()>>(),
+            )
+        };
+        self.inner.cells.borrow_mut().push(RegistryCell {
+            target,
+            held,
+            token,
+        });
+    }
+
+    /// Removes every registration made with `token`, their held values are
+    /// collected like any other unreachable value. Returns `true` if there
+    /// was one.
+    pub fn unregister<'gc, U: Trace + ?Sized>(
+        &self,
+        cx: &MutationContext<'id, 'gc>,
+        token: &Gc<'gc, U>,
+    ) -> bool {
+        // a collected token had its slot cleared, so a token reusing its box
+        // or its slot gets a different one
+        let header = unsafe { &(*token.ptr.as_ptr().as_ptr()).0.weak_slot };
+        let Some(token) = cx.collector.weak_table.borrow().existing_slot(header) else {
+            // never registered as a token
+            return false;
+        };
+        let mut cells = self.inner.cells.borrow_mut();
+        let before = cells.len();
+        cells.retain(|cell| cell.token != Some(token));
+        before != cells.len()
+    }
+
+    /// Returns the number of held values waiting in the cleanup queue.
+    pub fn pending_cleanup(&self) -> usize {
+        self.inner.cleanup.borrow().len()
+    }
+
+    /// Drains the cleanup queue, passing each held value to `callback`.
+    ///
+    /// Values queued while the callback runs are left for the next call.
+    pub fn cleanup_some<'gc>(
+        &self,
+        _cx: &MutationContext<'id, 'gc>,
+        mut callback: impl FnMut(Gc<'gc, H>),
+    ) where
+        H: 'gc,
+    {
+        let queued = core::mem::take(&mut *self.inner.cleanup.borrow_mut());
+        for held in queued {
+            // SAFETY: the queue only holds values allocated as `H` by `push`
+            callback(Gc::with_pointer(unsafe {
+                held.to_erased().to_typed_pool_pointer::<GcBox<H>>()
+            }));
+        }
+    }
+}
+
+impl<'id, H: Trace> Clone for FinalizationRegistry<'id, H> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'id, H: Trace> Finalize for FinalizationRegistry<'id, H> {}
+
+unsafe impl<'id, H: Trace> Trace for FinalizationRegistry<'id, H> {
+    // Held values are marked by the collector through the registry it tracks,
+    // targets and tokens are weak.
+    unsafe fn trace(&self, _tracer: &mut Tracer) {}
+}
@@ -6,8 +6,12 @@ use core::ptr::NonNull;
 
 use crate::alloc::mempool3::{PoolAllocator, PoolItem};
 use crate::collectors::mark_sweep_branded::trace::{Trace, TraceFn, Tracer};
+use crate::collectors::mark_sweep_branded::weak::WeakTable;
+use crate::collectors::unwind::PanicSink;
 
-pub(crate) type DropFn = unsafe fn(&mut PoolAllocator<'static>, NonNull<u8>);
+// finalizes, drops and frees a slot, the flag asks to poison the dropped value;
+// panics of the finalizer and destructor are caught by the sink
+pub(crate) type DropFn = unsafe fn(&mut PoolAllocator<'static>, NonNull<u8>, bool, &PanicSink);
 
 /// The tri-color marking state of a [`GcBox`]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -27,11 +31,21 @@ pub(crate) enum GcColor {
 pub struct GcBox<T: ?Sized> {
     /// tricolor marking state, updated by the mark phase
     pub(crate) color: Cell<GcColor>,
+    /// Collections survived since the last access through a [`SoftGc`].
+    ///
+    /// [`SoftGc`]: crate::collectors::mark_sweep_branded::SoftGc
+    pub(crate) soft_age: Cell<u8>,
+    /// Index of the weak table slot of this value, or `WeakTable::NO_SLOT`
+    /// until a weak reference to it is created.
+    pub(crate) weak_slot: Cell<u32>,
     /// Type-erased trace function.
     pub(crate) trace_fn: TraceFn,
     /// Type-erased finalize and free fn
     pub(crate) drop_fn: DropFn,
-    /// Allocation ID used to validate weak pointers.
+    /// Allocation ID, set to `FREED_ALLOC_ID` by the sweep.
+    ///
+    /// Weak references are validated through the weak table instead, the
+    /// header of a swept box may be unmapped and must not be read.
     pub(crate) alloc_id: usize,
     /// Unique identifier for the concrete type `T`.
     ///
@@ -39,6 +53,8 @@ pub struct GcBox<T: ?Sized> {
     /// (eg. `'gc`) without requiring `T: 'static`, giving us a stable
     /// unique identity guarantee for sound downcasting.
     pub(crate) type_id: TypeId,
+    /// Returns the Rust type name of `T`, used by heap introspection.
+    pub(crate) type_name: fn() -> &'static str,
     /// The user value.
     pub(crate) value: T,
 }
@@ -54,10 +70,13 @@ impl<T: Trace> GcBox<T> {
     pub(crate) fn new(value: T, trace_fn: TraceFn, drop_fn: DropFn, alloc_id: usize) -> Self {
         Self {
             color: Cell::new(GcColor::White),
+            soft_age: Cell::new(0),
+            weak_slot: Cell::new(WeakTable::NO_SLOT),
             trace_fn,
             drop_fn,
             alloc_id,
             type_id: typeid::of::<T>(),
+            type_name: core::any::type_name::<T>,
             value,
         }
     }
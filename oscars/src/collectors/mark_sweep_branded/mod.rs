@@ -3,35 +3,70 @@
 
 pub mod cell;
 pub mod ephemeron;
+pub mod finalization_registry;
 pub mod gc;
 pub mod gc_box;
 pub mod mutation_ctx;
 pub mod root;
+pub mod soft;
 pub mod trace;
 pub mod weak;
+pub mod weak_map;
 
 #[cfg(all(test, feature = "mark_sweep_branded"))]
 mod tests;
 
 pub use cell::GcRefCell;
 pub use ephemeron::Ephemeron;
+pub use finalization_registry::FinalizationRegistry;
 pub use gc::Gc;
 pub use mutation_ctx::MutationContext;
 pub use root::Root;
+pub use soft::SoftGc;
+// soft targets age the same way in both collectors
+pub use crate::collectors::mark_sweep::SoftPolicy;
 pub use trace::{Finalize, Trace, Tracer};
 pub use weak::WeakGc;
+pub use weak_map::WeakMap;
 
 use crate::alloc::mempool3::{PoolAllocError, PoolAllocator, PoolPointer};
+use crate::collectors::observer::{GcObserver, ObserverId, Observers};
+use crate::collectors::policy::{GcPolicy, Scheduler};
+use crate::collectors::retaining::{PathEdge, PathRoot, RetainingPath, shortest_path};
+use crate::collectors::stats::{GcClock, GcStats, StatsRecorder};
+use crate::collectors::unwind::PanicSink;
+use crate::collectors::zeal::{Zeal, ZealState};
 use core::cell::{Cell, RefCell};
 use core::marker::PhantomData;
 use core::ptr::NonNull;
+use finalization_registry::RegistryInner;
 use gc_box::{DropFn, GcBox, GcColor};
+use hashbrown::HashMap;
 use root::RootSentinel;
+use rust_alloc::boxed::Box;
+use rust_alloc::rc::Rc;
 use rust_alloc::vec::Vec;
+use rustc_hash::FxBuildHasher;
+use weak::{WeakSlot, WeakTable};
+use weak_map::WeakMapInner;
+
+/// A callback registered by [`WeakGc::new_with_callback`], with the weak
+/// slot of its target.
+pub(crate) struct WeakCallback {
+    pub(crate) slot: WeakSlot,
+    pub(crate) callback: Box<dyn FnOnce()>,
+}
+
+/// The target of a [`SoftGc`] with its weak slot.
+pub(crate) struct SoftRef {
+    pub(crate) target: PoolPointer<'static, GcBox<()>>,
+    pub(crate) slot: WeakSlot,
+}
 
-/// Type-erased ephemeron registration.
+/// Type-erased ephemeron registration, with the weak slot of its key.
 pub(crate) struct EphemeronEntry {
     pub(crate) key_ptr: Option<PoolPointer<'static, GcBox<()>>>,
+    pub(crate) key_slot: WeakSlot,
     pub(crate) value_ptr: PoolPointer<'static, GcBox<()>>,
 }
 
@@ -45,6 +80,32 @@ pub struct Collector {
     pub(crate) sentinel: RootSentinel,
     pub(crate) generic_alloc_id: Cell<usize>,
     pub(crate) ephemerons: RefCell<Vec<EphemeronEntry>>,
+    // finalization registries, dropped once the collector holds the last handle
+    pub(crate) registries: RefCell<Vec<Rc<RegistryInner>>>,
+    // weak maps, dropped once the collector holds the last handle
+    pub(crate) weak_maps: RefCell<Vec<Rc<WeakMapInner>>>,
+    // targets of `WeakGc::deref_keep`, traced as roots until cleared
+    pub(crate) kept_objects: RefCell<Vec<PoolPointer<'static, GcBox<()>>>>,
+    // callbacks of `WeakGc::new_with_callback`, run after their target is swept
+    pub(crate) weak_callbacks: RefCell<Vec<WeakCallback>>,
+    // targets of `SoftGc`s keyed by address, with their weak slot, traced as
+    // roots while fresh
+    pub(crate) soft_refs: RefCell<HashMap<usize, SoftRef, FxBuildHasher>>,
+    // liveness of the targets of weak handles, cleared by the sweep; a swept
+    // box may be unmapped, so nothing reads its header after the sweep
+    pub(crate) weak_table: RefCell<WeakTable>,
+    soft_policy: Cell<SoftPolicy>,
+    // decides when to collect and how the heap threshold moves between cycles
+    scheduler: Scheduler,
+    // set when the policy asked for a collection, branded `Gc`s on the stack
+    // are not roots so the cycle is deferred to `GcContext::collect_if_needed`
+    collect_needed: Cell<bool>,
+    stats: StatsRecorder,
+    observers: Observers,
+    zeal: ZealState,
+    // the first panic of a finalizer or destructor, raised once the
+    // collection or teardown that ran it is over
+    panics: PanicSink,
 }
 
 impl Default for Collector {
@@ -61,17 +122,230 @@ impl Collector {
             sentinel: RootSentinel::new(),
             generic_alloc_id: Cell::new(0),
             ephemerons: RefCell::new(Vec::new()),
+            registries: RefCell::new(Vec::new()),
+            weak_maps: RefCell::new(Vec::new()),
+            kept_objects: RefCell::new(Vec::new()),
+            weak_callbacks: RefCell::new(Vec::new()),
+            soft_refs: RefCell::new(HashMap::default()),
+            weak_table: RefCell::new(WeakTable::default()),
+            soft_policy: Cell::new(SoftPolicy::default()),
+            scheduler: Scheduler::default(),
+            collect_needed: Cell::new(false),
+            stats: StatsRecorder::default(),
+            observers: Observers::default(),
+            zeal: ZealState::default(),
+            panics: PanicSink::default(),
+        }
+    }
+
+    /// Sets the [`GcPolicy`] deciding when this collector runs.
+    pub fn with_policy(self, policy: impl GcPolicy + 'static) -> Self {
+        self.set_policy(policy);
+        self
+    }
+
+    /// Replaces the [`GcPolicy`], the new policy is consulted from the next
+    /// allocation on.
+    pub fn set_policy(&self, policy: impl GcPolicy + 'static) {
+        self.scheduler.set_policy(Box::new(policy));
+    }
+
+    /// Sets the [`SoftPolicy`] deciding how long [`SoftGc`] targets are kept.
+    pub fn with_soft_policy(self, policy: SoftPolicy) -> Self {
+        self.set_soft_policy(policy);
+        self
+    }
+
+    /// Replaces the [`SoftPolicy`], the new policy applies from the next
+    /// collection on.
+    pub fn set_soft_policy(&self, policy: SoftPolicy) {
+        self.soft_policy.set(policy);
+    }
+
+    pub fn soft_policy(&self) -> SoftPolicy {
+        self.soft_policy.get()
+    }
+
+    /// Returns the heap size in bytes at which the next collection is requested.
+    pub fn heap_threshold(&self) -> usize {
+        self.pool.borrow().heap_threshold()
+    }
+
+    /// Returns true if the policy requested a collection since the last cycle.
+    pub fn collect_needed(&self) -> bool {
+        self.collect_needed.get()
+    }
+
+    /// Sets the [`GcClock`] used to measure collection pauses.
+    pub fn with_clock(self, clock: impl GcClock + 'static) -> Self {
+        self.set_clock(clock);
+        self
+    }
+
+    /// Replaces the [`GcClock`] used to measure collection pauses.
+    pub fn set_clock(&self, clock: impl GcClock + 'static) {
+        self.stats.set_clock(Box::new(clock));
+    }
+
+    /// Returns the statistics gathered since creation or the last [`reset_stats`].
+    ///
+    /// [`reset_stats`]: Self::reset_stats
+    pub fn stats(&self) -> GcStats {
+        self.stats.stats()
+    }
+
+    /// Clears the gathered statistics.
+    pub fn reset_stats(&self) {
+        self.stats.reset(self.pool.borrow().heap_size());
+    }
+
+    /// Registers a [`GcObserver`] notified around every collection cycle.
+    ///
+    /// Here ephemeron entries are pruned after the sweep, so
+    /// [`GcObserver::on_weak_pruned`] runs once dead values are already freed.
+    /// See [`observer`](crate::collectors::observer) for what callbacks may do.
+    pub fn add_observer(&self, observer: impl GcObserver + 'static) -> ObserverId {
+        self.observers.add(Box::new(observer))
+    }
+
+    /// Removes a previously registered observer, returns false if it was
+    /// already removed.
+    pub fn remove_observer(&self, id: ObserverId) -> bool {
+        self.observers.remove(id)
+    }
+
+    /// Installs a [`Zeal`] stress mode, see [`zeal`](crate::collectors::zeal).
+    ///
+    /// Branded `Gc`s on the stack are not roots, so instead of collecting
+    /// inside `try_alloc` every `n`th allocation requests a collection that
+    /// runs at the next [`GcContext::collect_if_needed`].
+    pub fn with_zeal(self, zeal: Zeal) -> Self {
+        self.set_zeal(Some(zeal));
+        self
+    }
+
+    /// Installs or, with `None`, removes the [`Zeal`] stress mode. The
+    /// allocation count restarts from zero.
+    pub fn set_zeal(&self, zeal: Option<Zeal>) {
+        self.zeal.set(zeal);
+    }
+
+    pub fn zeal(&self) -> Option<Zeal> {
+        self.zeal.get()
+    }
+
+    /// Returns the shortest chain of references from a [`Root`] to `gc`, the
+    /// answer to why it survives collections.
+    ///
+    /// Returns `None` if `gc` is not reachable from any root. Must not be
+    /// called from inside a collection.
+    pub fn retaining_path<T: Trace + ?Sized>(&self, gc: &Gc<'_, T>) -> Option<RetainingPath> {
+        self.retaining_path_with_roots(gc, |_| {})
+    }
+
+    /// Like [`retaining_path`](Self::retaining_path), also starting from the
+    /// external roots traced by `trace_external`.
+    pub(crate) fn retaining_path_with_roots<T, F>(
+        &self,
+        gc: &Gc<'_, T>,
+        trace_external: F,
+    ) -> Option<RetainingPath>
+    where
+        T: Trace + ?Sized,
+        F: FnOnce(&mut Tracer),
+    {
+        use crate::alloc::mempool3::PoolItem;
+
+        unsafe fn gc_box<'a>(addr: usize) -> &'a GcBox<()> {
+            unsafe { &(*(addr as *const PoolItem<GcBox<()>>)).0 }
+        }
+
+        let target = gc.ptr.as_ptr().cast::<u8>().as_ptr() as usize;
+
+        let mut tracer = Tracer::new();
+        trace_external(&mut tracer);
+        let external: Vec<usize> = tracer
+            .worklist
+            .drain(..)
+            .map(|(ptr, _)| ptr.as_ptr() as usize)
+            .collect();
+        let roots = self.sentinel.iter().map(|link_ptr| unsafe {
+            let erased = link_ptr.cast::<root::ErasedRootNode>();
+            (
+                (*erased.as_ptr()).gc_ptr.as_ptr().cast::<u8>().as_ptr() as usize,
+                PathRoot::Root,
+            )
+        });
+
+        let mut ephemeron_values: HashMap<usize, Vec<usize>, FxBuildHasher> = HashMap::default();
+        for entry in self.ephemerons.borrow().iter() {
+            if let Some(key_ptr) = entry.key_ptr {
+                ephemeron_values
+                    .entry(key_ptr.as_ptr().as_ptr() as usize)
+                    .or_default()
+                    .push(entry.value_ptr.as_ptr().as_ptr() as usize);
+            }
+        }
+        for map in self.weak_maps.borrow().iter() {
+            map.for_each_entry(|key, value| ephemeron_values.entry(key).or_default().push(value));
+        }
+
+        // tracing a box blackens it and greys the children it reaches for the
+        // first time, which are exactly the ones the search has not seen yet
+        let path = shortest_path(
+            roots.chain(external.into_iter().map(|addr| (addr, PathRoot::External))),
+            target,
+            |addr, targets| {
+                let trace_fn = unsafe { gc_box(addr).trace_fn };
+                unsafe { trace_fn(NonNull::new_unchecked(addr as *mut u8), &mut tracer) };
+                targets.extend(
+                    tracer
+                        .worklist
+                        .drain(..)
+                        .map(|(ptr, _)| (ptr.as_ptr() as usize, PathEdge::Strong)),
+                );
+                if let Some(values) = ephemeron_values.get(&addr) {
+                    targets.extend(values.iter().map(|&value| (value, PathEdge::Ephemeron)));
+                }
+            },
+            |addr| unsafe { (gc_box(addr).type_name)() },
+        );
+
+        for ptr in self.pool.borrow().iter_live_slots() {
+            unsafe { gc_box(ptr.as_ptr() as usize).color.set(GcColor::White) };
         }
+        path
+    }
+
+    /// Releases the objects kept alive by [`WeakGc::deref_keep`], the
+    /// ECMAScript `ClearKeptObjects` operation run at the end of a job.
+    pub fn clear_kept_objects(&self) {
+        self.kept_objects.borrow_mut().clear();
+    }
+
+    /// Returns the weak table slot of the live box behind `ptr`, allocating
+    /// one on first use.
+    pub(crate) fn weak_slot_of<T: ?Sized>(&self, ptr: PoolPointer<'static, GcBox<T>>) -> WeakSlot {
+        // SAFETY: the caller holds a `Gc` to the box, so it is not swept yet
+        let header = unsafe { &(*ptr.as_ptr().as_ptr()).0.weak_slot };
+        self.weak_table.borrow_mut().slot_for(header)
+    }
+
+    /// Returns `true` if the target `slot` was handed out for is alive.
+    pub(crate) fn is_live(&self, slot: WeakSlot) -> bool {
+        self.weak_table.borrow().is_live(slot)
     }
 
     /// Registers an ephemeron key/value pair for processing during collection.
     pub(crate) fn register_ephemeron(
         &self,
         key_ptr: PoolPointer<'static, GcBox<()>>,
+        key_slot: WeakSlot,
         value_ptr: PoolPointer<'static, GcBox<()>>,
     ) {
         self.ephemerons.borrow_mut().push(EphemeronEntry {
             key_ptr: Some(key_ptr),
+            key_slot,
             value_ptr,
         });
     }
@@ -114,8 +388,8 @@ impl Collector {
         let alloc_id = self.generic_alloc_id.get();
 
         // Check for alloc_id wrap before incrementing.
-        // If alloc_id reaches FREED_ALLOC_ID (usize::MAX), weak reference validation
-        // would break because freed slots are marked with this sentinel value.
+        // If alloc_id reaches FREED_ALLOC_ID (usize::MAX), a live box could not
+        // be told apart from a swept one, which are marked with this sentinel value.
         if alloc_id == GcBox::<()>::FREED_ALLOC_ID {
             return Err(PoolAllocError::AllocIdExhausted);
         }
@@ -125,12 +399,19 @@ impl Collector {
         unsafe fn drop_and_free<T: trace::Trace + trace::Finalize>(
             pool: &mut PoolAllocator<'static>,
             ptr: NonNull<u8>,
+            poison: bool,
+            panics: &PanicSink,
         ) {
             use crate::alloc::mempool3::PoolItem;
             unsafe {
                 let typed_ptr = ptr.cast::<PoolItem<GcBox<T>>>();
-                (*typed_ptr.as_ptr()).0.value.finalize();
-                core::ptr::drop_in_place(typed_ptr.as_ptr());
+                panics.call(|| (*typed_ptr.as_ptr()).0.value.finalize());
+                panics.call(|| core::ptr::drop_in_place(typed_ptr.as_ptr()));
+                // weak handles check their `WeakTable` slot instead of the
+                // header, so nothing reads the slot once it is freed
+                if poison && let Some(size) = pool.slot_size_of(ptr) {
+                    crate::collectors::zeal::poison(ptr.as_ptr(), size);
+                }
                 pool.free_slot(ptr);
             }
         }
@@ -142,6 +423,17 @@ impl Collector {
             drop_and_free::<T>,
             alloc_id,
         ))?;
+        self.stats.observe_heap(pool.heap_size());
+        let zeal_due = self.zeal.tick();
+        if self.scheduler.on_alloc(
+            core::mem::size_of::<crate::alloc::mempool3::PoolItem<GcBox<T>>>(),
+            pool.heap_size(),
+            pool.heap_threshold(),
+            !pool.is_below_threshold(),
+        ) || zeal_due
+        {
+            self.collect_needed.set(true);
+        }
 
         drop(pool);
 
@@ -158,6 +450,10 @@ impl Collector {
     where
         F: FnOnce(&mut Tracer),
     {
+        self.collect_needed.set(false);
+        let heap_size_before = self.pool.borrow().heap_size();
+        let started = self.stats.begin_cycle();
+        self.observers.notify(|o| o.on_collection_start());
         let mut tracer = Tracer::new();
 
         trace_external(&mut tracer);
@@ -172,11 +468,45 @@ impl Collector {
             }
         }
 
+        for kept in self.kept_objects.borrow().iter() {
+            tracer.mark_raw(kept.as_ptr().cast::<u8>());
+        }
+
+        // soft targets accessed within the policy's age are roots this cycle
+        let soft_policy = self.soft_policy.get();
+        for soft in self.soft_refs.borrow().values() {
+            // SAFETY: entries are pruned in the cycle that sweeps their target
+            let age = unsafe { (*soft.target.as_ptr().as_ptr()).0.soft_age.get() };
+            if soft_policy.is_fresh(age, heap_size_before) {
+                tracer.mark_raw(soft.target.as_ptr().cast::<u8>());
+            }
+        }
+
+        // held values waiting in a cleanup queue are roots until taken
+        let registries = {
+            let mut registries = self.registries.borrow_mut();
+            registries.retain(|registry| Rc::strong_count(registry) > 1);
+            registries.clone()
+        };
+        for registry in registries.iter() {
+            registry.trace_cleanup(&mut tracer);
+        }
+
+        let weak_maps = {
+            let mut weak_maps = self.weak_maps.borrow_mut();
+            weak_maps.retain(|map| Rc::strong_count(map) > 1);
+            weak_maps.clone()
+        };
+
         tracer.drain();
 
         // Phase 2: ephemeron fixpoint.
         // If marking a value causes new keys of other ephemerons to become
         // reachable, we must iterate until no further values are marked.
+        // Weak map entries follow the same rule.
+        // Finalization registry cells are ephemerons from target to held
+        // value, once the fixpoint settles the held values of unmarked
+        // targets are queued and marked, which may mark more keys.
         loop {
             let mut any_newly_marked = false;
             for entry in self.ephemerons.borrow().iter() {
@@ -189,14 +519,27 @@ impl Collector {
                     }
                 }
             }
+            for map in weak_maps.iter() {
+                any_newly_marked |= map.trace_live_entries(&mut tracer);
+            }
+            for registry in registries.iter() {
+                any_newly_marked |= registry.trace_live_cells(&mut tracer);
+            }
             if !any_newly_marked {
-                break;
+                for registry in registries.iter() {
+                    any_newly_marked |= registry.queue_dead_cells(&mut tracer);
+                }
+                if !any_newly_marked {
+                    break;
+                }
             }
             tracer.drain();
         }
+        self.observers.notify(|o| o.on_mark_end());
 
         // Phase 3: sweep all slots. Collect unmarked ones, then invalidate and free them.
         use crate::alloc::mempool3::PoolItem;
+        let mut survivors = 0;
         let dead: Vec<(NonNull<u8>, DropFn)> = {
             let pool = self.pool.borrow();
             pool.iter_live_slots()
@@ -204,6 +547,7 @@ impl Collector {
                     let gc_box = &(*ptr.cast::<PoolItem<GcBox<()>>>().as_ptr()).0;
                     if gc_box.color.get() == GcColor::Black {
                         gc_box.color.set(GcColor::White);
+                        survivors += 1;
                         None
                     } else {
                         Some((ptr, gc_box.drop_fn))
@@ -213,23 +557,88 @@ impl Collector {
         };
         {
             let mut pool = self.pool.borrow_mut();
+            let objects_freed = dead.len();
+            let bytes_freed = dead
+                .iter()
+                .filter_map(|&(ptr, _)| pool.slot_size_of(ptr))
+                .sum::<usize>();
+            self.stats.update_cycle(|cycle| {
+                cycle.objects_freed = objects_freed;
+                cycle.bytes_freed = bytes_freed;
+                cycle.survivors = survivors;
+            });
+            let poison = self.zeal.poisons();
+            let mut weak_table = self.weak_table.borrow_mut();
             for (ptr, drop_fn) in dead {
                 unsafe {
-                    (*ptr.cast::<PoolItem<GcBox<()>>>().as_ptr()).0.alloc_id =
-                        GcBox::<()>::FREED_ALLOC_ID;
-                    (drop_fn)(&mut pool, ptr);
+                    let gc_box = &mut (*ptr.cast::<PoolItem<GcBox<()>>>().as_ptr()).0;
+                    gc_box.alloc_id = GcBox::<()>::FREED_ALLOC_ID;
+                    if gc_box.weak_slot.get() != WeakTable::NO_SLOT {
+                        weak_table.clear(gc_box.weak_slot.get());
+                    }
+                    (drop_fn)(&mut pool, ptr, poison, &self.panics);
                 }
             }
         }
 
-        // Phase 4: remove ephemeron entries whose key was swept this cycle.
-        // A swept key has alloc_id set to FREED_ALLOC_ID by the sweep above.
-        // Using the Option lets us express the invalid state without a stored alloc_id.
-        self.ephemerons.borrow_mut().retain(|entry| {
-            entry.key_ptr.is_some_and(|key_ptr| unsafe {
-                (*key_ptr.as_ptr().as_ptr()).0.alloc_id != GcBox::<()>::FREED_ALLOC_ID
-            })
+        // Phase 4: remove ephemeron and weak map entries whose key was swept this cycle.
+        // The sweep above cleared the weak slot of every swept key, the key
+        // itself is not read since large objects are unmapped when freed.
+        let weak_table = self.weak_table.borrow();
+        let mut ephemerons = self.ephemerons.borrow_mut();
+        let ephemerons_before = ephemerons.len();
+        ephemerons.retain(|entry| entry.key_ptr.is_some() && weak_table.is_live(entry.key_slot));
+        let ephemerons_pruned = ephemerons_before - ephemerons.len();
+        drop(ephemerons);
+        let weak_map_entries_pruned = weak_maps
+            .iter()
+            .map(|map| map.prune_dead_entries(&weak_table))
+            .sum::<usize>();
+        // surviving soft targets age by one cycle
+        self.soft_refs.borrow_mut().retain(|_, soft| {
+            if !weak_table.is_live(soft.slot) {
+                return false;
+            }
+            // SAFETY: the live slot proves the target survived the sweep
+            let gc_box = unsafe { &(*soft.target.as_ptr().as_ptr()).0 };
+            let age = gc_box.soft_age.get().saturating_add(1);
+            gc_box.soft_age.set(age.min(SoftPolicy::MAX_AGE));
+            true
         });
+        self.stats.update_cycle(|cycle| {
+            cycle.ephemerons_pruned = ephemerons_pruned;
+            cycle.weak_map_entries_pruned = weak_map_entries_pruned;
+        });
+        let cleared: Vec<WeakCallback> = self
+            .weak_callbacks
+            .borrow_mut()
+            .extract_if(.., |weak| !weak_table.is_live(weak.slot))
+            .collect();
+        drop(weak_table);
+        self.observers.notify(|o| o.on_weak_pruned());
+
+        let pause = self.stats.end_cycle(started);
+        let cycle = self.stats.stats().last_cycle;
+        self.observers.notify(|o| o.on_sweep_end(&cycle));
+
+        let mut pool = self.pool.borrow_mut();
+        let threshold = pool.heap_threshold();
+        let next_threshold =
+            self.scheduler
+                .on_cycle_end(heap_size_before, pool.heap_size(), pause, threshold);
+        pool.set_heap_threshold(next_threshold);
+        drop(pool);
+
+        if next_threshold != threshold {
+            self.observers
+                .notify(|o| o.on_threshold_change(threshold, next_threshold));
+        }
+
+        // callbacks run once the cycle is over, so they may use the heap
+        for weak in cleared {
+            self.panics.call(weak.callback);
+        }
+        self.panics.resume();
     }
 }
 
@@ -273,9 +682,11 @@ impl Drop for Collector {
             unsafe {
                 (*ptr.cast::<PoolItem<GcBox<()>>>().as_ptr()).0.alloc_id =
                     GcBox::<()>::FREED_ALLOC_ID;
-                (drop_fn)(&mut pool, ptr);
+                (drop_fn)(&mut pool, ptr, false, &self.panics);
             }
         }
+        drop(pool);
+        self.panics.resume();
     }
 }
 
@@ -292,6 +703,66 @@ impl<'id> GcContext<'id> {
         self.collector.collect();
     }
 
+    /// Runs a gc cycle if the collector's policy asked for one since the last
+    /// cycle, returns whether a cycle ran.
+    pub fn collect_if_needed(&self) -> bool {
+        let needed = self.collector.collect_needed();
+        if needed {
+            self.collector.collect();
+        }
+        needed
+    }
+
+    /// Releases the objects kept alive by [`WeakGc::deref_keep`].
+    pub fn clear_kept_objects(&self) {
+        self.collector.clear_kept_objects();
+    }
+
+    /// Replaces the collector's [`GcPolicy`].
+    pub fn set_policy(&self, policy: impl GcPolicy + 'static) {
+        self.collector.set_policy(policy);
+    }
+
+    /// Replaces the collector's [`SoftPolicy`].
+    pub fn set_soft_policy(&self, policy: SoftPolicy) {
+        self.collector.set_soft_policy(policy);
+    }
+
+    /// Returns the heap size in bytes at which the next collection is requested.
+    pub fn heap_threshold(&self) -> usize {
+        self.collector.heap_threshold()
+    }
+
+    /// Replaces the collector's [`GcClock`].
+    pub fn set_clock(&self, clock: impl GcClock + 'static) {
+        self.collector.set_clock(clock);
+    }
+
+    /// Returns the collector's statistics.
+    pub fn stats(&self) -> GcStats {
+        self.collector.stats()
+    }
+
+    /// Clears the collector's statistics.
+    pub fn reset_stats(&self) {
+        self.collector.reset_stats();
+    }
+
+    /// Installs or removes the collector's [`Zeal`] stress mode.
+    pub fn set_zeal(&self, zeal: Option<Zeal>) {
+        self.collector.set_zeal(zeal);
+    }
+
+    /// Registers a [`GcObserver`] on the collector.
+    pub fn add_observer(&self, observer: impl GcObserver + 'static) -> ObserverId {
+        self.collector.add_observer(observer)
+    }
+
+    /// Removes a previously registered observer.
+    pub fn remove_observer(&self, id: ObserverId) -> bool {
+        self.collector.remove_observer(id)
+    }
+
     pub fn mutate<R>(&self, f: impl for<'gc> FnOnce(&MutationContext<'id, 'gc>) -> R) -> R {
         let cx = MutationContext {
             collector: &self.collector,
@@ -304,6 +775,11 @@ impl<'id> GcContext<'id> {
     pub(crate) fn ephemeron_count(&self) -> usize {
         self.collector.ephemerons.borrow().len()
     }
+
+    #[cfg(test)]
+    pub(crate) fn weak_slot_count(&self) -> usize {
+        self.collector.weak_table.borrow().live_slots()
+    }
 }
 
 /// Creates a new GC context.
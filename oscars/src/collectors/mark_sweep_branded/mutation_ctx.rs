@@ -11,6 +11,7 @@ use crate::{
         trace::{Finalize, Trace},
         weak::WeakGc,
     },
+    collectors::retaining::RetainingPath,
 };
 use core::marker::PhantomData;
 
@@ -66,8 +67,7 @@ impl<'id, 'gc> MutationContext<'id, 'gc> {
         &self,
         gc: &Gc<'gc, T>,
     ) -> WeakGc<'id, T> {
-        let alloc_id = unsafe { (*gc.ptr.as_ptr().as_ptr()).0.alloc_id };
-        WeakGc::with_pointer_and_alloc_id(gc.ptr, alloc_id)
+        WeakGc::with_pointer_and_slot(gc.ptr, self.collector.weak_slot_of(gc.ptr))
     }
 
     /// Promotes a `Gc` pointer to a `Root`
@@ -89,15 +89,42 @@ impl<'id, 'gc> MutationContext<'id, 'gc> {
         key: &Gc<'gc, K>,
         value: Gc<'gc, V>,
     ) -> Ephemeron<'id, K, V> {
-        let key_alloc_id = unsafe { (*key.ptr.as_ptr().as_ptr()).0.alloc_id };
+        let key_slot = self.collector.weak_slot_of(key.ptr);
         // SAFETY: GcBox<K> and GcBox<V> are erased to GcBox<()>, the collector
         // only reads the fixed size prefix fields via this pointer
         let erased_key: PoolPointer<'static, GcBox<()>> =
             unsafe { key.ptr.to_erased().to_typed_pool_pointer::<GcBox<()>>() };
         let erased_value: PoolPointer<'static, GcBox<()>> =
             unsafe { value.ptr.to_erased().to_typed_pool_pointer::<GcBox<()>>() };
-        self.collector.register_ephemeron(erased_key, erased_value);
-        Ephemeron::new_raw(Some(key.ptr), key_alloc_id, value.ptr)
+        self.collector
+            .register_ephemeron(erased_key, key_slot, erased_value);
+        Ephemeron::new_raw(Some(key.ptr), key_slot, value.ptr)
+    }
+
+    /// Returns the shortest chain of references from a [`Root`] to `gc`.
+    ///
+    /// See [`Collector::retaining_path`].
+    pub fn retaining_path<T: Trace + ?Sized>(&self, gc: &Gc<'gc, T>) -> Option<RetainingPath> {
+        self.collector.retaining_path(gc)
+    }
+
+    /// Returns the shortest chain of references to `gc` from a [`Root`] or one
+    /// of the external roots traced by `trace_external`.
+    pub fn retaining_path_with_roots<T, F>(
+        &self,
+        gc: &Gc<'gc, T>,
+        trace_external: F,
+    ) -> Option<RetainingPath>
+    where
+        T: Trace + ?Sized,
+        F: FnOnce(&mut crate::collectors::mark_sweep_branded::Tracer),
+    {
+        self.collector.retaining_path_with_roots(gc, trace_external)
+    }
+
+    /// Releases the objects kept alive by [`WeakGc::deref_keep`].
+    pub fn clear_kept_objects(&self) {
+        self.collector.clear_kept_objects();
     }
 
     /// Triggers a gc cycle.
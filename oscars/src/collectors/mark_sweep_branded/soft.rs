@@ -0,0 +1,72 @@
+//! `SoftGc<'id, T>` for references kept alive for a while after their last access.
+
+use crate::collectors::mark_sweep_branded::{
+    SoftRef,
+    gc::Gc,
+    gc_box::GcBox,
+    mutation_ctx::MutationContext,
+    trace::{Finalize, Trace, Tracer},
+    weak::WeakGc,
+};
+
+/// A reference that keeps its target alive for a while after its last
+/// access, weaker than a [`Gc`] but stronger than a [`WeakGc`].
+///
+/// The target is treated as reachable from a root until it has gone through
+/// the number of collections set by the collector's
+/// [`SoftPolicy`](crate::collectors::mark_sweep_branded::SoftPolicy) without an
+/// [`upgrade`](Self::upgrade), or the heap grows over the policy's limit.
+/// The age is shared by every soft reference to the same target.
+pub struct SoftGc<'id, T: Trace + ?Sized> {
+    weak: WeakGc<'id, T>,
+}
+
+impl<'id, T: Trace + ?Sized> SoftGc<'id, T> {
+    /// Creates a soft reference to `value`, tracked by the collector of `cx`.
+    pub fn new<'gc>(cx: &MutationContext<'id, 'gc>, value: &Gc<'gc, T>) -> Self
+    where
+        T: Finalize + 'gc,
+    {
+        let weak = cx.alloc_weak(value);
+        // SAFETY: the collector only reads the fixed size prefix of the box
+        // through the erased pointer
+        let target = unsafe { value.ptr.to_erased().to_typed_pool_pointer::<GcBox<()>>() };
+        unsafe { (*target.as_ptr().as_ptr()).0.soft_age.set(0) };
+        cx.collector.soft_refs.borrow_mut().insert(
+            target.as_ptr().as_ptr() as usize,
+            SoftRef {
+                target,
+                slot: weak.slot,
+            },
+        );
+        Self { weak }
+    }
+
+    /// Returns the target if it is still alive and restarts its age.
+    pub fn upgrade<'gc>(&self, cx: &MutationContext<'id, 'gc>) -> Option<Gc<'gc, T>> {
+        let gc = self.weak.upgrade(cx)?;
+        unsafe { (*gc.ptr.as_ptr().as_ptr()).0.soft_age.set(0) };
+        Some(gc)
+    }
+
+    /// Returns the target if it is still alive, without counting as an
+    /// access.
+    pub fn peek<'gc>(&self, cx: &MutationContext<'id, 'gc>) -> Option<Gc<'gc, T>> {
+        self.weak.upgrade(cx)
+    }
+}
+
+impl<'id, T: Trace + ?Sized> Clone for SoftGc<'id, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'id, T: Trace + ?Sized> Copy for SoftGc<'id, T> {}
+
+impl<'id, T: Trace + ?Sized> Finalize for SoftGc<'id, T> {}
+
+unsafe impl<'id, T: Trace + ?Sized> Trace for SoftGc<'id, T> {
+    // Fresh targets are marked by the collector through its soft set.
+    unsafe fn trace(&self, _tracer: &mut Tracer) {}
+}
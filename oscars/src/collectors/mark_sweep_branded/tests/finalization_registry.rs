@@ -0,0 +1,108 @@
+use super::*;
+
+#[test]
+fn held_value_is_queued_after_target_is_swept() {
+    with_gc(|ctx| {
+        let (root, registry) = ctx.mutate(|cx| {
+            let target = cx.try_alloc(1u32).unwrap();
+            let registry = FinalizationRegistry::new(cx);
+            registry.register(cx, &target, 10u32);
+            (cx.root(target).unwrap(), registry)
+        });
+
+        ctx.collect();
+        assert_eq!(registry.pending_cleanup(), 0);
+
+        drop(root);
+        ctx.collect();
+        assert_eq!(registry.pending_cleanup(), 1);
+
+        // queued held values are roots until they are taken
+        ctx.collect();
+        let weak = ctx.mutate(|cx| {
+            let mut cleaned = Vec::new();
+            registry.cleanup_some(cx, |held| cleaned.push(held));
+            assert_eq!(cleaned.len(), 1);
+            assert_eq!(*cleaned[0], 10);
+            cx.alloc_weak(&cleaned[0])
+        });
+        assert_eq!(registry.pending_cleanup(), 0);
+
+        ctx.collect();
+        ctx.mutate(|cx| assert!(weak.upgrade(cx).is_none()));
+    });
+}
+
+#[test]
+fn held_value_follows_target_liveness() {
+    with_gc(|ctx| {
+        let (root, registry) = ctx.mutate(|cx| {
+            let target = cx.try_alloc(1u32).unwrap();
+            let registry = FinalizationRegistry::new(cx);
+            registry.register(cx, &target, 10u32);
+            (cx.root(target).unwrap(), registry)
+        });
+
+        // target and held value survive
+        ctx.collect();
+        assert_eq!(ctx.stats().last_cycle.survivors, 2);
+
+        drop(registry);
+        ctx.collect();
+        assert_eq!(ctx.stats().last_cycle.objects_freed, 1);
+        drop(root);
+    });
+}
+
+#[test]
+fn unregister_removes_registrations_with_token() {
+    with_gc(|ctx| {
+        let (target, token, registry) = ctx.mutate(|cx| {
+            let target = cx.try_alloc(1u32).unwrap();
+            let token = cx.try_alloc(2u32).unwrap();
+            let registry = FinalizationRegistry::new(cx);
+            registry.register_with_token(cx, &target, 10u32, &token);
+            registry.register_with_token(cx, &target, 20u32, &token);
+            registry.register(cx, &target, 30u32);
+            (cx.root(target).unwrap(), cx.root(token).unwrap(), registry)
+        });
+
+        ctx.mutate(|cx| {
+            assert!(registry.unregister(cx, &token.get(cx)));
+            assert!(!registry.unregister(cx, &token.get(cx)));
+        });
+
+        drop(target);
+        ctx.collect();
+        ctx.mutate(|cx| {
+            let mut cleaned = Vec::new();
+            registry.cleanup_some(cx, |held| cleaned.push(*held));
+            assert_eq!(cleaned, [30]);
+        });
+        drop(token);
+    });
+}
+
+#[test]
+fn token_is_held_weakly() {
+    with_gc(|ctx| {
+        let (target, registry) = ctx.mutate(|cx| {
+            let target = cx.try_alloc(1u32).unwrap();
+            let token = cx.try_alloc(2u32).unwrap();
+            let registry = FinalizationRegistry::new(cx);
+            registry.register_with_token(cx, &target, 10u32, &token);
+            (cx.root(target).unwrap(), registry)
+        });
+
+        // the token is swept, its slot may be reused by the next allocation
+        ctx.collect();
+        ctx.mutate(|cx| {
+            let token = cx.try_alloc(3u32).unwrap();
+            assert!(!registry.unregister(cx, &token));
+        });
+
+        drop(target);
+        ctx.collect();
+        assert_eq!(registry.pending_cleanup(), 1);
+    });
+}
@@ -100,6 +100,36 @@ fn weak_upgrade_with_live_root_is_some() {
     });
 }
 
+#[test]
+fn weak_slot_is_reused_once_target_is_swept() {
+    with_gc(|ctx| {
+        let stale = ctx.mutate(|cx| {
+            let target = cx.try_alloc(1u64).unwrap();
+            let weak = cx.alloc_weak(&target);
+            assert_eq!(cx.alloc_weak(&target), weak);
+            weak
+        });
+        assert_eq!(ctx.weak_slot_count(), 1);
+        ctx.collect();
+        assert_eq!(ctx.weak_slot_count(), 0);
+
+        // the new target may reuse both the freed box and the freed slot
+        let (root, fresh) = ctx.mutate(|cx| {
+            let target = cx.try_alloc(2u64).unwrap();
+            let weak = cx.alloc_weak(&target);
+            (cx.root(target).unwrap(), weak)
+        });
+        assert_eq!(ctx.weak_slot_count(), 1);
+        ctx.collect();
+        ctx.mutate(|cx| {
+            assert!(stale.upgrade(cx).is_none());
+            assert!(!stale.is_upgradable(cx));
+            assert_eq!(fresh.upgrade(cx).as_deref(), Some(&2));
+        });
+        drop(root);
+    });
+}
+
 #[test]
 fn multiple_roots_are_independent() {
     with_gc(|ctx| {
@@ -141,7 +171,361 @@ fn root_escapes_closure_safely() {
     });
 }
 
+#[test]
+fn policy_requests_deferred_collection() {
+    use crate::collectors::policy::{CycleSummary, FixedPolicy, GcPolicy, HeapState, NeverPolicy};
+
+    struct EveryAlloc;
+    impl GcPolicy for EveryAlloc {
+        fn should_collect(&self, _heap: &HeapState) -> bool {
+            true
+        }
+        fn next_threshold(&mut self, cycle: &CycleSummary) -> usize {
+            cycle.threshold
+        }
+    }
+
+    with_gc(|ctx| {
+        ctx.set_policy(NeverPolicy);
+        ctx.mutate(|cx| {
+            cx.try_alloc(JsObject {
+                name: "ignored".into(),
+                value: 1,
+            })
+            .unwrap();
+        });
+        assert!(!ctx.collect_if_needed());
+
+        ctx.set_policy(EveryAlloc);
+        let weak = ctx.mutate(|cx| {
+            cx.alloc_weak(
+                &cx.try_alloc(JsObject {
+                    name: "requested".into(),
+                    value: 2,
+                })
+                .unwrap(),
+            )
+        });
+        assert!(ctx.collect_if_needed());
+        assert!(!ctx.collect_if_needed());
+        ctx.mutate(|cx| {
+            assert!(weak.upgrade(cx).is_none());
+        });
+
+        ctx.set_policy(FixedPolicy::new(4096));
+        ctx.collect();
+        assert_eq!(ctx.heap_threshold(), 4096);
+    });
+}
+
+#[test]
+fn stats_track_collections() {
+    with_gc(|ctx| {
+        let root = ctx.mutate(|cx| {
+            cx.try_alloc(JsObject {
+                name: "garbage".into(),
+                value: 1,
+            })
+            .unwrap();
+            cx.root(
+                cx.try_alloc(JsObject {
+                    name: "kept".into(),
+                    value: 2,
+                })
+                .unwrap(),
+            )
+            .unwrap()
+        });
+        ctx.collect();
+
+        let stats = ctx.stats();
+        assert_eq!(stats.collections, 1);
+        assert_eq!(stats.last_cycle.objects_freed, 1);
+        assert!(stats.last_cycle.bytes_freed > 0);
+        assert_eq!(stats.last_cycle.survivors, 1);
+        assert!(stats.peak_heap_size > 0);
+
+        ctx.reset_stats();
+        assert_eq!(ctx.stats().collections, 0);
+        ctx.mutate(|cx| assert_eq!(root.get(cx).value, 2));
+    });
+}
+
+#[test]
+fn observer_sees_cycle_phases_in_order() {
+    use crate::collectors::observer::GcObserver;
+    use crate::collectors::stats::CycleStats;
+    use core::cell::RefCell;
+    use rust_alloc::rc::Rc;
+    use rust_alloc::vec::Vec;
+
+    struct Recorder(Rc<RefCell<Vec<&'static str>>>);
+
+    impl GcObserver for Recorder {
+        fn on_collection_start(&mut self) {
+            self.0.borrow_mut().push("start");
+        }
+        fn on_mark_end(&mut self) {
+            self.0.borrow_mut().push("mark");
+        }
+        fn on_weak_pruned(&mut self) {
+            self.0.borrow_mut().push("weak");
+        }
+        fn on_sweep_end(&mut self, cycle: &CycleStats) {
+            assert_eq!(cycle.objects_freed, 1);
+            self.0.borrow_mut().push("sweep");
+        }
+    }
+
+    with_gc(|ctx| {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let id = ctx.add_observer(Recorder(events.clone()));
+
+        ctx.mutate(|cx| {
+            cx.try_alloc(JsObject {
+                name: "garbage".into(),
+                value: 1,
+            })
+            .unwrap();
+        });
+        ctx.collect();
+        assert_eq!(*events.borrow(), ["start", "mark", "weak", "sweep"]);
+
+        assert!(ctx.remove_observer(id));
+        events.borrow_mut().clear();
+        ctx.collect();
+        assert!(events.borrow().is_empty());
+    });
+}
+
+#[test]
+fn retaining_path_from_roots() {
+    use crate::collectors::retaining::{PathEdge, PathRoot};
+
+    with_gc(|ctx| {
+        ctx.mutate(|cx| {
+            let leaf = cx.try_alloc(7u64).unwrap();
+            let middle = cx.try_alloc(rust_alloc::vec![leaf]).unwrap();
+            let root = cx
+                .root(cx.try_alloc(rust_alloc::vec![middle]).unwrap())
+                .unwrap();
+
+            let path = cx.retaining_path(&leaf).expect("leaf is rooted");
+            assert_eq!(path.root(), PathRoot::Root);
+            assert_eq!(path.hops().len(), 3);
+            assert_eq!(path.target().type_name, "u64");
+            assert_eq!(path.target().edge, PathEdge::Strong);
+
+            // ephemeron values are retained through their key
+            let value = cx.try_alloc(3u32).unwrap();
+            assert!(cx.retaining_path(&value).is_none());
+            let _ephemeron = cx.alloc_ephemeron(&leaf, value);
+            let path = cx.retaining_path(&value).expect("key is rooted");
+            assert_eq!(path.hops().len(), 4);
+            assert_eq!(path.target().edge, PathEdge::Ephemeron);
+
+            let external = cx.try_alloc(9u16).unwrap();
+            let path = cx
+                .retaining_path_with_roots(&external, |tracer| tracer.mark(&external))
+                .expect("traced as an external root");
+            assert_eq!(path.root(), PathRoot::External);
+            assert_eq!(path.hops().len(), 1);
+
+            // the query leaves the heap ready for a regular collection
+            cx.collect();
+            assert_eq!(*root.get(cx)[0][0], 7);
+        });
+    });
+}
+
+#[test]
+fn deref_keep_holds_target_until_cleared() {
+    with_gc(|ctx| {
+        let weak = ctx.mutate(|cx| cx.alloc_weak(&cx.try_alloc(7u32).unwrap()));
+
+        ctx.mutate(|cx| assert_eq!(weak.deref_keep(cx).as_deref(), Some(&7)));
+        ctx.collect();
+        ctx.mutate(|cx| assert_eq!(weak.upgrade(cx).as_deref(), Some(&7)));
+
+        ctx.clear_kept_objects();
+        ctx.collect();
+        ctx.mutate(|cx| assert!(weak.upgrade(cx).is_none()));
+    });
+}
+
+#[test]
+fn soft_ref_survives_until_too_old() {
+    use crate::collectors::mark_sweep_branded::SoftPolicy;
+
+    with_gc(|ctx| {
+        ctx.set_soft_policy(SoftPolicy::new(2));
+        let soft = ctx.mutate(|cx| SoftGc::new(cx, &cx.try_alloc(7u32).unwrap()));
+
+        ctx.collect();
+        ctx.collect();
+        // an access restarts the age
+        ctx.mutate(|cx| assert_eq!(soft.upgrade(cx).map(|gc| *gc), Some(7)));
+        ctx.collect();
+        ctx.collect();
+        ctx.mutate(|cx| assert!(soft.peek(cx).is_some()));
+
+        ctx.collect();
+        ctx.mutate(|cx| assert!(soft.peek(cx).is_none()));
+        assert!(ctx.collector.soft_refs.borrow().is_empty());
+    });
+}
+
+#[test]
+fn weak_callback_runs_after_target_is_swept() {
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    with_gc(|ctx| {
+        let calls = Rc::new(Cell::new(0));
+        let root = ctx.mutate(|cx| {
+            let target = cx.try_alloc(7u32).unwrap();
+            let counter = Rc::clone(&calls);
+            WeakGc::new_with_callback(cx, &target, move || counter.set(counter.get() + 1));
+            cx.root(target).unwrap()
+        });
+
+        ctx.collect();
+        assert_eq!(calls.get(), 0);
+
+        drop(root);
+        ctx.collect();
+        assert_eq!(calls.get(), 1);
+
+        ctx.collect();
+        assert_eq!(calls.get(), 1);
+    });
+}
+
+#[test]
+fn collection_survives_panicking_finalizer_and_drop() {
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    struct Bomb {
+        drops: Rc<Cell<u32>>,
+        in_finalize: bool,
+        in_drop: bool,
+    }
+
+    unsafe impl crate::collectors::mark_sweep_branded::Trace for Bomb {
+        unsafe fn trace(&self, _tracer: &mut crate::collectors::mark_sweep_branded::trace::Tracer) {
+        }
+    }
+
+    impl crate::collectors::mark_sweep_branded::Finalize for Bomb {
+        fn finalize(&self) {
+            if self.in_finalize {
+                panic!("finalizer failed");
+            }
+        }
+    }
+
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+            if self.in_drop {
+                panic!("destructor failed");
+            }
+        }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+    let bomb = |in_finalize, in_drop| Bomb {
+        drops: Rc::clone(&drops),
+        in_finalize,
+        in_drop,
+    };
+    with_gc(|ctx| {
+        let root = ctx.mutate(|cx| {
+            cx.try_alloc(bomb(true, false)).unwrap();
+            cx.try_alloc(bomb(false, false)).unwrap();
+            cx.try_alloc(bomb(false, true)).unwrap();
+            cx.root(cx.try_alloc(bomb(false, false)).unwrap()).unwrap()
+        });
+
+        let result = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| ctx.collect()));
+        assert!(result.is_err());
+        assert_eq!(drops.get(), 3, "every dead value is dropped");
+
+        ctx.mutate(|cx| {
+            cx.try_alloc(bomb(false, false)).unwrap();
+        });
+        ctx.collect();
+        assert_eq!(drops.get(), 4);
+        ctx.mutate(|cx| assert!(!root.get(cx).in_drop));
+    });
+    assert_eq!(drops.get(), 5);
+}
+
+#[test]
+fn zeal_requests_collection_every_nth_allocation() {
+    use crate::collectors::zeal::Zeal;
+
+    with_gc(|ctx| {
+        ctx.set_zeal(Some(Zeal::every(3).with_poison()));
+        let root = ctx.mutate(|cx| cx.root(cx.try_alloc(7u64).unwrap()).unwrap());
+        for i in 0..6u64 {
+            ctx.mutate(|cx| {
+                cx.try_alloc(i).unwrap();
+            });
+            // collections are deferred until no unrooted Gc can be on the stack
+            let due = ctx.collect_if_needed();
+            assert_eq!(due, i % 3 == 1, "allocation {i}");
+        }
+        assert_eq!(ctx.stats().collections, 2);
+        assert_eq!(ctx.stats().last_cycle.objects_freed, 3);
+        ctx.mutate(|cx| assert_eq!(*root.get(cx), 7));
+    });
+}
+
 mod api_compliance;
 mod ephemeron;
+mod finalization_registry;
 mod uaf;
 mod ui_tests;
+mod weak_map;
+
+#[test]
+fn weak_handles_to_large_objects_outlive_the_sweep() {
+    use crate::collectors::mark_sweep_branded::SoftPolicy;
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    with_gc(|ctx| {
+        ctx.set_soft_policy(SoftPolicy::new(0));
+        let cleared = Rc::new(Cell::new(false));
+        // bigger than every size class, unmapped as soon as it is swept
+        const LARGE: usize = 1 << 16;
+        let (ephemeron, soft, token_registry) = ctx.mutate(|cx| {
+            let key = cx.try_alloc([1u8; LARGE]).unwrap();
+            let ephemeron = Ephemeron::new(cx, &key, 10u32);
+            let soft = SoftGc::new(cx, &cx.try_alloc([2u8; LARGE]).unwrap());
+            let flag = cleared.clone();
+            WeakGc::new_with_callback(cx, &cx.try_alloc([3u8; LARGE]).unwrap(), move || {
+                flag.set(true)
+            });
+            let registry = FinalizationRegistry::new(cx);
+            let target = cx.try_alloc(4u32).unwrap();
+            let token = cx.try_alloc([4u8; LARGE]).unwrap();
+            registry.register_with_token(cx, &target, 40u32, &token);
+            (ephemeron, soft, registry)
+        });
+
+        ctx.collect();
+        assert!(cleared.get());
+        assert_eq!(ctx.ephemeron_count(), 0);
+        ctx.mutate(|cx| {
+            assert!(!ephemeron.has_value(cx));
+            assert!(ephemeron.key(cx).is_none());
+            assert!(soft.peek(cx).is_none());
+            assert!(!token_registry.unregister(cx, &cx.try_alloc(5u32).unwrap()));
+        });
+        assert!(ctx.collector.soft_refs.borrow().is_empty());
+        assert_eq!(token_registry.pending_cleanup(), 1);
+    });
+}
@@ -0,0 +1,117 @@
+use super::*;
+
+#[test]
+fn value_lives_while_key_is_reachable() {
+    with_gc(|ctx| {
+        let (key, map) = ctx.mutate(|cx| {
+            let key = cx.try_alloc(1u32).unwrap();
+            let map = WeakMap::new(cx);
+            assert!(map.insert(cx, &key, 10u32).is_none());
+            (cx.root(key).unwrap(), map)
+        });
+
+        // key and value survive
+        ctx.collect();
+        assert_eq!(ctx.stats().last_cycle.survivors, 2);
+        ctx.mutate(|cx| {
+            let key = key.get(cx);
+            assert!(map.contains_key(cx, &key));
+            assert_eq!(map.get(cx, &key).map(|value| *value), Some(10));
+        });
+
+        drop(key);
+        ctx.collect();
+        assert_eq!(ctx.stats().last_cycle.objects_freed, 2);
+        assert_eq!(ctx.stats().last_cycle.weak_map_entries_pruned, 1);
+        assert!(map.is_empty());
+    });
+}
+
+#[test]
+fn insert_remove_and_replace() {
+    with_gc(|ctx| {
+        let (a, b, map) = ctx.mutate(|cx| {
+            let a = cx.try_alloc(1u32).unwrap();
+            let b = cx.try_alloc(2u32).unwrap();
+            let map = WeakMap::new(cx);
+            map.insert(cx, &a, 10u32);
+            map.insert(cx, &b, 20u32);
+            assert_eq!(map.insert(cx, &a, 11u32).map(|old| *old), Some(10));
+            assert_eq!(map.len(), 2);
+            (cx.root(a).unwrap(), cx.root(b).unwrap(), map)
+        });
+
+        // the replaced value is swept
+        ctx.collect();
+        assert_eq!(ctx.stats().last_cycle.objects_freed, 1);
+
+        ctx.mutate(|cx| {
+            assert!(map.remove(cx, &b.get(cx)));
+            assert!(!map.remove(cx, &b.get(cx)));
+            assert_eq!(map.get(cx, &a.get(cx)).map(|value| *value), Some(11));
+        });
+
+        // and so is the removed one
+        ctx.collect();
+        assert_eq!(ctx.stats().last_cycle.objects_freed, 1);
+        assert_eq!(map.len(), 1);
+        drop((a, b));
+    });
+}
+
+#[test]
+fn values_reaching_other_keys_reach_a_fixpoint() {
+    with_gc(|ctx| {
+        ctx.mutate(|cx| {
+            let a = cx.try_alloc(1u32).unwrap();
+            let b = cx.try_alloc(2u32).unwrap();
+            let map = WeakMap::<u32, Gc<'_, u32>>::new(cx);
+            // b is only reachable through the value stored for a
+            map.insert(cx, &b, cx.try_alloc(20u32).unwrap());
+            map.insert(cx, &a, b);
+            let root = cx.root(a).unwrap();
+
+            cx.collect();
+            assert_eq!(map.len(), 2);
+            assert_eq!(map.get(cx, &b).map(|value| **value), Some(20));
+
+            drop(root);
+            cx.collect();
+            assert!(map.is_empty());
+        });
+    });
+}
+
+#[test]
+fn dropped_map_releases_its_values() {
+    with_gc(|ctx| {
+        let (key, map) = ctx.mutate(|cx| {
+            let key = cx.try_alloc(1u32).unwrap();
+            let map = WeakMap::new(cx);
+            map.insert(cx, &key, 10u32);
+            (cx.root(key).unwrap(), map)
+        });
+
+        drop(map);
+        ctx.collect();
+        assert_eq!(ctx.stats().last_cycle.objects_freed, 1);
+        drop(key);
+    });
+}
+
+#[test]
+fn large_key_is_pruned_without_reading_it() {
+    with_gc(|ctx| {
+        let map = ctx.mutate(|cx| {
+            // bigger than every size class, unmapped as soon as it is swept
+            let key = cx.try_alloc([7u8; 1 << 16]).unwrap();
+            let map = WeakMap::new(cx);
+            map.insert(cx, &key, 10u32);
+            map
+        });
+
+        ctx.collect();
+        assert_eq!(ctx.stats().last_cycle.weak_map_entries_pruned, 1);
+        assert!(map.is_empty());
+    });
+}
@@ -8,23 +8,103 @@ use crate::{
         trace::{Finalize, Trace},
     },
 };
+use core::cell::Cell;
 use core::marker::PhantomData;
+use rust_alloc::vec::Vec;
+
+/// A slot of the [`WeakTable`] with the generation it had when handed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WeakSlot {
+    index: u32,
+    generation: u32,
+}
+
+/// The side table weak references are validated against, one slot per
+/// target that has weak references.
+///
+/// The sweep clears the slot of every target it frees by bumping its
+/// generation, so a stale handle is detected without reading the freed
+/// memory, which the allocator may have returned to the system.
+#[derive(Default)]
+pub(crate) struct WeakTable {
+    generations: Vec<u32>,
+    free: Vec<u32>,
+}
+
+impl WeakTable {
+    /// Stored in a [`GcBox`] that has no slot.
+    pub(crate) const NO_SLOT: u32 = u32::MAX;
+
+    /// Returns the slot recorded in `header`, allocating one on first use.
+    pub(crate) fn slot_for(&mut self, header: &Cell<u32>) -> WeakSlot {
+        let index = match header.get() {
+            Self::NO_SLOT => {
+                let index = self.free.pop().unwrap_or_else(|| {
+                    let index = u32::try_from(self.generations.len())
+                        .ok()
+                        .filter(|&index| index != Self::NO_SLOT)
+                        .expect("weak slot table exhausted");
+                    self.generations.push(0);
+                    index
+                });
+                header.set(index);
+                index
+            }
+            index => index,
+        };
+        WeakSlot {
+            index,
+            generation: self.generations[index as usize],
+        }
+    }
+
+    /// Returns the slot recorded in `header`, if one was allocated.
+    pub(crate) fn existing_slot(&self, header: &Cell<u32>) -> Option<WeakSlot> {
+        match header.get() {
+            Self::NO_SLOT => None,
+            index => Some(WeakSlot {
+                index,
+                generation: self.generations[index as usize],
+            }),
+        }
+    }
+
+    /// Returns `true` if the target `slot` was handed out for is alive.
+    pub(crate) fn is_live(&self, slot: WeakSlot) -> bool {
+        self.generations[slot.index as usize] == slot.generation
+    }
+
+    /// Stales every handle to the slot and makes it available again, called
+    /// when its target is swept.
+    pub(crate) fn clear(&mut self, index: u32) {
+        // a handle would only be mistaken for live after 2^32 reuses of its slot
+        let generation = &mut self.generations[index as usize];
+        *generation = generation.wrapping_add(1);
+        self.free.push(index);
+    }
+
+    /// Returns the number of slots held by live targets.
+    #[cfg(test)]
+    pub(crate) fn live_slots(&self) -> usize {
+        self.generations.len() - self.free.len()
+    }
+}
 
 /// A weak reference to a GC managed value
 pub struct WeakGc<'id, T: Trace + ?Sized> {
     pub(crate) ptr: PoolPointer<'static, GcBox<T>>,
-    pub(crate) alloc_id: usize,
+    pub(crate) slot: WeakSlot,
     pub(crate) _marker: PhantomData<*mut &'id ()>,
 }
 
 impl<'id, T: Trace + ?Sized> WeakGc<'id, T> {
-    pub(crate) fn with_pointer_and_alloc_id(
+    pub(crate) fn with_pointer_and_slot(
         ptr: PoolPointer<'static, GcBox<T>>,
-        alloc_id: usize,
+        slot: WeakSlot,
     ) -> Self {
         Self {
             ptr,
-            alloc_id,
+            slot,
             _marker: PhantomData,
         }
     }
@@ -44,26 +124,65 @@ impl<'id, T: Trace + ?Sized> WeakGc<'id, T> {
         cx.alloc_weak(value)
     }
 
+    /// Creates a weak reference like [`new`](Self::new), and runs `callback`
+    /// after the collection that sweeps `value`.
+    ///
+    /// The callback runs once the collection has finished, even if the weak
+    /// reference is no longer used. It never runs if the collector is dropped
+    /// first.
+    pub fn new_with_callback<'gc>(
+        cx: &crate::collectors::mark_sweep_branded::MutationContext<'id, 'gc>,
+        value: &Gc<'gc, T>,
+        callback: impl FnOnce() + 'static,
+    ) -> Self
+    where
+        T: Finalize,
+    {
+        let weak = cx.alloc_weak(value);
+        cx.collector.weak_callbacks.borrow_mut().push(
+            crate::collectors::mark_sweep_branded::WeakCallback {
+                slot: weak.slot,
+                callback: rust_alloc::boxed::Box::new(callback),
+            },
+        );
+        weak
+    }
+
     /// Attempts to upgrade to a strong `Gc<'gc, T>`.
     pub fn upgrade<'gc>(
         &self,
-        _cx: &crate::collectors::mark_sweep_branded::MutationContext<'id, 'gc>,
+        cx: &crate::collectors::mark_sweep_branded::MutationContext<'id, 'gc>,
     ) -> Option<Gc<'gc, T>> {
-        // SAFETY: `_cx` proves the `Collector` is alive.
-        // `alloc_id` confirms the allocation is still valid.
-        // The allocator does not unmap memory, so reading a recycled block's `alloc_id` is safe
-        let is_valid = unsafe { (*self.ptr.as_ptr().as_ptr()).0.alloc_id == self.alloc_id };
-
-        if is_valid {
+        // `cx` proves the `Collector` is alive, and a live slot proves the
+        // target has not been swept, `ptr` is only used once that holds
+        if self.is_upgradable(cx) {
             Some(Gc::with_pointer(self.ptr))
         } else {
             None
         }
     }
 
+    /// Upgrades like [`upgrade`](Self::upgrade) and keeps the target alive
+    /// until the collector's kept objects are cleared, the semantics of
+    /// `WeakRef.prototype.deref`.
+    pub fn deref_keep<'gc>(
+        &self,
+        cx: &crate::collectors::mark_sweep_branded::MutationContext<'id, 'gc>,
+    ) -> Option<Gc<'gc, T>> {
+        let gc = self.upgrade(cx)?;
+        // SAFETY: the collector only reads the fixed size prefix of the box
+        // through the erased pointer
+        let erased = unsafe { gc.ptr.to_erased().to_typed_pool_pointer::<GcBox<()>>() };
+        cx.collector.kept_objects.borrow_mut().push(erased);
+        Some(gc)
+    }
+
     /// Returns `true` if the referenced value is still alive.
-    pub fn is_upgradable(&self) -> bool {
-        unsafe { (*self.ptr.as_ptr().as_ptr()).0.alloc_id == self.alloc_id }
+    pub fn is_upgradable<'gc>(
+        &self,
+        cx: &crate::collectors::mark_sweep_branded::MutationContext<'id, 'gc>,
+    ) -> bool {
+        cx.collector.is_live(self.slot)
     }
 }
 
@@ -79,15 +198,14 @@ impl<'id, T: Trace + ?Sized> core::fmt::Debug for WeakGc<'id, T> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("WeakGc")
             .field("ptr", &self.ptr.as_ptr())
-            .field("alloc_id", &self.alloc_id)
+            .field("slot", &self.slot)
             .finish()
     }
 }
 
 impl<'id, T: Trace + ?Sized> PartialEq for WeakGc<'id, T> {
     fn eq(&self, other: &Self) -> bool {
-        core::ptr::addr_eq(self.ptr.as_ptr().as_ptr(), other.ptr.as_ptr().as_ptr())
-            && self.alloc_id == other.alloc_id
+        self.slot == other.slot
     }
 }
 
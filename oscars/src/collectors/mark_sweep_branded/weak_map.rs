@@ -0,0 +1,201 @@
+//! `WeakMap<'id, K, V>` for associations that do not keep their keys alive.
+
+use crate::{
+    alloc::mempool3::PoolPointer,
+    collectors::mark_sweep_branded::{
+        gc::Gc,
+        gc_box::{GcBox, GcColor},
+        mutation_ctx::MutationContext,
+        trace::{Finalize, Trace, Tracer},
+        weak::{WeakSlot, WeakTable},
+    },
+};
+use core::cell::RefCell;
+use core::marker::PhantomData;
+use hashbrown::HashMap;
+use rust_alloc::rc::Rc;
+use rustc_hash::FxBuildHasher;
+
+/// A key with its weak slot, and its value.
+struct WeakMapEntry {
+    key: PoolPointer<'static, GcBox<()>>,
+    key_slot: WeakSlot,
+    value: PoolPointer<'static, GcBox<()>>,
+}
+
+/// Type-erased map state shared between the handle and the collector, keyed
+/// by key address.
+#[derive(Default)]
+pub(crate) struct WeakMapInner {
+    entries: RefCell<HashMap<usize, WeakMapEntry, FxBuildHasher>>,
+}
+
+impl WeakMapInner {
+    /// Marks the values of reachable keys, returning `true` if any was newly
+    /// marked.
+    pub(crate) fn trace_live_entries(&self, tracer: &mut Tracer) -> bool {
+        let mut any_newly_marked = false;
+        for entry in self.entries.borrow().values() {
+            // SAFETY: entries are pruned in the cycle that sweeps their key
+            if unsafe { (*entry.key.as_ptr().as_ptr()).0.color.get() } != GcColor::White {
+                any_newly_marked |= tracer.mark_raw(entry.value.as_ptr().cast::<u8>());
+            }
+        }
+        any_newly_marked
+    }
+
+    /// Removes the entries whose key was swept, returning how many.
+    ///
+    /// The keys are checked through their weak slot, a swept key may be
+    /// unmapped and is never read.
+    pub(crate) fn prune_dead_entries(&self, weak_table: &WeakTable) -> usize {
+        let mut entries = self.entries.borrow_mut();
+        let before = entries.len();
+        entries.retain(|_, entry| weak_table.is_live(entry.key_slot));
+        before - entries.len()
+    }
+
+    /// Calls `f` with the address of every key and its value.
+    pub(crate) fn for_each_entry(&self, mut f: impl FnMut(usize, usize)) {
+        for (&key, entry) in self.entries.borrow().iter() {
+            f(key, entry.value.as_ptr().as_ptr() as usize);
+        }
+    }
+}
+
+fn key_addr<T: Trace + ?Sized>(key: &Gc<'_, T>) -> usize {
+    key.ptr.as_ptr().cast::<u8>().as_ptr() as usize
+}
+
+/// A map from GC keys to GC values whose entries are removed once their key
+/// is collected, the building block of a JS `WeakMap`.
+///
+/// A value is kept alive by the map as long as its key is reachable, the
+/// ephemeron rule. Keys are compared by identity.
+pub struct WeakMap<'id, K: Trace + ?Sized, V: Trace> {
+    pub(crate) inner: Rc<WeakMapInner>,
+    pub(crate) _marker: PhantomData<(*mut &'id (), *const K, *const V)>,
+}
+
+impl<'id, K: Trace + ?Sized, V: Trace> WeakMap<'id, K, V> {
+    /// Creates an empty map tracked by the collector of `cx`.
+    pub fn new<'gc>(cx: &MutationContext<'id, 'gc>) -> Self {
+        let inner = Rc::new(WeakMapInner::default());
+        cx.collector.weak_maps.borrow_mut().push(inner.clone());
+        Self {
+            inner,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Allocates `value` and stores it for `key`, returning the value it
+    /// replaces.
+    pub fn insert<'gc>(
+        &self,
+        cx: &MutationContext<'id, 'gc>,
+        key: &Gc<'gc, K>,
+        value: V,
+    ) -> Option<Gc<'gc, V>>
+    where
+        K: 'gc,
+        V: Finalize + 'gc,
+    {
+        let value = cx.try_alloc(value).expect("WeakMap value alloc");
+        self.insert_gc(cx, key, value)
+    }
+
+    /// Stores the already allocated `value` for `key`, returning the value it
+    /// replaces.
+    pub fn insert_gc<'gc>(
+        &self,
+        cx: &MutationContext<'id, 'gc>,
+        key: &Gc<'gc, K>,
+        value: Gc<'gc, V>,
+    ) -> Option<Gc<'gc, V>>
+    where
+        K: 'gc,
+        V: 'gc,
+    {
+        let key_slot = cx.collector.weak_slot_of(key.ptr);
+        // SAFETY: GcBox<K> and GcBox<V> are erased to GcBox<()>, the collector
+        // only reads the fixed size prefix fields via these pointers
+        let entry = unsafe {
+            WeakMapEntry {
+                key: key.ptr.to_erased().to_typed_pool_pointer::<GcBox<()>>(),
+                key_slot,
+                value: value.ptr.to_erased().to_typed_pool_pointer::<GcBox<()>>(),
+            }
+        };
+        let old = self
+            .inner
+            .entries
+            .borrow_mut()
+            .insert(key_addr(key), entry)?;
+        // SAFETY: the map only holds values allocated as `V`
+        Some(Gc::with_pointer(unsafe {
+            old.value.to_erased().to_typed_pool_pointer::<GcBox<V>>()
+        }))
+    }
+
+    /// Returns the value stored for `key`.
+    pub fn get<'gc>(&self, _cx: &MutationContext<'id, 'gc>, key: &Gc<'gc, K>) -> Option<Gc<'gc, V>>
+    where
+        K: 'gc,
+        V: 'gc,
+    {
+        let entries = self.inner.entries.borrow();
+        let entry = entries.get(&key_addr(key))?;
+        // SAFETY: the map only holds values allocated as `V`
+        Some(Gc::with_pointer(unsafe {
+            entry.value.to_erased().to_typed_pool_pointer::<GcBox<V>>()
+        }))
+    }
+
+    /// Returns `true` if the map has an entry for `key`.
+    pub fn contains_key<'gc>(&self, _cx: &MutationContext<'id, 'gc>, key: &Gc<'gc, K>) -> bool
+    where
+        K: 'gc,
+    {
+        self.inner.entries.borrow().contains_key(&key_addr(key))
+    }
+
+    /// Removes the entry for `key`, returning `true` if there was one. The
+    /// value is collected like any other unreachable value.
+    pub fn remove<'gc>(&self, _cx: &MutationContext<'id, 'gc>, key: &Gc<'gc, K>) -> bool
+    where
+        K: 'gc,
+    {
+        self.inner
+            .entries
+            .borrow_mut()
+            .remove(&key_addr(key))
+            .is_some()
+    }
+
+    /// Returns the number of entries. Entries are pruned by the collection
+    /// that sweeps their key, so every one of them is live.
+    pub fn len(&self) -> usize {
+        self.inner.entries.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'id, K: Trace + ?Sized, V: Trace> Clone for WeakMap<'id, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'id, K: Trace + ?Sized, V: Trace> Finalize for WeakMap<'id, K, V> {}
+
+unsafe impl<'id, K: Trace + ?Sized, V: Trace> Trace for WeakMap<'id, K, V> {
+    // Values are marked by the collector through the map it tracks, keys are
+    // weak.
+    unsafe fn trace(&self, _tracer: &mut Tracer) {}
+}
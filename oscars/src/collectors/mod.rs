@@ -7,6 +7,22 @@ pub mod mark_sweep;
 pub mod mark_sweep_arena2;
 #[cfg(feature = "null_collector")]
 pub mod null_collector;
+#[cfg(feature = "mark_sweep")]
+pub mod observer;
+#[cfg(feature = "mark_sweep")]
+pub mod policy;
+#[cfg(any(test, feature = "heap_snapshot", feature = "mark_sweep_branded"))]
+pub mod retaining;
+#[cfg(feature = "mark_sweep")]
+pub mod shutdown;
+#[cfg(feature = "mark_sweep")]
+pub mod stats;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "mark_sweep")]
+pub(crate) mod unwind;
+#[cfg(feature = "mark_sweep")]
+pub mod zeal;
 
 #[cfg(feature = "null_collector_branded")]
 pub mod null_collector_branded;
@@ -11,7 +11,7 @@
 //! * **No cycle collection**: Leaks memory in long running programs.
 //! * **Weak pointers stay alive**: `WeakGc::upgrade` always succeeds.
 
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 use core::ptr::NonNull;
 
 use crate::{
@@ -21,7 +21,12 @@ use crate::{
         internals::{Ephemeron, GcBox, NonTraceable},
         trace::Trace,
     },
+    collectors::shutdown::{LeakReport, LeakedObject, ShutdownPolicy},
+    collectors::stats::{GcStats, StatsRecorder},
+    collectors::unwind::PanicSink,
 };
+use rust_alloc::boxed::Box;
+use rust_alloc::rc::Rc;
 use rust_alloc::vec::Vec;
 
 #[cfg(test)]
@@ -54,6 +59,16 @@ pub struct NullCollector {
     /// Heap allocations for `WeakMapInner`.
     /// Tracked to allow safe drops and freed when the collector drops.
     weak_maps: RefCell<Vec<NonNull<dyn ErasedWeakMap>>>,
+
+    /// Liveness flags of public `Ephemeron` handles, cleared when the
+    /// collector drops and frees their ephemerons.
+    ephemeron_handles: RefCell<Vec<Rc<Cell<bool>>>>,
+
+    /// What `Drop` does with a heap that still has rooted values.
+    shutdown_policy: Cell<ShutdownPolicy>,
+
+    // only the peak heap size is ever recorded, nothing is collected
+    stats: StatsRecorder,
 }
 
 impl Default for NullCollector {
@@ -63,6 +78,9 @@ impl Default for NullCollector {
             root_queue: RefCell::new(Vec::new()),
             ephemeron_queue: RefCell::new(Vec::new()),
             weak_maps: RefCell::new(Vec::new()),
+            ephemeron_handles: RefCell::new(Vec::new()),
+            shutdown_policy: Cell::new(ShutdownPolicy::default()),
+            stats: StatsRecorder::default(),
         }
     }
 }
@@ -93,6 +111,58 @@ impl NullCollector {
     pub fn pools_len(&self) -> usize {
         self.allocator.borrow().pools_len()
     }
+
+    /// Returns the statistics gathered since creation or the last [`reset_stats`].
+    ///
+    /// The null collector never collects, so only the peak heap size changes.
+    ///
+    /// [`reset_stats`]: Self::reset_stats
+    pub fn stats(&self) -> GcStats {
+        self.stats.stats()
+    }
+
+    /// Clears the gathered statistics.
+    pub fn reset_stats(&self) {
+        self.stats.reset(self.allocator.borrow().heap_size());
+    }
+
+    /// Sets the [`ShutdownPolicy`] applied when the collector is dropped while
+    /// values are still rooted.
+    ///
+    /// This matches `MarkSweepGarbageCollector::with_shutdown_policy`.
+    #[must_use]
+    pub fn with_shutdown_policy(self, policy: ShutdownPolicy) -> Self {
+        self.set_shutdown_policy(policy);
+        self
+    }
+
+    /// Replaces the [`ShutdownPolicy`].
+    pub fn set_shutdown_policy(&self, policy: ShutdownPolicy) {
+        self.shutdown_policy.set(policy);
+    }
+
+    pub fn shutdown_policy(&self) -> ShutdownPolicy {
+        self.shutdown_policy.get()
+    }
+
+    /// Lists the values currently rooted by handles outside the heap, the
+    /// values a drop of the collector would report.
+    pub fn leak_report(&self) -> LeakReport {
+        let mut report = LeakReport::default();
+        for node in self.root_queue.borrow().iter() {
+            // SAFETY: `node` is a live pool allocation with a valid vtable.
+            let gc_box = unsafe { node.as_ref().value() };
+            if gc_box.is_rooted() {
+                report.push(LeakedObject {
+                    addr: node.as_ptr() as usize,
+                    #[cfg(any(test, feature = "std"))]
+                    type_name: gc_box.type_name(),
+                    roots: gc_box.roots(),
+                });
+            }
+        }
+        report
+    }
 }
 
 impl NullCollector {
@@ -105,8 +175,11 @@ impl NullCollector {
     /// * Phase 2: call `drop_fn` for all roots and ephemerons, then
     ///   free the slots.
     ///
+    /// A panicking finalizer or destructor is recorded in `panics` and the
+    /// remaining values are still torn down.
+    ///
     /// This matches `MarkSweepGarbageCollector::sweep_all_queues`.
-    fn sweep_all_queues(&self) {
+    fn sweep_all_queues(&self, panics: &PanicSink) {
         let roots = core::mem::take(&mut *self.root_queue.borrow_mut());
         let ephemerons = core::mem::take(&mut *self.ephemeron_queue.borrow_mut());
 
@@ -114,26 +187,26 @@ impl NullCollector {
         for node in roots.iter().copied() {
             // SAFETY: `node` is a live pool allocation with a valid vtable.
             let gc_box = unsafe { node.as_ref().value() };
-            unsafe { gc_box.finalize_fn()(node) };
+            panics.call(|| unsafe { gc_box.finalize_fn()(node) });
         }
 
         for eph in ephemerons.iter().copied() {
             // SAFETY: `eph` is a live pool allocation with a valid vtable.
             let vtable = unsafe { eph.as_ref().value() };
-            unsafe { vtable.finalize_fn()(eph) };
+            panics.call(|| unsafe { vtable.finalize_fn()(eph) });
         }
 
         // Phase 2: drop + free
         for node in roots {
             // SAFETY: `drop_fn` is called exactly once before freeing the slot.
             let drop_fn = unsafe { node.as_ref().value().drop_fn() };
-            unsafe { drop_fn(node) };
+            panics.call(|| unsafe { drop_fn(node) });
             self.allocator.borrow_mut().free_slot(node.cast::<u8>());
         }
 
         for eph in ephemerons {
             let drop_fn = unsafe { eph.as_ref().value().drop_fn() };
-            unsafe { drop_fn(eph) };
+            panics.call(|| unsafe { drop_fn(eph) });
             self.allocator.borrow_mut().free_slot(eph.cast::<u8>());
         }
     }
@@ -155,7 +228,8 @@ impl NullCollector {
 impl Drop for NullCollector {
     fn drop(&mut self) {
         // If any rooted handles outlive the collector, skip teardown to
-        // avoid use-after-free. The pool pages will be freed by the allocator.
+        // avoid use-after-free unless the shutdown policy asks to finalize
+        // anyway. The pool pages will be freed by the allocator.
         // This matches `MarkSweepGarbageCollector::drop`.
         let has_rooted = self
             .root_queue
@@ -163,13 +237,25 @@ impl Drop for NullCollector {
             .iter()
             .any(|node| unsafe { node.as_ref().value().is_rooted() });
 
-        if self.pools_len() > 0 && has_rooted {
+        for alive in self.ephemeron_handles.take() {
+            alive.set(false);
+        }
+
+        let panics = PanicSink::default();
+        if self.pools_len() > 0
+            && has_rooted
+            && !self
+                .shutdown_policy
+                .get()
+                .sweeps_rooted(|| self.leak_report())
+        {
             // Intentional leak: rooted handles outlive the collector.
         } else {
-            self.sweep_all_queues();
+            self.sweep_all_queues(&panics);
         }
 
         self.drop_weak_maps();
+        panics.resume();
     }
 }
 
@@ -201,7 +287,10 @@ impl Collector for NullCollector {
         value: T,
     ) -> Result<PoolPointer<'gc, GcBox<T>>, PoolAllocError> {
         let gc_box = GcBox::new_in(value, NULL_TRACE_COLOR);
-        let arena_ptr = self.allocator.borrow_mut().try_alloc(gc_box)?;
+        let mut alloc = self.allocator.borrow_mut();
+        let arena_ptr = alloc.try_alloc(gc_box)?;
+        self.stats.observe_heap(alloc.heap_size());
+        drop(alloc);
 
         let erased: GcErasedPointer = arena_ptr.as_ptr().cast();
         self.root_queue.borrow_mut().push(erased);
@@ -209,6 +298,26 @@ impl Collector for NullCollector {
         Ok(arena_ptr)
     }
 
+    /// Move a boxed, possibly dynamically sized, value into a `GcBox` and
+    /// register it for teardown.
+    fn alloc_boxed_gc_node<'gc, T: Trace + ?Sized + 'static>(
+        &'gc self,
+        value: Box<T>,
+    ) -> Result<PoolPointer<'gc, GcBox<T>>, PoolAllocError> {
+        let (layout, _) = GcBox::boxed_layout(&*value);
+        let mut alloc = self.allocator.borrow_mut();
+        let slot = alloc.try_alloc_layout(layout)?;
+        self.stats.observe_heap(alloc.heap_size());
+        drop(alloc);
+
+        // SAFETY: `slot` was allocated with the layout of the box
+        let arena_ptr = unsafe { GcBox::write_boxed(slot, value, NULL_TRACE_COLOR) };
+        self.root_queue.borrow_mut().push(arena_ptr.cast());
+
+        // SAFETY: the box was just written to the slot
+        Ok(unsafe { PoolPointer::from_raw(arena_ptr) })
+    }
+
     /// Allocate an `Ephemeron<K, V>` and register it for teardown.
     ///
     /// No collection is ever triggered. Because the collector never sweeps,
@@ -219,7 +328,10 @@ impl Collector for NullCollector {
         value: V,
     ) -> Result<PoolPointer<'gc, Ephemeron<K, V>>, PoolAllocError> {
         let ephemeron = Ephemeron::new(key, value, NULL_TRACE_COLOR);
-        let inner_ptr = self.allocator.borrow_mut().try_alloc(ephemeron)?;
+        let mut alloc = self.allocator.borrow_mut();
+        let inner_ptr = alloc.try_alloc(ephemeron)?;
+        self.stats.observe_heap(alloc.heap_size());
+        drop(alloc);
 
         let eph_ptr = inner_ptr
             .as_ptr()
@@ -239,4 +351,22 @@ impl Collector for NullCollector {
     fn track_weak_map(&self, map: NonNull<dyn ErasedWeakMap>) {
         self.weak_maps.borrow_mut().push(map);
     }
+
+    /// No-op: nothing is collected, so every object is kept anyway.
+    #[inline]
+    fn keep_alive<T: Trace + ?Sized>(&self, _gc: &Gc<T>) {}
+
+    /// No-op: nothing is collected, so the callback never runs.
+    #[inline]
+    fn on_weak_cleared(&self, _ephemeron: NonNull<u8>, _callback: Box<dyn FnOnce()>) {}
+
+    /// No-op: nothing is collected, so every object is kept anyway.
+    #[inline]
+    fn track_soft_ref<T: Trace + ?Sized>(&self, _gc: &Gc<T>) {}
+
+    fn track_ephemeron(&self, _ephemeron: NonNull<u8>) -> Rc<Cell<bool>> {
+        let alive = Rc::new(Cell::new(true));
+        self.ephemeron_handles.borrow_mut().push(Rc::clone(&alive));
+        alive
+    }
 }
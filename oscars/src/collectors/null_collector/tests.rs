@@ -197,3 +197,134 @@ fn builder_methods_compile_and_work() {
     nc.collect();
     assert_eq!(*gc.borrow(), 77u64);
 }
+
+#[test]
+fn stats_only_track_peak_heap() {
+    let nc = NullCollector::default().with_page_size(64);
+
+    let _a = Gc::new_in(1u64, &nc);
+    let _b = Gc::new_in(2u64, &nc);
+    nc.collect();
+
+    let stats = nc.stats();
+    assert_eq!(stats.collections, 0, "the null collector never collects");
+    assert_eq!(stats.objects_freed, 0);
+    assert!(stats.peak_heap_size > 0);
+
+    nc.reset_stats();
+    assert_eq!(nc.stats().peak_heap_size, nc.allocator.borrow().heap_size());
+}
+
+#[test]
+fn unsized_values_drop_with_collector() {
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    struct DropSpy(Rc<Cell<u32>>);
+
+    impl Finalize for DropSpy {}
+
+    // SAFETY: `DropSpy` has no traceable children.
+    unsafe impl Trace for DropSpy {
+        crate::empty_trace!();
+    }
+
+    impl Drop for DropSpy {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+    {
+        let nc = NullCollector::default();
+        let spies = [DropSpy(Rc::clone(&drops)), DropSpy(Rc::clone(&drops))];
+        let slice = Gc::from_box_in(
+            rust_alloc::boxed::Box::new(spies) as rust_alloc::boxed::Box<[DropSpy]>,
+            &nc,
+        );
+        let text = Gc::from_str_in("null", &nc);
+        assert_eq!(slice.len(), 2);
+        assert_eq!(&*text, "null");
+    }
+    assert_eq!(
+        drops.get(),
+        2,
+        "collector drop must drop every slice element"
+    );
+}
+
+#[test]
+fn shutdown_policy_reports_rooted_values() {
+    use crate::collectors::shutdown::ShutdownPolicy;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static REPORTED: AtomicUsize = AtomicUsize::new(0);
+
+    let nc = NullCollector::default().with_shutdown_policy(ShutdownPolicy::Report(|report| {
+        REPORTED.store(report.objects()[0].roots.into(), Ordering::Relaxed);
+    }));
+    let rooted = Gc::new_in(5u64, &nc);
+    let holder = Gc::new_in(GcRefCell::new(rooted.clone()), &nc);
+    assert_eq!(nc.leak_report().len(), 2);
+    // the null collector never collects, the value held by `holder` keeps
+    // its root
+    drop(holder);
+    let report = nc.leak_report();
+    assert_eq!(report.len(), 1);
+    assert_eq!(report.objects()[0].type_name, "u64");
+    assert_eq!(report.objects()[0].roots, 2);
+
+    drop(nc);
+    assert_eq!(REPORTED.load(Ordering::Relaxed), 2);
+    core::mem::forget(rooted);
+}
+
+#[test]
+fn panicking_drop_still_drops_other_values() {
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    struct Bomb {
+        drops: Rc<Cell<u32>>,
+        in_finalize: bool,
+        in_drop: bool,
+    }
+
+    impl Finalize for Bomb {
+        fn finalize(&self) {
+            if self.in_finalize {
+                panic!("finalizer failed");
+            }
+        }
+    }
+
+    // SAFETY: `Bomb` has no traceable children.
+    unsafe impl Trace for Bomb {
+        crate::empty_trace!();
+    }
+
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+            if self.in_drop {
+                panic!("destructor failed");
+            }
+        }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+    let result = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| {
+        let nc = NullCollector::default();
+        let bomb = |in_finalize, in_drop| Bomb {
+            drops: Rc::clone(&drops),
+            in_finalize,
+            in_drop,
+        };
+        let _a = Gc::new_in(bomb(true, false), &nc);
+        let _b = Gc::new_in(bomb(false, false), &nc);
+        let _c = Gc::new_in(bomb(false, true), &nc);
+    }));
+    assert!(result.is_err(), "the first panic is raised after teardown");
+    assert_eq!(drops.get(), 3, "collector drop must drop every value");
+}
@@ -63,7 +63,7 @@ impl<'id, K: Trace + ?Sized, V: Trace> Ephemeron<'id, K, V> {
     ///
     /// Note: Always returns `true` in the null collector.
     /// TODO: Return `false` once the collector clears `key_ptr`.
-    pub fn has_value(&self) -> bool {
+    pub fn has_value<'gc>(&self, _cx: &MutationContext<'id, 'gc>) -> bool {
         self.key_ptr.is_some()
     }
 }
@@ -10,6 +10,9 @@ pub mod root;
 pub mod trace;
 pub mod weak;
 
+#[cfg(test)]
+mod tests;
+
 pub use cell::GcRefCell;
 pub use ephemeron::Ephemeron;
 pub use gc::Gc;
@@ -19,6 +22,7 @@ pub use trace::{Finalize, Trace, Tracer};
 pub use weak::WeakGc;
 
 use crate::alloc::mempool3::{PoolAllocError, PoolAllocator};
+use crate::collectors::unwind::PanicSink;
 use core::cell::RefCell;
 use core::marker::PhantomData;
 use core::ptr::NonNull;
@@ -49,12 +53,16 @@ impl Collector {
             ptr: NonNull<u8>,
         ) {
             use crate::alloc::mempool3::PoolItem;
+            // the value is dropped and its slot freed before a panic of
+            // either call is raised
+            let panics = PanicSink::default();
             unsafe {
                 let typed_ptr = ptr.cast::<PoolItem<GcBox<T>>>();
-                (*typed_ptr.as_ptr()).0.value.finalize();
-                core::ptr::drop_in_place(typed_ptr.as_ptr());
+                panics.call(|| (*typed_ptr.as_ptr()).0.value.finalize());
+                panics.call(|| core::ptr::drop_in_place(typed_ptr.as_ptr()));
                 pool.free_slot(ptr);
             }
+            panics.resume();
         }
 
         let mut pool = self.pool.borrow_mut();
@@ -85,11 +93,12 @@ impl Drop for Collector {
             })
             .collect();
         let mut pool = self.pool.borrow_mut();
+        let panics = PanicSink::default();
         for (ptr, drop_fn) in all {
-            unsafe {
-                (drop_fn)(&mut pool, ptr);
-            }
+            panics.call(|| unsafe { (drop_fn)(&mut pool, ptr) });
         }
+        drop(pool);
+        panics.resume();
     }
 }
 
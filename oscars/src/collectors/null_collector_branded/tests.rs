@@ -0,0 +1,52 @@
+use super::{Finalize, Trace, Tracer, with_gc};
+
+#[test]
+fn panicking_drop_still_drops_other_values() {
+    use core::cell::Cell;
+    use rust_alloc::rc::Rc;
+
+    struct Bomb {
+        drops: Rc<Cell<u32>>,
+        in_finalize: bool,
+        in_drop: bool,
+    }
+
+    unsafe impl Trace for Bomb {
+        unsafe fn trace(&self, _tracer: &mut Tracer) {}
+    }
+
+    impl Finalize for Bomb {
+        fn finalize(&self) {
+            if self.in_finalize {
+                panic!("finalizer failed");
+            }
+        }
+    }
+
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+            if self.in_drop {
+                panic!("destructor failed");
+            }
+        }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+    let bomb = |in_finalize, in_drop| Bomb {
+        drops: Rc::clone(&drops),
+        in_finalize,
+        in_drop,
+    };
+    let result = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| {
+        with_gc(|ctx| {
+            ctx.mutate(|cx| {
+                cx.try_alloc(bomb(true, false)).unwrap();
+                cx.try_alloc(bomb(false, false)).unwrap();
+                cx.try_alloc(bomb(false, true)).unwrap();
+            });
+        })
+    }));
+    assert!(result.is_err(), "the first panic is raised after teardown");
+    assert_eq!(drops.get(), 3, "collector drop must drop every value");
+}
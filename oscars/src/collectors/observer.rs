@@ -0,0 +1,87 @@
+//! Collection lifecycle hooks.
+//!
+//! A [`GcObserver`] registered on a collector is notified at fixed points of
+//! every collection cycle, in this order:
+//!
+//! 1. [`on_collection_start`]: before marking, the heap is fully intact.
+//! 2. [`on_mark_end`]: every reachable object is marked, nothing is freed yet.
+//! 3. [`on_weak_pruned`]: dead weak map entries and ephemerons were removed.
+//! 4. [`on_sweep_end`]: unreachable objects were finalized and freed.
+//! 5. [`on_threshold_change`]: only if the policy moved the heap threshold.
+//!
+//! All callbacks run while the collector is collecting. Inside a callback:
+//!
+//! - do not allocate on the collector that is notifying you, allocations made
+//!   while collecting are deferred and would escape this cycle's bookkeeping;
+//! - do not call `collect` or register / remove observers, the collector is
+//!   not reentrant and will panic;
+//! - only touch `Gc` values you know to be alive, after [`on_mark_end`] an
+//!   unreachable value may already have been finalized or freed.
+//!
+//! [`on_collection_start`]: GcObserver::on_collection_start
+//! [`on_mark_end`]: GcObserver::on_mark_end
+//! [`on_weak_pruned`]: GcObserver::on_weak_pruned
+//! [`on_sweep_end`]: GcObserver::on_sweep_end
+//! [`on_threshold_change`]: GcObserver::on_threshold_change
+
+use core::cell::{Cell, RefCell};
+
+use rust_alloc::boxed::Box;
+use rust_alloc::vec::Vec;
+
+use crate::collectors::stats::CycleStats;
+
+/// Callbacks invoked around a collection cycle, see the [module docs](self)
+/// for the order and what is allowed inside them.
+///
+/// Every method has an empty default so observers only implement what they
+/// need.
+pub trait GcObserver {
+    /// A collection cycle is starting.
+    fn on_collection_start(&mut self) {}
+
+    /// The mark phase finished.
+    fn on_mark_end(&mut self) {}
+
+    /// Dead weak map entries and ephemerons were pruned.
+    fn on_weak_pruned(&mut self) {}
+
+    /// The sweep finished, `cycle` holds the statistics of this cycle.
+    fn on_sweep_end(&mut self, _cycle: &CycleStats) {}
+
+    /// The heap threshold changed from `old` to `new` bytes.
+    fn on_threshold_change(&mut self, _old: usize, _new: usize) {}
+}
+
+/// Handle returned when registering a [`GcObserver`], used to remove it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(usize);
+
+// Observers registered on a collector.
+#[derive(Default)]
+pub(crate) struct Observers {
+    list: RefCell<Vec<(ObserverId, Box<dyn GcObserver>)>>,
+    next_id: Cell<usize>,
+}
+
+impl Observers {
+    pub(crate) fn add(&self, observer: Box<dyn GcObserver>) -> ObserverId {
+        let id = ObserverId(self.next_id.get());
+        self.next_id.set(id.0 + 1);
+        self.list.borrow_mut().push((id, observer));
+        id
+    }
+
+    pub(crate) fn remove(&self, id: ObserverId) -> bool {
+        let mut list = self.list.borrow_mut();
+        let len = list.len();
+        list.retain(|(observer_id, _)| *observer_id != id);
+        list.len() != len
+    }
+
+    pub(crate) fn notify(&self, mut f: impl FnMut(&mut dyn GcObserver)) {
+        for (_, observer) in self.list.borrow_mut().iter_mut() {
+            f(observer.as_mut());
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Collection scheduling policies shared by the mark-and-sweep collectors.
+//!
+//! A collector asks its [`GcPolicy`] on every allocation whether it should
+//! collect, and after every cycle what the heap threshold for the next cycle
+//! should be.
+
+use core::cell::{Cell, RefCell};
+use core::time::Duration;
+
+use rust_alloc::boxed::Box;
+
+/// Heap state handed to [`GcPolicy::should_collect`] on every allocation.
+#[derive(Debug, Clone, Copy)]
+pub struct HeapState {
+    /// Bytes of heap pages currently held by the allocator.
+    pub heap_size: usize,
+    /// The current heap threshold.
+    pub threshold: usize,
+    /// Whether the allocator reports the heap as over its threshold,
+    /// including the allocator's own headroom.
+    pub over_threshold: bool,
+    /// Bytes allocated since the last collection.
+    pub allocated_bytes: usize,
+}
+
+/// Summary of a finished collection cycle handed to [`GcPolicy::next_threshold`].
+#[derive(Debug, Clone, Copy)]
+pub struct CycleSummary {
+    /// Bytes of heap pages held before the cycle started.
+    pub heap_size_before: usize,
+    /// Bytes of heap pages still held after the sweep.
+    ///
+    /// This bounds the surviving bytes from above: a page holding a single
+    /// survivor counts in full, and so does the slack of every size class.
+    pub heap_size_after: usize,
+    /// Bytes allocated between the previous cycle and this one.
+    pub allocated_bytes: usize,
+    /// How long the cycle took, `None` if the collector has no clock.
+    pub pause: Option<Duration>,
+    /// The heap threshold that was in effect during the cycle.
+    pub threshold: usize,
+}
+
+/// Decides when a collector runs and how far the heap may grow between cycles.
+pub trait GcPolicy {
+    /// Returns `true` if the collector should run a collection now.
+    ///
+    /// The default defers to the allocator's own threshold check.
+    fn should_collect(&self, heap: &HeapState) -> bool {
+        heap.over_threshold
+    }
+
+    /// Returns the heap threshold to use until the next collection.
+    fn next_threshold(&mut self, cycle: &CycleSummary) -> usize;
+}
+
+/// Keeps the heap threshold fixed.
+///
+/// This is the default policy. [`FixedPolicy::default`] keeps whatever
+/// threshold the allocator was configured with.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FixedPolicy {
+    threshold: Option<usize>,
+}
+
+impl FixedPolicy {
+    /// Pins the threshold to `threshold` bytes after the next collection.
+    pub const fn new(threshold: usize) -> Self {
+        Self {
+            threshold: Some(threshold),
+        }
+    }
+}
+
+impl GcPolicy for FixedPolicy {
+    fn next_threshold(&mut self, cycle: &CycleSummary) -> usize {
+        self.threshold.unwrap_or(cycle.threshold)
+    }
+}
+
+/// GOGC-style policy: the next threshold is the heap held after the cycle
+/// grown by a percentage.
+///
+/// With `percent = 100` the heap may double before the next collection. The
+/// heap is measured in pages, see [`CycleSummary::heap_size_after`], so a
+/// fragmented heap grows its threshold faster than its surviving values alone
+/// would.
+#[derive(Debug, Clone, Copy)]
+pub struct GrowthPolicy {
+    percent: usize,
+    min_threshold: usize,
+}
+
+impl GrowthPolicy {
+    /// Creates a policy growing the held heap by `percent` percent each cycle,
+    /// never going below `min_threshold` bytes.
+    pub const fn new(percent: usize, min_threshold: usize) -> Self {
+        Self {
+            percent,
+            min_threshold,
+        }
+    }
+}
+
+impl Default for GrowthPolicy {
+    fn default() -> Self {
+        Self::new(100, 2_097_152)
+    }
+}
+
+impl GcPolicy for GrowthPolicy {
+    fn next_threshold(&mut self, cycle: &CycleSummary) -> usize {
+        let growth = cycle.heap_size_after.saturating_mul(self.percent) / 100;
+        cycle
+            .heap_size_after
+            .saturating_add(growth)
+            .max(self.min_threshold)
+    }
+}
+
+/// Never collects automatically, only explicit `collect` calls run a cycle.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NeverPolicy;
+
+impl GcPolicy for NeverPolicy {
+    fn should_collect(&self, _heap: &HeapState) -> bool {
+        false
+    }
+
+    fn next_threshold(&mut self, cycle: &CycleSummary) -> usize {
+        cycle.threshold
+    }
+}
+
+// Per-collector scheduling state: the active policy and the allocation
+// volume since the last cycle.
+pub(crate) struct Scheduler {
+    policy: RefCell<Box<dyn GcPolicy>>,
+    allocated_bytes: Cell<usize>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self {
+            policy: RefCell::new(Box::new(FixedPolicy::default())),
+            allocated_bytes: Cell::new(0),
+        }
+    }
+}
+
+impl Scheduler {
+    pub(crate) fn set_policy(&self, policy: Box<dyn GcPolicy>) {
+        *self.policy.borrow_mut() = policy;
+    }
+
+    // record an allocation and ask the policy whether to collect
+    pub(crate) fn on_alloc(
+        &self,
+        bytes: usize,
+        heap_size: usize,
+        threshold: usize,
+        over_threshold: bool,
+    ) -> bool {
+        let allocated_bytes = self.allocated_bytes.get().saturating_add(bytes);
+        self.allocated_bytes.set(allocated_bytes);
+        self.policy.borrow().should_collect(&HeapState {
+            heap_size,
+            threshold,
+            over_threshold,
+            allocated_bytes,
+        })
+    }
+
+    // finish a cycle, returns the threshold for the next one
+    pub(crate) fn on_cycle_end(
+        &self,
+        heap_size_before: usize,
+        heap_size_after: usize,
+        pause: Option<Duration>,
+        threshold: usize,
+    ) -> usize {
+        let summary = CycleSummary {
+            heap_size_before,
+            heap_size_after,
+            allocated_bytes: self.allocated_bytes.replace(0),
+            pause,
+            threshold,
+        };
+        self.policy.borrow_mut().next_threshold(&summary)
+    }
+}
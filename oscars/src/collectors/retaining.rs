@@ -0,0 +1,161 @@
+//! Retaining path queries.
+//!
+//! A [`RetainingPath`] answers "why is this object still alive": it is the
+//! shortest chain of references from a root to the object, as found by
+//! `retaining_path` on the collectors. Paths are discovered with the same
+//! `Trace` implementations the collector marks with, nothing has to be added
+//! to user types.
+#![cfg_attr(
+    not(any(test, feature = "std", feature = "mark_sweep_branded")),
+    allow(dead_code)
+)]
+
+use core::fmt;
+
+use hashbrown::HashMap;
+use rust_alloc::collections::VecDeque;
+use rust_alloc::vec::Vec;
+use rustc_hash::FxBuildHasher;
+
+/// What keeps the first hop of a [`RetainingPath`] alive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathRoot {
+    /// A `Gc` handle living outside the heap.
+    Handle,
+    /// A branded `Root`.
+    Root,
+    /// A value traced by the caller supplied external roots.
+    External,
+}
+
+/// How a hop of a [`RetainingPath`] is reached from the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathEdge {
+    /// The first hop, held by the path root.
+    Root,
+    /// A `Gc` stored in the previous hop.
+    Strong,
+    /// The previous hop is the key of an ephemeron.
+    Ephemeron,
+}
+
+/// One allocation on a [`RetainingPath`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathHop {
+    /// Address of the allocation.
+    pub id: usize,
+    /// Rust type name of the allocation.
+    pub type_name: &'static str,
+    pub edge: PathEdge,
+}
+
+/// The shortest chain of references from a root to an object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetainingPath {
+    root: PathRoot,
+    hops: Vec<PathHop>,
+}
+
+impl RetainingPath {
+    pub fn root(&self) -> PathRoot {
+        self.root
+    }
+
+    /// Hops from the root held allocation to the queried object, both included.
+    pub fn hops(&self) -> &[PathHop] {
+        &self.hops
+    }
+
+    /// The queried object, the last hop.
+    pub fn target(&self) -> &PathHop {
+        self.hops
+            .last()
+            .expect("a retaining path has at least one hop")
+    }
+}
+
+impl fmt::Display for RetainingPath {
+    /// Formats the path as `(Handle) -> A -> B`, ephemeron hops use `~>`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({:?})", self.root)?;
+        for hop in &self.hops {
+            let arrow = match hop.edge {
+                PathEdge::Root | PathEdge::Strong => "->",
+                PathEdge::Ephemeron => "~>",
+            };
+            write!(f, " {arrow} {}", hop.type_name)?;
+        }
+        Ok(())
+    }
+}
+
+// Breadth first search from `roots` to `target` over the graph described by
+// `edges`, which pushes the `(address, edge)` pairs leaving an address.
+// Returns `None` if `target` is not reachable.
+pub(crate) fn shortest_path(
+    roots: impl IntoIterator<Item = (usize, PathRoot)>,
+    target: usize,
+    mut edges: impl FnMut(usize, &mut Vec<(usize, PathEdge)>),
+    mut type_name: impl FnMut(usize) -> &'static str,
+) -> Option<RetainingPath> {
+    // address -> the hop it was first reached from
+    let mut parents: HashMap<usize, Parent, FxBuildHasher> = HashMap::default();
+    let mut queue = VecDeque::new();
+    for (addr, root) in roots {
+        if !parents.contains_key(&addr) {
+            parents.insert(addr, Parent::Root(root));
+            queue.push_back(addr);
+        }
+    }
+
+    let mut found = parents.contains_key(&target);
+    let mut targets = Vec::new();
+    while let Some(addr) = queue.pop_front() {
+        if found {
+            break;
+        }
+        edges(addr, &mut targets);
+        for (to, edge) in targets.drain(..) {
+            if parents.contains_key(&to) {
+                continue;
+            }
+            parents.insert(to, Parent::Hop(addr, edge));
+            queue.push_back(to);
+            found |= to == target;
+        }
+    }
+    if !found {
+        return None;
+    }
+
+    let mut hops = Vec::new();
+    let mut addr = target;
+    let root = loop {
+        match parents[&addr] {
+            Parent::Root(root) => {
+                hops.push(PathHop {
+                    id: addr,
+                    type_name: type_name(addr),
+                    edge: PathEdge::Root,
+                });
+                break root;
+            }
+            Parent::Hop(parent, edge) => {
+                hops.push(PathHop {
+                    id: addr,
+                    type_name: type_name(addr),
+                    edge,
+                });
+                addr = parent;
+            }
+        }
+    };
+    hops.reverse();
+    Some(RetainingPath { root, hops })
+}
+
+#[derive(Clone, Copy)]
+enum Parent {
+    Root(PathRoot),
+    Hop(usize, PathEdge),
+}
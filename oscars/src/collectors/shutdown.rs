@@ -0,0 +1,114 @@
+//! What a collector does with its heap when it is dropped while values are
+//! still rooted.
+//!
+//! A rooted value is held by a `Gc` handle that outlives the collector.
+//! Sweeping the heap would leave that handle dangling, so by default the
+//! collector leaks every remaining value instead. A [`ShutdownPolicy`] picks
+//! another behavior, and a [`LeakReport`] lists the values that were still
+//! rooted, which helps to find handles escaping their context.
+
+use core::fmt;
+
+use rust_alloc::vec::Vec;
+
+/// What a collector does when it is dropped while values are still rooted.
+///
+/// The policy does not matter if nothing is rooted, the heap is then always
+/// finalized and dropped.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ShutdownPolicy {
+    /// Leaks every remaining value without a word.
+    #[default]
+    Leak,
+    /// Finalizes and drops every remaining value anyway.
+    ///
+    /// Handles that outlive the collector dangle and must not be used.
+    Finalize,
+    /// Leaks every remaining value and passes the rooted ones to the function.
+    Report(fn(&LeakReport)),
+    /// Panics with the report in debug builds, leaks like
+    /// [`Leak`](Self::Leak) in release builds.
+    ///
+    /// With `std`, nothing is raised while the thread is already panicking.
+    DebugPanic,
+}
+
+impl ShutdownPolicy {
+    // whether the heap is swept although some values are rooted, `report`
+    // lists them and is only built when the policy needs it
+    pub(crate) fn sweeps_rooted(self, report: impl FnOnce() -> LeakReport) -> bool {
+        match self {
+            Self::Leak => false,
+            Self::Finalize => true,
+            Self::Report(f) => {
+                f(&report());
+                false
+            }
+            Self::DebugPanic => {
+                #[cfg(any(test, feature = "std"))]
+                let unwinding = std::thread::panicking();
+                #[cfg(not(any(test, feature = "std")))]
+                let unwinding = false;
+                if cfg!(debug_assertions) && !unwinding {
+                    panic!("{}", report());
+                }
+                false
+            }
+        }
+    }
+}
+
+/// A value that was still rooted when its collector was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeakedObject {
+    /// Address of the allocation.
+    pub addr: usize,
+    /// Rust type name of the value.
+    #[cfg(any(test, feature = "std"))]
+    pub type_name: &'static str,
+    /// Number of handles outside the heap that root the value.
+    pub roots: u16,
+}
+
+/// The values that were still rooted when a collector was dropped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LeakReport {
+    objects: Vec<LeakedObject>,
+}
+
+impl LeakReport {
+    pub(crate) fn push(&mut self, object: LeakedObject) {
+        self.objects.push(object);
+    }
+
+    /// The rooted values, in allocation queue order.
+    pub fn objects(&self) -> &[LeakedObject] {
+        &self.objects
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "collector dropped with {} rooted values",
+            self.objects.len()
+        )?;
+        for object in &self.objects {
+            #[cfg(any(test, feature = "std"))]
+            write!(f, "\n  {}", object.type_name)?;
+            #[cfg(not(any(test, feature = "std")))]
+            write!(f, "\n  value")?;
+            write!(f, " at {:#x} with {} roots", object.addr, object.roots)?;
+        }
+        Ok(())
+    }
+}
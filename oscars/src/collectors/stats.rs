@@ -0,0 +1,151 @@
+//! Collection statistics shared by the collectors.
+//!
+//! Every collector keeps a [`GcStats`] that can be read with `stats()` and
+//! cleared with `reset_stats()`. Pause times are only measured once a
+//! [`GcClock`] has been installed, which keeps the collectors usable in
+//! `no_std` environments without a time source.
+
+use core::cell::{Cell, RefCell};
+use core::time::Duration;
+
+use rust_alloc::boxed::Box;
+
+/// A monotonic time source used to measure collection pauses.
+pub trait GcClock {
+    /// Returns the time elapsed since an arbitrary, fixed origin.
+    fn now(&self) -> Duration;
+}
+
+/// A [`GcClock`] backed by [`std::time::Instant`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct StdClock {
+    origin: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+    fn default() -> Self {
+        Self {
+            origin: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl GcClock for StdClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// Statistics of a single collection cycle.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CycleStats {
+    /// Number of `Gc` objects freed.
+    pub objects_freed: usize,
+    /// Bytes of `Gc` objects freed.
+    pub bytes_freed: usize,
+    /// Number of `Gc` objects that survived the cycle.
+    pub survivors: usize,
+    /// Number of ephemerons (weak references and weak map entries) freed.
+    pub ephemerons_pruned: usize,
+    /// Number of entries removed from weak maps because their key died.
+    pub weak_map_entries_pruned: usize,
+    /// How long the cycle took, zero if no clock is installed.
+    pub pause: Duration,
+}
+
+/// Cumulative collector statistics.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GcStats {
+    /// Number of collection cycles run.
+    pub collections: u64,
+    /// Sum of all pause times, zero if no clock is installed.
+    pub total_pause: Duration,
+    /// Total number of `Gc` objects freed.
+    pub objects_freed: u64,
+    /// Total bytes of `Gc` objects freed.
+    pub bytes_freed: u64,
+    /// Largest heap size observed, in bytes.
+    pub peak_heap_size: usize,
+    /// Statistics of the most recent cycle.
+    pub last_cycle: CycleStats,
+}
+
+impl GcStats {
+    /// Pause time of the most recent cycle.
+    pub fn last_pause(&self) -> Duration {
+        self.last_cycle.pause
+    }
+}
+
+// Per-collector statistics state: the running totals, the counters of the
+// cycle in progress and the optional clock.
+#[derive(Default)]
+pub(crate) struct StatsRecorder {
+    stats: Cell<GcStats>,
+    cycle: Cell<CycleStats>,
+    clock: RefCell<Option<Box<dyn GcClock>>>,
+}
+
+impl StatsRecorder {
+    pub(crate) fn set_clock(&self, clock: Box<dyn GcClock>) {
+        *self.clock.borrow_mut() = Some(clock);
+    }
+
+    pub(crate) fn stats(&self) -> GcStats {
+        self.stats.get()
+    }
+
+    // clear all counters, the peak restarts from the current heap size
+    pub(crate) fn reset(&self, heap_size: usize) {
+        self.stats.set(GcStats {
+            peak_heap_size: heap_size,
+            ..GcStats::default()
+        });
+    }
+
+    pub(crate) fn observe_heap(&self, heap_size: usize) {
+        let mut stats = self.stats.get();
+        if heap_size > stats.peak_heap_size {
+            stats.peak_heap_size = heap_size;
+            self.stats.set(stats);
+        }
+    }
+
+    // start a cycle, returns the start time if a clock is installed
+    pub(crate) fn begin_cycle(&self) -> Option<Duration> {
+        self.cycle.set(CycleStats::default());
+        self.clock.borrow().as_ref().map(|clock| clock.now())
+    }
+
+    pub(crate) fn update_cycle(&self, f: impl FnOnce(&mut CycleStats)) {
+        let mut cycle = self.cycle.get();
+        f(&mut cycle);
+        self.cycle.set(cycle);
+    }
+
+    // finish a cycle, returns its pause if a clock is installed
+    pub(crate) fn end_cycle(&self, started: Option<Duration>) -> Option<Duration> {
+        let pause = started.and_then(|start| {
+            let clock = self.clock.borrow();
+            clock
+                .as_ref()
+                .map(|clock| clock.now().saturating_sub(start))
+        });
+
+        let mut cycle = self.cycle.take();
+        cycle.pause = pause.unwrap_or_default();
+
+        let mut stats = self.stats.get();
+        stats.collections += 1;
+        stats.total_pause = stats.total_pause.saturating_add(cycle.pause);
+        stats.objects_freed += cycle.objects_freed as u64;
+        stats.bytes_freed += cycle.bytes_freed as u64;
+        stats.last_cycle = cycle;
+        self.stats.set(stats);
+
+        pause
+    }
+}
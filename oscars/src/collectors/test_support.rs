@@ -0,0 +1,76 @@
+//! Test scenarios shared by the collectors that expose the same handle API.
+
+// `key1 -> key2 -> payload` across two weak maps, where the only handle to
+// `key2` is the value of the `key1` entry and the only handle to the payload
+// is the value of the `key2` entry. The `key2` entry sits earlier in the
+// ephemeron queue than the `key1` entry that keeps `key2` alive.
+//
+// Expands in a test module that has `Gc`, `WeakMap`, `Finalize` and `Trace`
+// of the collector in scope, `$pools_len` names the allocator method that
+// counts its pools.
+macro_rules! chained_weak_map_fixpoint {
+    ($collector:expr, $pools_len:ident) => {{
+        use core::cell::Cell;
+        use rust_alloc::rc::Rc;
+
+        struct DropSpy {
+            drops: Rc<Cell<u32>>,
+        }
+
+        impl Drop for DropSpy {
+            fn drop(&mut self) {
+                self.drops.set(self.drops.get() + 1);
+            }
+        }
+
+        impl Finalize for DropSpy {}
+
+        // SAFETY: `DropSpy` has no traceable children.
+        unsafe impl Trace for DropSpy {
+            crate::empty_trace!();
+        }
+
+        let collector = $collector;
+        let drops = Rc::new(Cell::new(0));
+        let mut inner_map = WeakMap::new(collector);
+        let mut outer_map = WeakMap::new(collector);
+
+        let key1 = Gc::new_in(1u64, collector);
+        let key2 = Gc::new_in(2u64, collector);
+        let payload = Gc::new_in(
+            DropSpy {
+                drops: Rc::clone(&drops),
+            },
+            collector,
+        );
+        inner_map.insert(&key2, payload, collector);
+        outer_map.insert(&key1, key2, collector);
+
+        collector.collect();
+
+        assert_eq!(
+            drops.get(),
+            0,
+            "chained ephemeron value swept while key live"
+        );
+        let key2 = outer_map.get(&key1).expect("key1 entry pruned");
+        assert_eq!(**key2, 2);
+        assert!(inner_map.is_key_alive(key2), "key2 entry pruned");
+
+        // each collection frees one link of the chain, its sweep drops the
+        // handle to the next one
+        drop(key1);
+        for _ in 0..3 {
+            collector.collect();
+        }
+
+        assert_eq!(drops.get(), 1, "payload not swept after chain head died");
+        assert_eq!(
+            collector.allocator.borrow().$pools_len(),
+            0,
+            "chained ephemerons leaked"
+        );
+    }};
+}
+
+pub(crate) use chained_weak_map_fixpoint;
@@ -0,0 +1,48 @@
+//! Unwind safety for user code run by a collection.
+//!
+//! Collectors run `Finalize` and `Drop` implementations while their queues
+//! are taken apart. With `std`, every such call goes through
+//! [`PanicSink::call`], which catches a panic so that the remaining objects
+//! are still swept and the queues stay coherent. The first panic is re-raised
+//! by [`PanicSink::resume`] once the collector is consistent again, later
+//! ones are discarded. Without `std` panics are not caught.
+
+#[cfg(any(test, feature = "std"))]
+use core::cell::RefCell;
+#[cfg(any(test, feature = "std"))]
+use rust_alloc::boxed::Box;
+
+#[derive(Default)]
+pub(crate) struct PanicSink {
+    #[cfg(any(test, feature = "std"))]
+    first: RefCell<Option<Box<dyn core::any::Any + Send>>>,
+}
+
+impl PanicSink {
+    /// Runs `f`, recording a panic instead of unwinding through the caller.
+    #[inline]
+    pub(crate) fn call(&self, f: impl FnOnce()) {
+        #[cfg(any(test, feature = "std"))]
+        if let Err(payload) = std::panic::catch_unwind(core::panic::AssertUnwindSafe(f)) {
+            self.first.borrow_mut().get_or_insert(payload);
+        }
+        #[cfg(not(any(test, feature = "std")))]
+        f();
+    }
+
+    /// Re-raises the first recorded panic, if any.
+    ///
+    /// The panic is discarded if the thread is already unwinding, raising it
+    /// there would abort.
+    pub(crate) fn resume(&self) {
+        #[cfg(any(test, feature = "std"))]
+        {
+            let payload = self.first.borrow_mut().take();
+            if let Some(payload) = payload
+                && !std::thread::panicking()
+            {
+                std::panic::resume_unwind(payload);
+            }
+        }
+    }
+}
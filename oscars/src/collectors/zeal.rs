@@ -0,0 +1,130 @@
+//! GC stress mode.
+//!
+//! Many rooting bugs only show up when a collection happens at exactly the
+//! wrong allocation. With a [`Zeal`] installed, a collector collects on every
+//! `n`th allocation instead of waiting for its policy, optionally at seeded
+//! random intervals and optionally poisoning freed memory, so a test suite hits
+//! those allocations deterministically.
+
+use core::cell::Cell;
+
+/// Byte written over freed allocations when poisoning is enabled.
+pub const POISON_BYTE: u8 = 0xDB;
+
+/// Stress mode settings, installed with `with_zeal` or `set_zeal` on a collector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Zeal {
+    interval: usize,
+    seed: Option<u64>,
+    poison: bool,
+}
+
+impl Zeal {
+    /// Collects on every `n`th allocation, `1` collects on every allocation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn every(n: usize) -> Self {
+        assert!(n > 0, "zeal interval must be at least 1");
+        Self {
+            interval: n,
+            seed: None,
+            poison: false,
+        }
+    }
+
+    /// Draws every interval at random from `1..=n` instead, the same seed
+    /// always yields the same collection points.
+    pub fn randomized(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Overwrites freed values with [`POISON_BYTE`], so a use after free reads
+    /// garbage instead of the stale value.
+    pub fn with_poison(mut self) -> Self {
+        self.poison = true;
+        self
+    }
+
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn poisons(&self) -> bool {
+        self.poison
+    }
+}
+
+// Per-collector zeal state: the settings and the allocations left until the
+// next forced collection.
+#[derive(Default)]
+pub(crate) struct ZealState {
+    zeal: Cell<Option<Zeal>>,
+    countdown: Cell<usize>,
+    rng: Cell<u64>,
+}
+
+impl ZealState {
+    pub(crate) fn set(&self, zeal: Option<Zeal>) {
+        self.zeal.set(zeal);
+        if let Some(zeal) = zeal {
+            // xorshift gets stuck on a zero state
+            self.rng.set(
+                zeal.seed
+                    .map_or(1, |seed| seed ^ 0x9E37_79B9_7F4A_7C15)
+                    .max(1),
+            );
+            self.countdown.set(self.next_interval(zeal));
+        }
+    }
+
+    pub(crate) fn get(&self) -> Option<Zeal> {
+        self.zeal.get()
+    }
+
+    // counts an allocation, returns true if a collection is due before it
+    pub(crate) fn tick(&self) -> bool {
+        let Some(zeal) = self.zeal.get() else {
+            return false;
+        };
+        let left = self.countdown.get() - 1;
+        if left == 0 {
+            self.countdown.set(self.next_interval(zeal));
+            true
+        } else {
+            self.countdown.set(left);
+            false
+        }
+    }
+
+    pub(crate) fn poisons(&self) -> bool {
+        self.zeal.get().is_some_and(|zeal| zeal.poison)
+    }
+
+    fn next_interval(&self, zeal: Zeal) -> usize {
+        if zeal.seed.is_none() {
+            return zeal.interval;
+        }
+        let mut x = self.rng.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng.set(x);
+        1 + (x % zeal.interval as u64) as usize
+    }
+}
+
+/// Fills `len` bytes at `ptr` with [`POISON_BYTE`].
+///
+/// # Safety
+///
+/// `ptr` must be valid for writes of `len` bytes and hold no live value.
+pub(crate) unsafe fn poison(ptr: *mut u8, len: usize) {
+    unsafe { core::ptr::write_bytes(ptr, POISON_BYTE, len) };
+}
@@ -30,5 +30,8 @@ pub mod null_collector {
     pub use crate::collectors::null_collector::*;
 }
 
+#[cfg(feature = "boa_gc_compat")]
+pub mod boa_gc_compat;
+
 pub mod alloc;
 pub mod collectors;
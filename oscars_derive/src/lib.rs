@@ -141,7 +141,46 @@ decl_derive! {
 }
 
 /// Derives the `Finalize` trait.
-#[allow(clippy::needless_pass_by_value)]
-fn derive_finalize(s: Structure<'_>) -> proc_macro2::TokenStream {
-    s.unbound_impl(quote!(::oscars::mark_sweep::Finalize), quote!())
+///
+/// The derived `finalize` does nothing, so the value needs finalization only
+/// if one of the fields its `Trace::run_finalizer` visits does.
+fn derive_finalize(mut s: Structure<'_>) -> proc_macro2::TokenStream {
+    // an empty trace never runs the finalizers of the fields
+    let empty_trace = s.ast().attrs.iter().any(|attr| {
+        attr.path().is_ident("oscars")
+            && attr
+                .parse_args::<Ident>()
+                .is_ok_and(|i| i == "empty_trace" || i == "unsafe_empty_trace")
+    });
+    let needs_finalize_body = if empty_trace {
+        quote!(false)
+    } else {
+        s.filter(|bi| {
+            !bi.ast()
+                .attrs
+                .iter()
+                .any(|attr| attr.path().is_ident("unsafe_ignore_trace"))
+        });
+        // fields of a type without a `Finalize` impl, e.g. a generic one,
+        // fall back to `Unknown` and answer `true`
+        let fields = s.fold(quote!(false), |acc, bi| {
+            quote! {
+                #acc || {
+                    use ::oscars::mark_sweep::__needs_finalize::{Known as _, Unknown as _};
+                    (&::oscars::mark_sweep::__needs_finalize::Field(#bi)).needs_finalize()
+                }
+            }
+        });
+        quote!(match *self { #fields })
+    };
+
+    s.unbound_impl(
+        quote!(::oscars::mark_sweep::Finalize),
+        quote! {
+            #[inline]
+            fn needs_finalize(&self) -> bool {
+                #needs_finalize_body
+            }
+        },
+    )
 }